@@ -0,0 +1,119 @@
+// Copyright 2021-2022 Farcaster Devs
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; either
+// version 3 of the License, or (at your option) any later version.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301, USA
+
+//! Derive macros for the Farcaster core library. Use the re-exports available in
+//! `farcaster_core::consensus` instead of depending on this crate directly.
+
+#![forbid(unsafe_code)]
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, parse_quote, Data, DeriveInput, Error, Fields, GenericParam, Index};
+
+/// Derive `CanonicalBytes` on a struct. The canonical bytes of the struct are the concatenation,
+/// in declaration order, of each field canonical bytes serialized as length prefixed vectors of
+/// bytes, exactly like a hand-written consensus encoding of the fields would produce. Decoding
+/// fails if the buffer is not entirely consumed.
+#[proc_macro_derive(CanonicalBytes)]
+pub fn derive_canonical_bytes(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+fn expand(mut input: DeriveInput) -> Result<TokenStream2, Error> {
+    let fields = match &input.data {
+        Data::Struct(data) => &data.fields,
+        _ => {
+            return Err(Error::new(
+                Span::call_site(),
+                "CanonicalBytes can only be derived on structs",
+            ))
+        }
+    };
+
+    let accessors: Vec<TokenStream2> = match fields {
+        Fields::Named(fields) => fields
+            .named
+            .iter()
+            .map(|f| {
+                let ident = f.ident.as_ref().expect("named field");
+                quote!(#ident)
+            })
+            .collect(),
+        Fields::Unnamed(fields) => (0..fields.unnamed.len())
+            .map(|i| {
+                let index = Index::from(i);
+                quote!(#index)
+            })
+            .collect(),
+        Fields::Unit => vec![],
+    };
+    let vars: Vec<_> = (0..accessors.len())
+        .map(|i| format_ident!("field_{}", i))
+        .collect();
+    let construct = match fields {
+        Fields::Named(_) => quote!(Self { #(#accessors: #vars),* }),
+        Fields::Unnamed(_) => quote!(Self(#(#vars),*)),
+        Fields::Unit => quote!(Self),
+    };
+
+    for param in input.generics.params.iter_mut() {
+        if let GenericParam::Type(ty) = param {
+            ty.bounds
+                .push(parse_quote!(::farcaster_core::consensus::CanonicalBytes));
+        }
+    }
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::farcaster_core::consensus::CanonicalBytes for #name #ty_generics #where_clause {
+            fn as_canonical_bytes(&self) -> ::std::vec::Vec<u8> {
+                let mut bytes = ::std::vec::Vec::new();
+                #(
+                    ::farcaster_core::consensus::Encodable::consensus_encode(
+                        &::farcaster_core::consensus::CanonicalBytes::as_canonical_bytes(&self.#accessors),
+                        &mut bytes,
+                    )
+                    .expect("Writing in a vector never fails");
+                )*
+                bytes
+            }
+
+            fn from_canonical_bytes(
+                bytes: &[u8],
+            ) -> ::std::result::Result<Self, ::farcaster_core::consensus::Error>
+            where
+                Self: Sized,
+            {
+                let mut reader = ::std::io::Cursor::new(bytes);
+                #(
+                    let #vars = ::farcaster_core::consensus::CanonicalBytes::from_canonical_bytes(
+                        <::std::vec::Vec<u8> as ::farcaster_core::consensus::Decodable>::consensus_decode(
+                            &mut reader,
+                        )?
+                        .as_ref(),
+                    )?;
+                )*
+                ::farcaster_core::consensus::check_length(bytes, reader.position() as usize)?;
+                Ok(#construct)
+            }
+        }
+    })
+}
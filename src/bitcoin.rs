@@ -21,7 +21,9 @@ use std::fmt::Debug;
 use std::io;
 use std::marker::PhantomData;
 
+use bitcoin::secp256k1::SecretKey;
 use ecdsa_fun::adaptor::EncryptedSignature;
+use rand_alt::{CryptoRng, RngCore};
 
 use crate::blockchain::Network;
 use crate::consensus::{self, CanonicalBytes, Decodable, Encodable};
@@ -95,6 +97,18 @@ pub struct Bitcoin<S: Strategy> {
     _e: PhantomData<S>,
 }
 
+// Draw random 32 bytes until they form a valid secp256k1 secret key, i.e. a non-zero scalar lower
+// than the curve order.
+pub(crate) fn random_secret_key<R: RngCore + CryptoRng>(rng: &mut R) -> SecretKey {
+    loop {
+        let mut bytes = [0u8; 32];
+        rng.fill_bytes(&mut bytes);
+        if let Ok(secret_key) = SecretKey::from_slice(&bytes) {
+            return secret_key;
+        }
+    }
+}
+
 impl<S: Strategy> Bitcoin<S> {
     /// Create a new Bitcoin for the defined strategy.
     pub fn new() -> Self {
@@ -14,6 +14,7 @@
 // License along with this library; if not, write to the Free Software
 // Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301, USA
 
+use crate::blockchain::{Network, Networked};
 use crate::consensus::{self, CanonicalBytes};
 use bitcoin::Address;
 
@@ -32,3 +33,9 @@ impl CanonicalBytes for Address {
             .map_err(consensus::Error::new)
     }
 }
+
+impl Networked for Address {
+    fn network(&self) -> Network {
+        self.network.into()
+    }
+}
@@ -14,6 +14,7 @@
 // License along with this library; if not, write to the Free Software
 // Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301, USA
 
+use crate::blockchain::{AssetUnit, Blockchain};
 use crate::consensus::{self, CanonicalBytes};
 use bitcoin::Amount;
 
@@ -31,3 +32,15 @@ impl CanonicalBytes for Amount {
         ))
     }
 }
+
+impl AssetUnit for Amount {
+    const BLOCKCHAIN: Blockchain = Blockchain::Bitcoin;
+
+    fn as_atomic_units(&self) -> u64 {
+        self.as_sat()
+    }
+
+    fn from_atomic_units(units: u64) -> Self {
+        Amount::from_sat(units)
+    }
+}
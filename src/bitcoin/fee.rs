@@ -39,12 +39,16 @@ use bitcoin::util::psbt::PartiallySignedTransaction;
 use bitcoin::Amount;
 
 use crate::bitcoin::transaction;
-use crate::blockchain::{Fee, FeePriority, FeeStrategy, FeeStrategyError};
+use crate::blockchain::{CheckedAdd, Fee, FeePriority, FeeStrategy, FeeStrategyError};
 use crate::consensus::{self, CanonicalBytes};
+use crate::transaction::Error as FError;
 
+use std::ops::Add;
 use std::str::FromStr;
 
+#[cfg(feature = "serde")]
 use serde::ser::{Serialize, Serializer};
+#[cfg(feature = "serde")]
 use serde::{de, Deserialize, Deserializer};
 
 /// An amount of Bitcoin (internally in satoshis) representing the number of satoshis per virtual
@@ -63,6 +67,10 @@ fn display_sats_per_vbyte(rate: &SatPerVByte) -> String {
 }
 
 impl SatPerVByte {
+    /// Highest fee rate accepted when decoding, in satoshis per virtual byte. Same as the default
+    /// maximum fee rate of Bitcoin Core (0.1 BTC/kvB).
+    pub const MAX_RATE: u64 = 10_000;
+
     /// Create a fee quantity per virtual byte of given satoshis.
     pub fn from_sat(satoshis: u64) -> Self {
         SatPerVByte(Amount::from_sat(satoshis))
@@ -83,8 +91,40 @@ impl SatPerVByte {
     pub fn as_native_unit(&self) -> Amount {
         self.0
     }
+
+    /// Compute the absolute fee for a transaction of `vsize` virtual bytes at this rate. Returns
+    /// `None` if the computation overflows.
+    pub fn checked_mul_vsize(&self, vsize: u64) -> Option<Amount> {
+        self.0.checked_mul(vsize)
+    }
+
+    // Reject zero rates, which can never confirm, and rates above the sane maximum.
+    fn validate(self) -> Result<Self, consensus::Error> {
+        match self.as_sat() {
+            0 => Err(consensus::Error::ParseFailed("Fee rate cannot be zero")),
+            rate if rate > Self::MAX_RATE => {
+                Err(consensus::Error::ParseFailed("Fee rate is too high"))
+            }
+            _ => Ok(self),
+        }
+    }
+}
+
+impl Add for SatPerVByte {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        SatPerVByte(self.0 + rhs.0)
+    }
 }
 
+impl CheckedAdd for SatPerVByte {
+    fn checked_add(&self, rhs: &Self) -> Option<Self> {
+        self.0.checked_add(rhs.0).map(SatPerVByte)
+    }
+}
+
+#[cfg(feature = "serde")]
 impl Serialize for SatPerVByte {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -94,6 +134,7 @@ impl Serialize for SatPerVByte {
     }
 }
 
+#[cfg(feature = "serde")]
 impl<'de> Deserialize<'de> for SatPerVByte {
     fn deserialize<D>(deserializer: D) -> Result<SatPerVByte, D::Error>
     where
@@ -112,9 +153,10 @@ impl CanonicalBytes for SatPerVByte {
     where
         Self: Sized,
     {
-        Ok(SatPerVByte(Amount::from_sat(
+        SatPerVByte(Amount::from_sat(
             bitcoin::consensus::encode::deserialize(bytes).map_err(consensus::Error::new)?,
-        )))
+        ))
+        .validate()
     }
 }
 
@@ -130,7 +172,7 @@ impl FromStr for SatPerVByte {
         }
         let amount = parts[0].parse::<Amount>().map_err(consensus::Error::new)?;
         match parts[1] {
-            "vByte" => Ok(Self(amount)),
+            "vByte" => Self(amount).validate(),
             _ => Err(consensus::Error::ParseFailed("SatPerVByte parse failed")),
         }
     }
@@ -153,6 +195,22 @@ fn get_available_input_sat(tx: &PartiallySignedTransaction) -> Result<Amount, Fe
     ))
 }
 
+// Returns whether the transaction has an anchor output, only transactions with one output followed
+// by an optional metadata output and an optional anchor output are supported.
+fn has_anchor_output(tx: &PartiallySignedTransaction) -> Result<bool, FeeStrategyError> {
+    match tx
+        .unsigned_tx
+        .output
+        .split_first()
+        .and_then(|(_, extra)| transaction::split_extra_outputs(extra))
+    {
+        Some((_, anchor)) => Ok(anchor.is_some()),
+        None => Err(FeeStrategyError::new(
+            transaction::Error::MultiUTXOUnsuported,
+        )),
+    }
+}
+
 impl Fee for PartiallySignedTransaction {
     type FeeUnit = SatPerVByte;
 
@@ -164,13 +222,12 @@ impl Fee for PartiallySignedTransaction {
         strategy: &FeeStrategy<SatPerVByte>,
         politic: FeePriority,
     ) -> Result<Self::Amount, FeeStrategyError> {
-        if self.unsigned_tx.output.len() != 1 {
-            return Err(FeeStrategyError::new(
-                transaction::Error::MultiUTXOUnsuported,
-            ));
-        }
-
+        let has_anchor = has_anchor_output(self)?;
         let input_sum = get_available_input_sat(self)?;
+        let anchor_amount = match has_anchor {
+            true => Amount::from_sat(transaction::ANCHOR_AMOUNT),
+            false => Amount::ZERO,
+        };
 
         // FIXME This does not account for witnesses
         // currently the fees are wrong
@@ -189,14 +246,21 @@ impl Fee for PartiallySignedTransaction {
                 FeePriority::Low => min_inc.as_native_unit().checked_mul(weight),
                 FeePriority::High => max_inc.as_native_unit().checked_mul(weight),
             },
+            // The fee is paid by a child transaction spending the anchor output
+            FeeStrategy::None => match has_anchor {
+                true => Some(Amount::ZERO),
+                false => return Err(FeeStrategyError::MissingAnchorOutput),
+            },
         }
         .ok_or(FeeStrategyError::AmountOfFeeTooHigh)?;
 
-        // Apply the fee on the first output
-        self.unsigned_tx.output[0].value = input_sum
-            .checked_sub(fee_amount)
-            .ok_or(FeeStrategyError::NotEnoughAssets)?
-            .as_sat();
+        // Apply the fee on the first output, the remaining amount must not be dust
+        let output_amount = input_sum
+            .checked_sub(anchor_amount)
+            .and_then(|amount| amount.checked_sub(fee_amount))
+            .filter(|amount| amount >= &self.dust_limit())
+            .ok_or(FeeStrategyError::NotEnoughAssets)?;
+        self.unsigned_tx.output[0].value = output_amount.as_sat();
 
         // Return the fee amount set in native blockchain asset unit
         Ok(fee_amount)
@@ -204,33 +268,229 @@ impl Fee for PartiallySignedTransaction {
 
     /// Validates that the fees for the given transaction are set accordingly to the strategy
     fn validate_fee(&self, strategy: &FeeStrategy<SatPerVByte>) -> Result<bool, FeeStrategyError> {
-        if self.unsigned_tx.output.len() != 1 {
-            return Err(FeeStrategyError::new(
-                transaction::Error::MultiUTXOUnsuported,
-            ));
+        match check_rate(self, strategy) {
+            Ok(()) => Ok(true),
+            Err(FeeStrategyError::AmountOfFeeTooLow)
+            | Err(FeeStrategyError::AmountOfFeeTooHigh) => Ok(false),
+            Err(e) => Err(e),
         }
+    }
 
-        let input_sum = get_available_input_sat(self)?.as_sat();
-        let output_sum = self.unsigned_tx.output[0].value;
-        let fee = input_sum
-            .checked_sub(output_sum)
-            .ok_or(FeeStrategyError::AmountOfFeeTooHigh)?;
-        let weight = self.unsigned_tx.weight() as u64;
+    /// Validates that the effective fee rate of the given transaction is within the strategy
+    /// bounds. A zero fee is only valid with the none strategy on a transaction with an anchor
+    /// output.
+    fn validate_rate(&self, strategy: &FeeStrategy<SatPerVByte>) -> Result<(), FError> {
+        check_rate(self, strategy).map_err(|e| match e {
+            FeeStrategyError::AmountOfFeeTooLow | FeeStrategyError::AmountOfFeeTooHigh => {
+                FError::FeeOutOfBounds
+            }
+            e => FError::new(e),
+        })
+    }
 
-        let effective_sat_per_vbyte = SatPerVByte::from_sat(
-            weight
-                .checked_div(fee)
-                .ok_or(FeeStrategyError::AmountOfFeeTooLow)?,
-        );
+    /// Returns the highest dust limit among the transaction outputs based on their script type,
+    /// as computed by Bitcoin Core with the default dust relay fee
+    fn dust_limit(&self) -> Amount {
+        self.unsigned_tx
+            .output
+            .iter()
+            .map(|output| output.script_pubkey.dust_value())
+            .max()
+            .unwrap_or(Amount::ZERO)
+    }
+}
 
-        Ok(strategy.check(&effective_sat_per_vbyte))
+// Computes the fee paid by the transaction, the anchor output, if any, is not part of the fee.
+fn get_fee(tx: &PartiallySignedTransaction) -> Result<Amount, FeeStrategyError> {
+    has_anchor_output(tx)?;
+    let input_sum = get_available_input_sat(tx)?.as_sat();
+    let output_sum = tx
+        .unsigned_tx
+        .output
+        .iter()
+        .try_fold(0u64, |sum, output| sum.checked_add(output.value))
+        .ok_or(FeeStrategyError::NotEnoughAssets)?;
+    input_sum
+        .checked_sub(output_sum)
+        .map(Amount::from_sat)
+        .ok_or(FeeStrategyError::NotEnoughAssets)
+}
+
+// Checks the effective fee rate of the transaction against the strategy bounds, fails with
+// `AmountOfFeeTooLow` or `AmountOfFeeTooHigh` when out of bounds.
+fn check_rate(
+    tx: &PartiallySignedTransaction,
+    strategy: &FeeStrategy<SatPerVByte>,
+) -> Result<(), FeeStrategyError> {
+    let has_anchor = has_anchor_output(tx)?;
+    let fee = get_fee(tx)?;
+    let (min, max) = match strategy {
+        FeeStrategy::Fixed(sat_per_vbyte) => (sat_per_vbyte, sat_per_vbyte),
+        FeeStrategy::Range { min_inc, max_inc } => (min_inc, max_inc),
+        FeeStrategy::None if !has_anchor => return Err(FeeStrategyError::MissingAnchorOutput),
+        FeeStrategy::None if fee == Amount::ZERO => return Ok(()),
+        FeeStrategy::None => return Err(FeeStrategyError::AmountOfFeeTooHigh),
+    };
+    if fee == Amount::ZERO {
+        return Err(FeeStrategyError::AmountOfFeeTooLow);
+    }
+    let rate = get_effective_fee_rate(tx)?;
+    if &rate < min {
+        Err(FeeStrategyError::AmountOfFeeTooLow)
+    } else if &rate > max {
+        Err(FeeStrategyError::AmountOfFeeTooHigh)
+    } else {
+        Ok(())
     }
 }
 
+// Computes the fee rate paid by the transaction, the rate is computed on the transaction weight to
+// match the computation done when setting the fee.
+fn get_effective_fee_rate(
+    tx: &PartiallySignedTransaction,
+) -> Result<SatPerVByte, FeeStrategyError> {
+    let fee = get_fee(tx)?.as_sat();
+    let weight = tx.unsigned_tx.weight() as u64;
+
+    Ok(SatPerVByte::from_sat(
+        fee.checked_div(weight)
+            .ok_or(FeeStrategyError::AmountOfFeeTooLow)?,
+    ))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    use bitcoin::blockdata::transaction::{OutPoint, TxIn};
+    use bitcoin::{Script, Witness};
+
+    fn psbt_with_input(value: u64) -> PartiallySignedTransaction {
+        let tx = bitcoin::Transaction {
+            version: 2,
+            lock_time: 0,
+            input: vec![TxIn {
+                previous_output: OutPoint::null(),
+                script_sig: Script::default(),
+                sequence: 0,
+                witness: Witness::new(),
+            }],
+            output: vec![TxOut {
+                value: 0,
+                script_pubkey: Script::default(),
+            }],
+        };
+        let mut psbt = PartiallySignedTransaction::from_unsigned_tx(tx).unwrap();
+        psbt.inputs[0].witness_utxo = Some(TxOut {
+            value,
+            script_pubkey: Script::default(),
+        });
+        psbt
+    }
+
+    #[test]
+    fn validate_exact_fixed_rate() {
+        let strategy = FeeStrategy::Fixed(SatPerVByte::from_sat(20));
+        let mut psbt = psbt_with_input(100_000);
+        psbt.set_fee(&strategy, FeePriority::Low).unwrap();
+        assert!(psbt.validate_rate(&strategy).is_ok());
+        assert!(psbt.validate_fee(&strategy).unwrap());
+    }
+
+    #[test]
+    fn validate_underpaying_rate() {
+        let strategy = FeeStrategy::Fixed(SatPerVByte::from_sat(20));
+        let mut psbt = psbt_with_input(100_000);
+        psbt.set_fee(
+            &FeeStrategy::Fixed(SatPerVByte::from_sat(10)),
+            FeePriority::Low,
+        )
+        .unwrap();
+        assert!(matches!(
+            psbt.validate_rate(&strategy),
+            Err(FError::FeeOutOfBounds)
+        ));
+        assert!(!psbt.validate_fee(&strategy).unwrap());
+        // Within a range including the lower rate
+        let range = FeeStrategy::Range {
+            min_inc: SatPerVByte::from_sat(5),
+            max_inc: SatPerVByte::from_sat(10),
+        };
+        assert!(psbt.validate_rate(&range).is_ok());
+        // Overpaying
+        let range = FeeStrategy::Range {
+            min_inc: SatPerVByte::from_sat(1),
+            max_inc: SatPerVByte::from_sat(5),
+        };
+        assert!(matches!(
+            psbt.validate_rate(&range),
+            Err(FError::FeeOutOfBounds)
+        ));
+    }
+
+    #[test]
+    fn reject_fee_leaving_dust_output() {
+        let psbt = psbt_with_input(100_000);
+        let dust_limit = psbt.dust_limit().as_sat();
+        let weight = psbt.unsigned_tx.weight() as u64;
+        // Tiny swap amount barely above the fee, the remaining output is dust
+        let mut psbt = psbt_with_input(100 * weight + dust_limit - 1);
+        let strategy = FeeStrategy::Fixed(SatPerVByte::from_sat(100));
+        assert!(matches!(
+            psbt.set_fee(&strategy, FeePriority::Low),
+            Err(FeeStrategyError::NotEnoughAssets)
+        ));
+        let mut psbt = psbt_with_input(100 * weight + dust_limit);
+        assert!(psbt.set_fee(&strategy, FeePriority::Low).is_ok());
+        assert_eq!(psbt.unsigned_tx.output[0].value, dust_limit);
+    }
+
+    #[test]
+    fn zero_fee_only_with_none_strategy() {
+        let mut psbt = psbt_with_input(100_000);
+        // The none strategy requires an anchor output
+        assert!(matches!(
+            psbt.set_fee(&FeeStrategy::None, FeePriority::Low),
+            Err(FeeStrategyError::MissingAnchorOutput)
+        ));
+
+        psbt.unsigned_tx.output.push(TxOut {
+            value: transaction::ANCHOR_AMOUNT,
+            script_pubkey: transaction::anchor_script_pubkey(),
+        });
+        psbt.outputs.push(Default::default());
+        assert_eq!(
+            psbt.set_fee(&FeeStrategy::None, FeePriority::Low).unwrap(),
+            Amount::ZERO
+        );
+        assert_eq!(
+            psbt.unsigned_tx.output[0].value,
+            100_000 - transaction::ANCHOR_AMOUNT
+        );
+        assert!(psbt.validate_rate(&FeeStrategy::None).is_ok());
+        assert!(psbt.validate_fee(&FeeStrategy::None).unwrap());
+
+        // A zero fee is rejected by fixed strategies, even with a zero rate
+        for rate in [0, 1] {
+            let strategy = FeeStrategy::Fixed(SatPerVByte::from_sat(rate));
+            assert!(matches!(
+                psbt.validate_rate(&strategy),
+                Err(FError::FeeOutOfBounds)
+            ));
+            assert!(!psbt.validate_fee(&strategy).unwrap());
+        }
+
+        // Fees paid on a transaction are rejected by the none strategy
+        let strategy = FeeStrategy::Fixed(SatPerVByte::from_sat(1));
+        psbt.set_fee(&strategy, FeePriority::Low).unwrap();
+        assert!(psbt.validate_rate(&strategy).is_ok());
+        assert!(matches!(
+            psbt.validate_rate(&FeeStrategy::None),
+            Err(FError::FeeOutOfBounds)
+        ));
+    }
+
+    #[cfg(feature = "serde")]
     #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
     struct SerdeTest {
         fee: SatPerVByte,
@@ -264,6 +524,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "serde")]
     fn serialize_fee_rate_in_yaml() {
         let fee_rate = SerdeTest {
             fee: SatPerVByte::from_sat(10),
@@ -273,6 +534,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "serde")]
     fn deserialize_fee_rate_in_yaml() {
         let s = "---\nfee: 10 satoshi/vByte\n";
         let fee_rate = serde_yaml::from_str(&s).expect("Decode fee rate from yaml");
@@ -283,4 +545,29 @@ mod tests {
             fee_rate
         );
     }
+
+    #[test]
+    fn decode_fee_rate_bounds() {
+        let zero = FeeStrategy::Fixed(SatPerVByte::from_sat(0));
+        let res: Result<FeeStrategy<SatPerVByte>, _> =
+            consensus::deserialize(&consensus::serialize(&zero));
+        assert!(res.is_err());
+
+        let too_high = SatPerVByte::from_sat(SatPerVByte::MAX_RATE + 1);
+        assert!(SatPerVByte::from_canonical_bytes(&too_high.as_canonical_bytes()).is_err());
+        assert!("0 satoshi/vByte".parse::<SatPerVByte>().is_err());
+
+        let rate = SatPerVByte::from_sat(SatPerVByte::MAX_RATE);
+        assert_eq!(
+            SatPerVByte::from_canonical_bytes(&rate.as_canonical_bytes()).unwrap(),
+            rate
+        );
+    }
+
+    #[test]
+    fn checked_mul_vsize() {
+        let rate = SatPerVByte::from_sat(9);
+        assert_eq!(rate.checked_mul_vsize(110), Some(Amount::from_sat(990)));
+        assert_eq!(rate.checked_mul_vsize(u64::MAX), None);
+    }
 }
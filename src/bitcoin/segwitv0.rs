@@ -22,34 +22,40 @@ use std::str::FromStr;
 
 use crate::bitcoin::segwitv0::{
     buy::Buy, cancel::Cancel, funding::Funding, lock::Lock, punish::Punish, refund::Refund,
+    sweep::Sweep,
 };
 use crate::bitcoin::transaction::TxInRef;
 use crate::bitcoin::transaction::{MetadataOutput, Tx};
-use crate::bitcoin::{Bitcoin, BitcoinSegwitV0, Btc, Strategy};
+use crate::bitcoin::{random_secret_key, Bitcoin, BitcoinSegwitV0, Btc, Strategy};
 
 use crate::bitcoin::timelock::CSVTimelock;
 use crate::blockchain::Transactions;
 use crate::consensus::{self, CanonicalBytes};
-use crate::crypto::{DeriveKeys, SharedKeyId};
+use crate::crypto::adaptor::{adaptor, to_point, to_scalar};
+use crate::crypto::{self, AdaptorSignature, DeriveKeys, SharedKeyId, Signatures};
 use crate::role::SwapRole;
 use crate::script::{DataLock, DataPunishableLock, DoubleKeys, ScriptPath};
 
 use bitcoin::blockdata::opcodes;
-use bitcoin::blockdata::script::{Builder, Instruction, Script};
+use bitcoin::blockdata::script::{self, Builder, Instruction, Script};
 use bitcoin::blockdata::transaction::EcdsaSighashType;
-use bitcoin::hashes::sha256d::Hash as Sha256dHash;
+use bitcoin::hashes::{sha256d::Hash as Sha256dHash, Hash};
 use bitcoin::secp256k1::{ecdsa::Signature, Message, PublicKey, Secp256k1, SecretKey, Signing};
 use bitcoin::util::psbt::PartiallySignedTransaction;
 use bitcoin::util::sighash::SighashCache;
 
 use ecdsa_fun::adaptor::EncryptedSignature;
+use rand_alt::{CryptoRng, RngCore};
 
 mod buy;
 mod cancel;
+#[cfg(test)]
+mod fixtures;
 pub mod funding;
 mod lock;
 mod punish;
 mod refund;
+mod sweep;
 
 /// Spend the lock output and reveal the first secret.
 pub type BuyTx = Tx<Buy>;
@@ -69,6 +75,9 @@ pub type PunishTx = Tx<Punish>;
 /// Spend the cancel output and reveal the second secret.
 pub type RefundTx = Tx<Refund>;
 
+/// Sweep the output of a completed swap, e.g. of the buy or refund transaction, to another address.
+pub type SweepTx = Tx<Sweep>;
+
 /// Inner type for the implementation of SegWit version 0 transactions and ECDSA cryptography.
 #[derive(Clone, Debug, Copy, Eq, PartialEq)]
 pub struct SegwitV0;
@@ -191,9 +200,21 @@ impl CoopLock {
 pub struct PunishLock {
     alice: PublicKey,
     bob: PublicKey,
+    timelock: CSVTimelock,
     punish: PublicKey,
 }
 
+impl From<DataPunishableLock<CSVTimelock, PublicKey>> for PunishLock {
+    fn from(data: DataPunishableLock<CSVTimelock, PublicKey>) -> Self {
+        Self {
+            alice: data.success.alice,
+            bob: data.success.bob,
+            timelock: data.timelock,
+            punish: data.failure,
+        }
+    }
+}
+
 impl PunishLock {
     pub fn script(data: DataPunishableLock<CSVTimelock, PublicKey>) -> Script {
         let DataPunishableLock {
@@ -285,7 +306,23 @@ impl PunishLock {
                 _ => Err(Error::WrongTemplate("Missing ELSE opcode")),
             })?;
         // Timelock
-        let _ = ints.next().ok_or(Error::WrongTemplate("Missing opcode"))?;
+        let timelock = ints
+            .next()
+            .ok_or(Error::WrongTemplate("Missing opcode"))
+            .map_or_else(Err, |v| match v {
+                Ok(Instruction::PushBytes(b)) => script::read_scriptint(b)
+                    .ok()
+                    .and_then(|t| u32::try_from(t).ok())
+                    .ok_or(Error::WrongTemplate("Invalid timelock")),
+                Ok(Instruction::Op(op))
+                    if (all::OP_PUSHNUM_1.into_u8()..=all::OP_PUSHNUM_16.into_u8())
+                        .contains(&op.into_u8()) =>
+                {
+                    Ok((op.into_u8() - all::OP_PUSHNUM_1.into_u8() + 1) as u32)
+                }
+                Err(e) => Err(Error::new(e)),
+                _ => Err(Error::WrongTemplate("Missing timelock")),
+            })?;
         // CSV opcode
         ints.next()
             .ok_or(Error::WrongTemplate("Missing opcode"))
@@ -337,7 +374,61 @@ impl PunishLock {
             };
         }
 
-        Ok(Self { alice, bob, punish })
+        Ok(Self {
+            alice,
+            bob,
+            timelock: CSVTimelock::new(timelock),
+            punish,
+        })
+    }
+
+    /// Returns the script of the cooperative path, spendable with Alice and Bob signatures:
+    /// `<alice> OP_CHECKSIGVERIFY <bob> OP_CHECKSIG`.
+    pub fn cooperative_script(&self) -> Script {
+        Builder::new()
+            .push_key(&bitcoin::util::key::PublicKey::new(self.alice))
+            .push_opcode(opcodes::all::OP_CHECKSIGVERIFY)
+            .push_key(&bitcoin::util::key::PublicKey::new(self.bob))
+            .push_opcode(opcodes::all::OP_CHECKSIG)
+            .into_script()
+    }
+
+    /// Returns the script of the punish path, spendable with the punish key signature after the
+    /// punish timelock: `<timelock> OP_CSV OP_DROP <punish> OP_CHECKSIG`.
+    pub fn punish_script(&self) -> Script {
+        Builder::new()
+            .push_int(self.timelock.as_u32().into())
+            .push_opcode(opcodes::all::OP_CSV)
+            .push_opcode(opcodes::all::OP_DROP)
+            .push_key(&bitcoin::util::key::PublicKey::new(self.punish))
+            .push_opcode(opcodes::all::OP_CHECKSIG)
+            .into_script()
+    }
+
+    /// Returns the punish timelock of the script.
+    pub fn timelock(&self) -> CSVTimelock {
+        self.timelock
+    }
+
+    /// Verify that both paths of the script match the negotiated keys and punish timelock, fails
+    /// with [`WrongTemplate`] naming the first path that does not match.
+    ///
+    /// [`WrongTemplate`]: crate::transaction::Error::WrongTemplate
+    pub fn verify(
+        &self,
+        keys: DoubleKeys<PublicKey>,
+        punish: PublicKey,
+        timelock: CSVTimelock,
+    ) -> Result<(), crate::transaction::Error> {
+        use crate::transaction::Error;
+
+        if self.alice != keys.alice || self.bob != keys.bob {
+            return Err(Error::WrongTemplate("Cooperative path keys do not match"));
+        }
+        if self.punish != punish || self.timelock != timelock {
+            return Err(Error::WrongTemplate("Punish path does not match"));
+        }
+        Ok(())
     }
 
     pub fn get_pubkey(&self, swap_role: SwapRole, script_path: ScriptPath) -> Option<&PublicKey> {
@@ -399,6 +490,79 @@ impl DeriveKeys for Bitcoin<SegwitV0> {
         // No shared key in Bitcoin, transparent ledger
         vec![]
     }
+
+    fn generate_keypair<R: RngCore + CryptoRng>(rng: &mut R) -> (SecretKey, PublicKey) {
+        let secret_key = random_secret_key(rng);
+        let public_key = PublicKey::from_secret_key(&Secp256k1::signing_only(), &secret_key);
+        (secret_key, public_key)
+    }
+}
+
+impl Signatures for Bitcoin<SegwitV0> {
+    type Message = Sha256dHash;
+    type Signature = Signature;
+    type EncryptedSignature = EncryptedSignature;
+
+    fn verify(key: &PublicKey, msg: Sha256dHash, sig: &Signature) -> Result<(), crypto::Error> {
+        let msg = Message::from_slice(&msg[..]).expect("Hash is always a valid message");
+        Secp256k1::verification_only()
+            .verify_ecdsa(&msg, sig, key)
+            .map_err(|_| crypto::Error::InvalidSignature)
+    }
+}
+
+impl AdaptorSignature for Bitcoin<SegwitV0> {
+    fn encrypt_sign(
+        signing_key: &SecretKey,
+        encryption_key: &PublicKey,
+        msg: Sha256dHash,
+    ) -> Result<EncryptedSignature, crypto::Error> {
+        let adaptor = adaptor();
+        let signing_key = to_scalar(signing_key);
+        let encryption_key = to_point(encryption_key);
+        Ok(adaptor.encrypted_sign(&signing_key, &encryption_key, msg.as_inner()))
+    }
+
+    fn verify_adaptor(
+        signing_key: &PublicKey,
+        encryption_key: &PublicKey,
+        msg: Sha256dHash,
+        sig: &EncryptedSignature,
+    ) -> Result<(), crypto::Error> {
+        let adaptor = adaptor();
+        match adaptor.verify_encrypted_signature(
+            &to_point(signing_key),
+            &to_point(encryption_key),
+            msg.as_inner(),
+            sig,
+        ) {
+            true => Ok(()),
+            false => Err(crypto::Error::InvalidEncryptedSignature),
+        }
+    }
+
+    fn decrypt_sig(
+        decryption_key: &SecretKey,
+        sig: EncryptedSignature,
+    ) -> Result<Signature, crypto::Error> {
+        let adaptor = adaptor();
+        let sig = adaptor.decrypt_signature(&to_scalar(decryption_key), sig);
+        Signature::from_compact(sig.to_bytes().as_ref()).map_err(crypto::Error::new)
+    }
+
+    fn recover_secret(
+        encryption_key: &PublicKey,
+        sig: &Signature,
+        encrypted_sig: &EncryptedSignature,
+    ) -> Result<SecretKey, crypto::Error> {
+        let adaptor = adaptor();
+        let sig = ecdsa_fun::Signature::from_bytes(sig.serialize_compact())
+            .ok_or(crypto::Error::InvalidSignature)?;
+        let decryption_key = adaptor
+            .recover_decryption_key(&to_point(encryption_key), &sig, encrypted_sig)
+            .ok_or(crypto::Error::InvalidSignature)?;
+        SecretKey::from_slice(decryption_key.to_bytes().as_ref()).map_err(crypto::Error::new)
+    }
 }
 
 impl CanonicalBytes for SecretKey {
@@ -410,7 +574,9 @@ impl CanonicalBytes for SecretKey {
     where
         Self: Sized,
     {
-        SecretKey::from_slice(bytes).map_err(consensus::Error::new)
+        consensus::check_length(bytes, 32)?;
+        SecretKey::from_slice(bytes)
+            .map_err(|_| consensus::Error::InvalidEncoding("invalid secp256k1 scalar"))
     }
 }
 
@@ -499,6 +665,372 @@ pub fn sign_hash(
 mod tests {
     use super::*;
 
+    use crate::bitcoin::segwitv0::fixtures::{
+        setup_funding, setup_lock, setup_lock_with_amount, PunishWallet,
+    };
+
+    use crate::crypto;
+    use crate::protocol::message::{
+        BuyProcedureSignature, CoreArbitratingSetup, RefundProcedureSignatures,
+    };
+    use crate::swap::SwapId;
+    use crate::transaction::{
+        self as tx, Broadcastable, Buyable, Cancelable, Fundable, Lockable, Refundable,
+        Transaction, TxLabel, Witnessable,
+    };
+
+    #[test]
+    fn punish_lock_paths_match_templates() {
+        let key = || BitcoinSegwitV0::generate_keypair(&mut rand_alt::thread_rng()).1;
+        let (alice, bob, punish) = (key(), key(), key());
+        // Small timelocks are pushed as opcodes, larger ones as bytes
+        for timelock in [10, 144] {
+            let data = DataPunishableLock {
+                timelock: CSVTimelock::new(timelock),
+                success: DoubleKeys::new(alice, bob),
+                failure: punish,
+            };
+            let script = PunishLock::script(data);
+            let lock = PunishLock::from_script(&script).unwrap();
+            assert_eq!(lock.timelock(), CSVTimelock::new(timelock));
+
+            let cooperative = Builder::new()
+                .push_key(&bitcoin::util::key::PublicKey::new(alice))
+                .push_opcode(opcodes::all::OP_CHECKSIGVERIFY)
+                .push_key(&bitcoin::util::key::PublicKey::new(bob))
+                .push_opcode(opcodes::all::OP_CHECKSIG)
+                .into_script();
+            let punish_path = Builder::new()
+                .push_int(timelock.into())
+                .push_opcode(opcodes::all::OP_CSV)
+                .push_opcode(opcodes::all::OP_DROP)
+                .push_key(&bitcoin::util::key::PublicKey::new(punish))
+                .push_opcode(opcodes::all::OP_CHECKSIG)
+                .into_script();
+            assert_eq!(lock.cooperative_script(), cooperative);
+            assert_eq!(lock.punish_script(), punish_path);
+            assert_eq!(PunishLock::from(data).punish_script(), punish_path);
+
+            // The full script is the two paths wrapped in a conditional
+            let mut bytes = vec![opcodes::all::OP_IF.into_u8()];
+            bytes.extend(cooperative.as_bytes());
+            bytes.push(opcodes::all::OP_ELSE.into_u8());
+            bytes.extend(punish_path.as_bytes());
+            bytes.push(opcodes::all::OP_ENDIF.into_u8());
+            assert_eq!(script.as_bytes(), &bytes[..]);
+
+            let keys = DoubleKeys::new(alice, bob);
+            let timelock = CSVTimelock::new(timelock);
+            assert!(lock.verify(keys, punish, timelock).is_ok());
+            assert!(matches!(
+                lock.verify(DoubleKeys::new(bob, alice), punish, timelock),
+                Err(tx::Error::WrongTemplate(
+                    "Cooperative path keys do not match"
+                ))
+            ));
+            assert!(matches!(
+                lock.verify(keys, alice, timelock),
+                Err(tx::Error::WrongTemplate("Punish path does not match"))
+            ));
+            assert!(matches!(
+                lock.verify(keys, punish, CSVTimelock::new(11)),
+                Err(tx::Error::WrongTemplate("Punish path does not match"))
+            ));
+        }
+    }
+
+    // Build lock, cancel, and a refund spending a cancel with the given punish timelock
+    fn setup_arbitrating_chain(refund_timelock: u32) -> (LockTx, CancelTx, RefundTx, Signature) {
+        let (lock, datalock, funding_address, pubkey, secret) = setup_lock();
+        let punish_lock = |timelock| DataPunishableLock {
+            timelock: CSVTimelock::new(timelock),
+            success: DoubleKeys::new(pubkey, pubkey),
+            failure: pubkey,
+        };
+        let cancel = CancelTx::initialize(&lock, datalock, punish_lock(10)).unwrap();
+        let refund_cancel =
+            CancelTx::initialize(&lock, datalock, punish_lock(refund_timelock)).unwrap();
+        let refund = RefundTx::initialize(&refund_cancel, funding_address).unwrap();
+        let msg = cancel
+            .generate_witness_message(ScriptPath::Failure)
+            .unwrap();
+        let cancel_sig = sign_hash(msg, &secret).unwrap();
+        (lock, cancel, refund, cancel_sig)
+    }
+
+    #[test]
+    fn core_arbitrating_setup_from_chained_transactions() {
+        let (lock, cancel, refund, cancel_sig) = setup_arbitrating_chain(10);
+        let setup = CoreArbitratingSetup::from_transactions(
+            SwapId::zero(),
+            lock,
+            cancel,
+            refund,
+            cancel_sig,
+        )
+        .unwrap();
+        assert_eq!(setup.cancel_sig, cancel_sig);
+    }
+
+    #[test]
+    fn core_arbitrating_setup_rejects_mismatched_refund() {
+        let (lock, cancel, refund, cancel_sig) = setup_arbitrating_chain(20);
+        assert!(matches!(
+            CoreArbitratingSetup::from_transactions(
+                SwapId::zero(),
+                lock,
+                cancel,
+                refund,
+                cancel_sig
+            ),
+            Err(tx::Error::InvalidTransactionChain)
+        ));
+    }
+
+    #[test]
+    fn verify_chain_reports_first_broken_link() {
+        let (lock, datalock, funding_address, _, _) = setup_lock();
+        let (_, cancel, refund, _) = setup_arbitrating_chain(10);
+        let buy = BuyTx::initialize(&lock, datalock, funding_address.clone()).unwrap();
+        assert!(tx::verify_chain(&lock, &cancel, &refund, Some(&buy)).is_ok());
+
+        // The refund spends a stale cancel output
+        let (_, _, stale_refund, _) = setup_arbitrating_chain(20);
+        assert!(matches!(
+            tx::verify_chain(&lock, &cancel, &stale_refund, Some(&buy)),
+            Err(tx::Error::BrokenLink {
+                tx: TxLabel::Refund,
+                prev: TxLabel::Cancel
+            })
+        ));
+
+        // The buy spends another lock
+        let (other_lock, datalock, _, _, _) = setup_lock_with_amount(123450000);
+        let other_buy = BuyTx::initialize(&other_lock, datalock, funding_address).unwrap();
+        assert!(matches!(
+            tx::verify_chain(&lock, &cancel, &refund, Some(&other_buy)),
+            Err(tx::Error::BrokenLink {
+                tx: TxLabel::Buy,
+                prev: TxLabel::Lock
+            })
+        ));
+    }
+
+    #[test]
+    fn verify_core_arbitrating_setup_cancel_sig() {
+        let (_, _, _, pubkey, secret) = setup_lock();
+        let (lock, cancel, refund, cancel_sig) = setup_arbitrating_chain(10);
+        // Signature over the refund transaction instead of the cancel transaction
+        let wrong_sig = sign_hash(
+            refund
+                .generate_witness_message(ScriptPath::Success)
+                .unwrap(),
+            &secret,
+        )
+        .unwrap();
+        let mut setup = CoreArbitratingSetup::from_transactions(
+            SwapId::zero(),
+            lock,
+            cancel,
+            refund,
+            cancel_sig,
+        )
+        .unwrap();
+        let wallet = PunishWallet(None);
+
+        assert!(setup
+            .verify_cancel_sig::<CancelTx, _, _, _, _, _>(&wallet, &pubkey)
+            .is_ok());
+        setup.cancel_sig = wrong_sig;
+        assert!(matches!(
+            setup.verify_cancel_sig::<CancelTx, _, _, _, _, _>(&wallet, &pubkey),
+            Err(crate::Error::Transaction(tx::Error::InvalidSignature(
+                TxLabel::Cancel
+            )))
+        ));
+    }
+
+    #[test]
+    fn generate_matching_keypair() {
+        let (secret_key, public_key) =
+            BitcoinSegwitV0::generate_keypair(&mut rand_alt::thread_rng());
+        assert_eq!(
+            public_key,
+            PublicKey::from_secret_key(&Secp256k1::new(), &secret_key)
+        );
+        let (other, _) = BitcoinSegwitV0::generate_keypair(&mut rand_alt::thread_rng());
+        assert_ne!(secret_key, other);
+    }
+
+    #[test]
+    fn signatures_verify_rejects_wrong_message() {
+        let (_, _, _, pubkey, secret) = setup_lock();
+        let msg = Sha256dHash::hash(b"farcaster");
+        let sig = sign_hash(msg, &secret).unwrap();
+        assert!(BitcoinSegwitV0::verify(&pubkey, msg, &sig).is_ok());
+        assert!(matches!(
+            BitcoinSegwitV0::verify(&pubkey, Sha256dHash::hash(b"other"), &sig),
+            Err(crypto::Error::InvalidSignature)
+        ));
+    }
+
+    #[test]
+    fn verify_buy_procedure_adaptor_sig() {
+        let (lock, datalock, funding_address, pubkey, secret) = setup_lock();
+        let encryption_key = PublicKey::from_secret_key(
+            &Secp256k1::new(),
+            &SecretKey::from_slice(&[2u8; 32]).unwrap(),
+        );
+        let buy = BuyTx::initialize(&lock, datalock, funding_address).unwrap();
+        let msg = buy.generate_witness_message(ScriptPath::Success).unwrap();
+        let mut adaptor_buy = BuyProcedureSignature {
+            swap_id: SwapId::zero(),
+            buy: buy.to_partial(),
+            buy_adaptor_sig: BitcoinSegwitV0::encrypt_sign(&secret, &encryption_key, msg).unwrap(),
+        };
+        assert!(adaptor_buy
+            .verify_buy_sig::<BuyTx, BitcoinSegwitV0, _, _>(&pubkey, &encryption_key)
+            .is_ok());
+
+        // Adaptor signature over an unrelated message instead of the buy witness message
+        let wrong_msg = Sha256dHash::hash(b"farcaster");
+        adaptor_buy.buy_adaptor_sig =
+            BitcoinSegwitV0::encrypt_sign(&secret, &encryption_key, wrong_msg).unwrap();
+        assert!(matches!(
+            adaptor_buy.verify_buy_sig::<BuyTx, BitcoinSegwitV0, _, _>(&pubkey, &encryption_key),
+            Err(crate::Error::Transaction(tx::Error::InvalidSignature(
+                TxLabel::Buy
+            )))
+        ));
+    }
+
+    #[test]
+    fn recover_secret_from_onchain_buy() {
+        // Alice and Bob keys differ so reading Alice's signature instead of Bob's fails
+        let key = || BitcoinSegwitV0::generate_keypair(&mut rand_alt::thread_rng());
+        let ((alice_secret, alice), (bob_secret, bob)) = (key(), key());
+        let funding = setup_funding(alice, 123456789);
+        let funding_address = funding.get_address().unwrap();
+        let datalock = DataLock {
+            timelock: CSVTimelock::new(10),
+            success: DoubleKeys::new(alice, bob),
+            failure: DoubleKeys::new(alice, bob),
+        };
+        let lock = LockTx::initialize(
+            &funding,
+            datalock,
+            bitcoin::Amount::from_sat(123456000),
+            None,
+        )
+        .unwrap();
+        let decryption_key = SecretKey::from_slice(&[2u8; 32]).unwrap();
+        let encryption_key = PublicKey::from_secret_key(&Secp256k1::new(), &decryption_key);
+        let buy = BuyTx::initialize(&lock, datalock, funding_address).unwrap();
+        let msg = buy.generate_witness_message(ScriptPath::Success).unwrap();
+        let adaptor_buy = BuyProcedureSignature {
+            swap_id: SwapId::zero(),
+            buy: buy.to_partial(),
+            buy_adaptor_sig: BitcoinSegwitV0::encrypt_sign(&bob_secret, &encryption_key, msg)
+                .unwrap(),
+        };
+
+        // Alice completes Bob's adaptor signature and publishes the buy transaction
+        let sig =
+            BitcoinSegwitV0::decrypt_sig(&decryption_key, adaptor_buy.buy_adaptor_sig.clone())
+                .unwrap();
+        let mut buy = BuyTx::from_partial(adaptor_buy.buy.clone());
+        buy.add_witness(bob, sig).unwrap();
+        buy.add_witness(alice, sign_hash(msg, &alice_secret).unwrap())
+            .unwrap();
+        let onchain_buy = buy.finalize_and_extract().unwrap();
+        let recovered = adaptor_buy
+            .recover_secret::<BuyTx, BitcoinSegwitV0, _, _, _, _, _>(&encryption_key, onchain_buy)
+            .unwrap();
+        assert_eq!(recovered, decryption_key);
+
+        // Another transaction than the expected buy does not reveal the secret
+        let onchain_lock = Broadcastable::<bitcoin::Transaction>::extract(&lock);
+        assert!(matches!(
+            adaptor_buy.recover_secret::<BuyTx, BitcoinSegwitV0, _, _, _, _, _>(
+                &encryption_key,
+                onchain_lock
+            ),
+            Err(crate::Error::Transaction(tx::Error::MissingWitness))
+        ));
+    }
+
+    #[test]
+    fn verify_refund_procedure_adaptor_sig() {
+        let (_, _, _, pubkey, secret) = setup_lock();
+        let (_, cancel, refund, cancel_sig) = setup_arbitrating_chain(10);
+        let encryption_key = PublicKey::from_secret_key(
+            &Secp256k1::new(),
+            &SecretKey::from_slice(&[2u8; 32]).unwrap(),
+        );
+        let msg = refund
+            .generate_witness_message(ScriptPath::Success)
+            .unwrap();
+        let mut refund_sigs = RefundProcedureSignatures {
+            swap_id: SwapId::zero(),
+            cancel_sig,
+            refund_adaptor_sig: BitcoinSegwitV0::encrypt_sign(&secret, &encryption_key, msg)
+                .unwrap(),
+        };
+        let partial_refund = refund.to_partial();
+        assert!(refund_sigs
+            .verify_refund_adaptor_sig::<RefundTx, BitcoinSegwitV0, _, _, _>(
+                &partial_refund,
+                &pubkey,
+                &encryption_key
+            )
+            .is_ok());
+
+        // Adaptor signature over the cancel transaction instead of the refund transaction
+        let wrong_msg = cancel
+            .generate_witness_message(ScriptPath::Failure)
+            .unwrap();
+        refund_sigs.refund_adaptor_sig =
+            BitcoinSegwitV0::encrypt_sign(&secret, &encryption_key, wrong_msg).unwrap();
+        assert!(matches!(
+            refund_sigs.verify_refund_adaptor_sig::<RefundTx, BitcoinSegwitV0, _, _, _>(
+                &partial_refund,
+                &pubkey,
+                &encryption_key
+            ),
+            Err(crate::Error::Transaction(tx::Error::InvalidSignature(
+                TxLabel::Refund
+            )))
+        ));
+    }
+
+    #[test]
+    fn adaptor_signature_reveals_decryption_key() {
+        let secp = Secp256k1::new();
+        let signing_key = SecretKey::from_slice(&[1u8; 32]).unwrap();
+        let signing_pubkey = PublicKey::from_secret_key(&secp, &signing_key);
+        let decryption_key = SecretKey::from_slice(&[2u8; 32]).unwrap();
+        let encryption_key = PublicKey::from_secret_key(&secp, &decryption_key);
+        let msg = Sha256dHash::hash(b"farcaster");
+
+        let enc_sig = BitcoinSegwitV0::encrypt_sign(&signing_key, &encryption_key, msg).unwrap();
+        assert!(
+            BitcoinSegwitV0::verify_adaptor(&signing_pubkey, &encryption_key, msg, &enc_sig)
+                .is_ok()
+        );
+        // Encrypted signature does not verify against another encryption key
+        assert!(matches!(
+            BitcoinSegwitV0::verify_adaptor(&signing_pubkey, &signing_pubkey, msg, &enc_sig),
+            Err(crypto::Error::InvalidEncryptedSignature)
+        ));
+
+        let sig = BitcoinSegwitV0::decrypt_sig(&decryption_key, enc_sig.clone()).unwrap();
+        let message = Message::from_slice(&msg[..]).unwrap();
+        assert!(secp.verify_ecdsa(&message, &sig, &signing_pubkey).is_ok());
+
+        let recovered = BitcoinSegwitV0::recover_secret(&encryption_key, &sig, &enc_sig).unwrap();
+        assert_eq!(recovered, decryption_key);
+    }
+
     #[test]
     fn from_string() {
         let parse = Bitcoin::<SegwitV0>::from_str("SegwitV0");
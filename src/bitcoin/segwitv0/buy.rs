@@ -26,10 +26,12 @@ use bitcoin::Address;
 use bitcoin::Amount;
 use bitcoin::Transaction;
 
+use crate::blockchain::{Fee, FeeStrategy};
 use crate::role::SwapRole;
 use crate::script;
-use crate::transaction::{Buyable, Error as FError, Lockable};
+use crate::transaction::{Buyable, Error as FError, Lockable, TxLabel};
 
+use crate::bitcoin::fee::SatPerVByte;
 use crate::bitcoin::segwitv0::CoopLock;
 use crate::bitcoin::segwitv0::Sha256dHash;
 use crate::bitcoin::timelock::CSVTimelock;
@@ -39,6 +41,8 @@ use crate::bitcoin::transaction::{Error, MetadataOutput, SubTransaction, Tx};
 pub struct Buy;
 
 impl SubTransaction for Buy {
+    const LABEL: TxLabel = TxLabel::Buy;
+
     fn finalize(psbt: &mut PartiallySignedTransaction) -> Result<(), FError> {
         let script = psbt.inputs[0]
             .witness_script
@@ -69,6 +73,18 @@ impl SubTransaction for Buy {
 
         Ok(())
     }
+
+    fn signing_keys(psbt: &PartiallySignedTransaction) -> Result<Vec<Vec<PublicKey>>, FError> {
+        let script = psbt.inputs[0]
+            .witness_script
+            .as_ref()
+            .ok_or(FError::MissingWitness)?;
+        let swaplock = CoopLock::from_script(script)?;
+        Ok(vec![vec![
+            *swaplock.get_pubkey(SwapRole::Alice),
+            *swaplock.get_pubkey(SwapRole::Bob),
+        ]])
+    }
 }
 
 impl
@@ -119,17 +135,27 @@ impl
         let mut psbt =
             PartiallySignedTransaction::from_unsigned_tx(unsigned_tx).map_err(Error::from)?;
 
+        // The output must be relayable before any fee is taken from it
+        if psbt.unsigned_tx.output[0].value < psbt.dust_limit().as_sat() {
+            return Err(FError::NotEnoughAssets);
+        }
+
         // Set the input witness data and sighash type
         psbt.inputs[0].witness_utxo = Some(output_metadata.tx_out);
         psbt.inputs[0].witness_script = output_metadata.script_pubkey;
 
         Ok(Tx {
             psbt,
+            witness_messages: None,
             _t: PhantomData,
         })
     }
 
-    fn verify_template(&self, destination_target: Address) -> Result<(), FError> {
+    fn verify_template(
+        &self,
+        destination_target: Address,
+        fee_strategy: &FeeStrategy<SatPerVByte>,
+    ) -> Result<(), FError> {
         (self.psbt.unsigned_tx.version == 2)
             .then(|| 0)
             .ok_or(FError::WrongTemplate("Tx version is not 2"))?;
@@ -154,7 +180,7 @@ impl
             .then(|| 0)
             .ok_or(FError::WrongTemplate("Script pubkey does not match"))?;
 
-        Ok(())
+        self.psbt.validate_rate(fee_strategy)
     }
 
     fn extract_witness(tx: bitcoin::Transaction) -> Signature {
@@ -164,4 +190,66 @@ impl
             .expect("Validated transaction on-chain, signature and witness position is correct.");
         ecdsa_sig.sig
     }
+
+    fn extract_onchain_witness(&self, tx: bitcoin::Transaction) -> Result<Signature, FError> {
+        // The transaction id commits to everything but the witness data
+        (tx.txid() == self.psbt.unsigned_tx.txid())
+            .then(|| 0)
+            .ok_or(FError::MissingWitness)?;
+        let witness = tx.input[0].witness.to_vec();
+        (witness.len() == 3)
+            .then(|| 0)
+            .ok_or(FError::MissingWitness)?;
+        EcdsaSig::from_slice(witness[0].as_ref())
+            .map(|ecdsa_sig| ecdsa_sig.sig)
+            .map_err(|_| FError::MissingWitness)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitcoin::segwitv0::fixtures::{
+        assert_dust_fee_rejected, setup_lock, setup_lock_with_amount,
+    };
+    use crate::bitcoin::segwitv0::BuyTx;
+    use crate::blockchain::FeePriority;
+    use crate::transaction::{self as tx, Buyable, Transaction};
+
+    #[test]
+    fn verify_template_checks_fee_rate() {
+        let (lock, datalock, funding_address, _, _) = setup_lock();
+        let strategy = FeeStrategy::Fixed(SatPerVByte::from_sat(10));
+        let overpaid = FeeStrategy::Fixed(SatPerVByte::from_sat(5));
+
+        let mut buy = BuyTx::initialize(&lock, datalock, funding_address.clone()).unwrap();
+        // Without fee the rate is rejected by the strategy
+        assert!(matches!(
+            buy.verify_template(funding_address.clone(), &strategy),
+            Err(tx::Error::FeeOutOfBounds)
+        ));
+        buy.as_partial_mut()
+            .set_fee(&strategy, FeePriority::Low)
+            .unwrap();
+        assert!(buy
+            .verify_template(funding_address.clone(), &strategy)
+            .is_ok());
+        assert!(matches!(
+            buy.verify_template(funding_address, &overpaid),
+            Err(tx::Error::FeeOutOfBounds)
+        ));
+    }
+
+    #[test]
+    fn reject_dust_output() {
+        let (lock, datalock, funding_address, _, _) = setup_lock_with_amount(200);
+        assert!(matches!(
+            BuyTx::initialize(&lock, datalock, funding_address.clone()),
+            Err(tx::Error::NotEnoughAssets)
+        ));
+
+        let (lock, datalock, _, _, _) = setup_lock_with_amount(10_000);
+        let mut buy = BuyTx::initialize(&lock, datalock, funding_address).unwrap();
+        assert_dust_fee_rejected(buy.as_partial_mut());
+    }
 }
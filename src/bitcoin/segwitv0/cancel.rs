@@ -25,10 +25,12 @@ use bitcoin::Address;
 use bitcoin::Amount;
 use bitcoin::Transaction;
 
+use crate::blockchain::{Fee, FeeStrategy};
 use crate::role::SwapRole;
 use crate::script;
-use crate::transaction::{Cancelable, Error as FError, Lockable};
+use crate::transaction::{Cancelable, Error as FError, Lockable, TxLabel};
 
+use crate::bitcoin::fee::SatPerVByte;
 use crate::bitcoin::segwitv0::Sha256dHash;
 use crate::bitcoin::segwitv0::{CoopLock, PunishLock};
 use crate::bitcoin::timelock::CSVTimelock;
@@ -38,6 +40,8 @@ use crate::bitcoin::transaction::{Error, MetadataOutput, SubTransaction, Tx};
 pub struct Cancel;
 
 impl SubTransaction for Cancel {
+    const LABEL: TxLabel = TxLabel::Cancel;
+
     fn finalize(psbt: &mut PartiallySignedTransaction) -> Result<(), FError> {
         let script = psbt.inputs[0]
             .witness_script
@@ -68,6 +72,18 @@ impl SubTransaction for Cancel {
 
         Ok(())
     }
+
+    fn signing_keys(psbt: &PartiallySignedTransaction) -> Result<Vec<Vec<PublicKey>>, FError> {
+        let script = psbt.inputs[0]
+            .witness_script
+            .as_ref()
+            .ok_or(FError::MissingWitness)?;
+        let swaplock = CoopLock::from_script(script)?;
+        Ok(vec![vec![
+            *swaplock.get_pubkey(SwapRole::Alice),
+            *swaplock.get_pubkey(SwapRole::Bob),
+        ]])
+    }
 }
 
 impl
@@ -128,6 +144,7 @@ impl
 
         Ok(Tx {
             psbt,
+            witness_messages: None,
             _t: PhantomData,
         })
     }
@@ -136,6 +153,7 @@ impl
         &self,
         lock: script::DataLock<CSVTimelock, PublicKey>,
         punish_lock: script::DataPunishableLock<CSVTimelock, PublicKey>,
+        fee_strategy: &FeeStrategy<SatPerVByte>,
     ) -> Result<(), FError> {
         (self.psbt.unsigned_tx.version == 2)
             .then(|| 0)
@@ -163,6 +181,36 @@ impl
             .then(|| 0)
             .ok_or(FError::WrongTemplate("Script pubkey does not match"))?;
 
-        Ok(())
+        self.psbt.validate_rate(fee_strategy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitcoin::segwitv0::fixtures::{setup_lock, setup_punish_lock};
+    use crate::bitcoin::segwitv0::CancelTx;
+    use crate::blockchain::FeePriority;
+    use crate::transaction::{self as tx, Cancelable, Transaction};
+
+    #[test]
+    fn verify_template_checks_fee_rate() {
+        let (lock, datalock, _, pubkey, _) = setup_lock();
+        let punish_lock = setup_punish_lock(pubkey);
+        let strategy = FeeStrategy::Fixed(SatPerVByte::from_sat(10));
+        let overpaid = FeeStrategy::Fixed(SatPerVByte::from_sat(5));
+
+        let mut cancel = CancelTx::initialize(&lock, datalock, punish_lock).unwrap();
+        cancel
+            .as_partial_mut()
+            .set_fee(&strategy, FeePriority::Low)
+            .unwrap();
+        assert!(cancel
+            .verify_template(datalock, punish_lock, &strategy)
+            .is_ok());
+        assert!(matches!(
+            cancel.verify_template(datalock, punish_lock, &overpaid),
+            Err(tx::Error::FeeOutOfBounds)
+        ));
     }
 }
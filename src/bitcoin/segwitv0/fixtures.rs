@@ -0,0 +1,156 @@
+// Copyright 2021-2022 Farcaster Devs
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; either
+// version 3 of the License, or (at your option) any later version.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301, USA
+
+//! Transactions and wallets shared by the tests of the SegWit v0 transaction templates.
+
+use bitcoin::blockdata::script::Script;
+use bitcoin::blockdata::transaction::{OutPoint, TxIn, TxOut};
+use bitcoin::hashes::sha256d::Hash as Sha256dHash;
+use bitcoin::secp256k1::{ecdsa::Signature, PublicKey, Secp256k1, SecretKey};
+use bitcoin::util::psbt::PartiallySignedTransaction;
+use bitcoin::Witness;
+
+use crate::bitcoin::fee::SatPerVByte;
+use crate::bitcoin::segwitv0::{sign_hash, FundingTx, LockTx};
+use crate::bitcoin::timelock::CSVTimelock;
+use crate::bitcoin::BitcoinSegwitV0;
+use crate::blockchain::{Fee, FeePriority, FeeStrategy, FeeStrategyError, Network};
+use crate::crypto::{self, ArbitratingKeyId, Sign, Signatures};
+use crate::script::{DataLock, DataPunishableLock, DoubleKeys};
+use crate::transaction::{Fundable, Lockable};
+
+pub(super) struct PunishWallet(pub(super) Option<SecretKey>);
+
+impl Sign<PublicKey, Sha256dHash, Signature> for PunishWallet {
+    fn sign(
+        &mut self,
+        key: ArbitratingKeyId,
+        msg: Sha256dHash,
+    ) -> Result<Signature, crypto::Error> {
+        match (key, self.0) {
+            (ArbitratingKeyId::Punish, Some(secret)) => {
+                sign_hash(msg, &secret).map_err(crypto::Error::new)
+            }
+            _ => Err(crypto::Error::MissingKey),
+        }
+    }
+
+    fn verify_signature(
+        &self,
+        key: &PublicKey,
+        msg: Sha256dHash,
+        sig: &Signature,
+    ) -> Result<(), crypto::Error> {
+        BitcoinSegwitV0::verify(key, msg, sig)
+    }
+}
+
+pub(super) fn setup_funding(pubkey: PublicKey, value: u64) -> FundingTx {
+    let mut funding = FundingTx::initialize(pubkey, Network::Local).unwrap();
+    let funding_address = funding.get_address().unwrap();
+    funding
+        .update(bitcoin::Transaction {
+            version: 2,
+            lock_time: 0,
+            input: vec![TxIn {
+                previous_output: OutPoint::null(),
+                script_sig: Script::default(),
+                sequence: (1 << 31) as u32,
+                witness: Witness::new(),
+            }],
+            output: vec![TxOut {
+                value,
+                script_pubkey: funding_address.script_pubkey(),
+            }],
+        })
+        .unwrap();
+    funding
+}
+
+pub(super) fn setup_lock() -> (
+    LockTx,
+    DataLock<CSVTimelock, PublicKey>,
+    bitcoin::Address,
+    PublicKey,
+    SecretKey,
+) {
+    setup_lock_with_amount(123456000)
+}
+
+pub(super) fn setup_lock_with_amount(
+    target_amount: u64,
+) -> (
+    LockTx,
+    DataLock<CSVTimelock, PublicKey>,
+    bitcoin::Address,
+    PublicKey,
+    SecretKey,
+) {
+    let secp = Secp256k1::new();
+    let secret = SecretKey::from_slice(&[1u8; 32]).unwrap();
+    let pubkey = PublicKey::from_secret_key(&secp, &secret);
+
+    let funding = setup_funding(pubkey, 123456789);
+    let funding_address = funding.get_address().unwrap();
+
+    let datalock = DataLock {
+        timelock: CSVTimelock::new(10),
+        success: DoubleKeys::new(pubkey, pubkey),
+        failure: DoubleKeys::new(pubkey, pubkey),
+    };
+    let lock = LockTx::initialize(
+        &funding,
+        datalock,
+        bitcoin::Amount::from_sat(target_amount),
+        None,
+    )
+    .unwrap();
+    (lock, datalock, funding_address, pubkey, secret)
+}
+
+// Punishable lock with the same key on every path
+pub(super) fn setup_punish_lock(pubkey: PublicKey) -> DataPunishableLock<CSVTimelock, PublicKey> {
+    DataPunishableLock {
+        timelock: CSVTimelock::new(10),
+        success: DoubleKeys::new(pubkey, pubkey),
+        failure: pubkey,
+    }
+}
+
+// Check that a high fee rate leaving a dust output is rejected when setting the fee
+pub(super) fn assert_dust_fee_rejected(psbt: &mut PartiallySignedTransaction) {
+    let amount = psbt.unsigned_tx.output[0].value;
+    let dust_limit = psbt.dust_limit().as_sat();
+    let weight = psbt.unsigned_tx.weight() as u64;
+    // The highest rate leaving at least the dust limit in the output
+    let rate = (amount - dust_limit) / weight;
+    assert!((rate + 1) * weight <= amount);
+    assert!(matches!(
+        psbt.set_fee(
+            &FeeStrategy::Fixed(SatPerVByte::from_sat(rate + 1)),
+            FeePriority::Low
+        ),
+        Err(FeeStrategyError::NotEnoughAssets)
+    ));
+    assert_eq!(psbt.unsigned_tx.output[0].value, amount);
+    assert!(psbt
+        .set_fee(
+            &FeeStrategy::Fixed(SatPerVByte::from_sat(rate)),
+            FeePriority::Low
+        )
+        .is_ok());
+    assert!(psbt.unsigned_tx.output[0].value >= dust_limit);
+}
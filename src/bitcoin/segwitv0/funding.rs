@@ -19,7 +19,7 @@
 use bitcoin::blockdata::transaction::{OutPoint, Transaction};
 use bitcoin::network::constants::Network as BtcNetwork;
 use bitcoin::secp256k1::PublicKey;
-use bitcoin::Address;
+use bitcoin::{Address, Amount};
 
 use crate::blockchain::Network;
 use crate::consensus::{CanonicalBytes, Decodable, Encodable};
@@ -29,11 +29,58 @@ use crate::bitcoin::transaction::{Error, MetadataOutput};
 
 /// Manages the steps to handle on-chain funding. Receives the public key derived from the key
 /// manager, receives the network of operations and the raw funding transaction when seen.
+///
+/// The funding is considered safe to build on once its transaction reached the confirmation
+/// depth, by default selected per network with [`Funding::default_confirmation_depth`].
 #[derive(Debug, Clone)]
 pub struct Funding {
     pubkey: Option<PublicKey>,
     network: Option<Network>,
     seen_tx: Option<Transaction>,
+    confirmation_depth: Option<u32>,
+    seen_height: Option<u64>,
+}
+
+impl Funding {
+    /// Return the default number of confirmations required on the given network: 6 on mainnet, 3
+    /// on testnet, and 1 on local networks.
+    pub fn default_confirmation_depth(network: Network) -> u32 {
+        match network {
+            Network::Mainnet => 6,
+            Network::Testnet => 3,
+            Network::Local => 1,
+        }
+    }
+
+    /// Override the number of confirmations required before the funding is confirmed.
+    pub fn set_confirmation_depth(&mut self, depth: u32) {
+        self.confirmation_depth = Some(depth);
+    }
+
+    /// Return the number of confirmations required before the funding is confirmed. Without a
+    /// network the mainnet default applies.
+    pub fn confirmation_depth(&self) -> u32 {
+        self.confirmation_depth.unwrap_or_else(|| {
+            Self::default_confirmation_depth(self.network.unwrap_or(Network::Mainnet))
+        })
+    }
+
+    /// Set the height of the block including the seen funding transaction. The height is reset
+    /// when the funding is updated with a new transaction.
+    pub fn set_seen_height(&mut self, height: u64) {
+        self.seen_height = Some(height);
+    }
+
+    /// Return `true` if the funding transaction is included in a block and has at least the
+    /// required number of confirmations at `current_height`, counting the including block.
+    pub fn is_confirmed(&self, current_height: u64) -> bool {
+        match (&self.seen_tx, self.seen_height) {
+            (Some(_), Some(height)) if current_height >= height => {
+                current_height - height + 1 >= self.confirmation_depth() as u64
+            }
+            _ => false,
+        }
+    }
 }
 
 impl Linkable<MetadataOutput> for Funding {
@@ -75,6 +122,7 @@ impl Linkable<MetadataOutput> for Funding {
                     out_point: OutPoint::new(t.txid(), ix as u32),
                     tx_out: tx_out.clone(),
                     script_pubkey: Some(Address::p2pkh(&pubkey, network).script_pubkey()),
+                    signing_key: Some(pubkey.inner),
                 })
                 .ok_or(FError::MissingUTXO),
             // The transaction has not been see yet, cannot infer the UTXO
@@ -83,12 +131,14 @@ impl Linkable<MetadataOutput> for Funding {
     }
 }
 
-impl Fundable<Transaction, MetadataOutput, Address, PublicKey> for Funding {
+impl Fundable<Transaction, MetadataOutput, Address, PublicKey, Amount> for Funding {
     fn initialize(pubkey: PublicKey, network: Network) -> Result<Self, FError> {
         Ok(Funding {
             pubkey: Some(pubkey),
             network: Some(network),
             seen_tx: None,
+            confirmation_depth: None,
+            seen_height: None,
         })
     }
 
@@ -114,6 +164,7 @@ impl Fundable<Transaction, MetadataOutput, Address, PublicKey> for Funding {
 
     fn update(&mut self, tx: Transaction) -> Result<(), FError> {
         self.seen_tx = Some(tx);
+        self.seen_height = None;
         Ok(())
     }
 
@@ -122,29 +173,303 @@ impl Fundable<Transaction, MetadataOutput, Address, PublicKey> for Funding {
             pubkey: None,
             network: None,
             seen_tx: Some(tx),
+            confirmation_depth: None,
+            seen_height: None,
         })
     }
 
     fn was_seen(&self) -> bool {
         self.seen_tx.is_some()
     }
+
+    fn verify_funding(
+        &self,
+        expected_address: &Address,
+        expected_amount: Amount,
+    ) -> Result<(), FError> {
+        let tx = self
+            .seen_tx
+            .as_ref()
+            .ok_or(FError::MissingOnchainTransaction)?;
+        let script_pubkey = expected_address.script_pubkey();
+        let amount = tx
+            .output
+            .iter()
+            // Same output as the one consumed by the lock transaction
+            .find(|tx_out| tx_out.script_pubkey == script_pubkey)
+            .map(|tx_out| tx_out.value)
+            .ok_or(FError::WrongTemplate(
+                "Funding transaction does not pay the expected address",
+            ))?;
+        (amount >= expected_amount.as_sat())
+            .then(|| ())
+            .ok_or(FError::WrongTemplate(
+                "Funding amount is lower than expected",
+            ))
+    }
 }
 
+/// Version of the funding encoding carrying the confirmation depth and the seen height. The
+/// unversioned encoding starts with the length of the public key, 0 or 33, so it never starts with
+/// this byte.
+const FUNDING_VERSION: u8 = 2;
+
 impl Encodable for Funding {
     fn consensus_encode<W: std::io::Write>(&self, writer: &mut W) -> Result<usize, std::io::Error> {
-        let mut len = self.pubkey.as_canonical_bytes().consensus_encode(writer)?;
+        let mut len = FUNDING_VERSION.consensus_encode(writer)?;
+        len += self.pubkey.as_canonical_bytes().consensus_encode(writer)?;
         len += self.network.consensus_encode(writer)?;
         len += self.seen_tx.as_canonical_bytes().consensus_encode(writer)?;
+        len += self.confirmation_depth.consensus_encode(writer)?;
+        len += self.seen_height.consensus_encode(writer)?;
         Ok(len)
     }
 }
 
 impl Decodable for Funding {
     fn consensus_decode<D: std::io::Read>(d: &mut D) -> Result<Self, crate::consensus::Error> {
+        let version: u8 = Decodable::consensus_decode(d)?;
+        if version != FUNDING_VERSION {
+            // Unversioned encoding, the byte read starts the public key length, the confirmation
+            // depth and the seen height use the defaults
+            let first = [version];
+            let mut d = std::io::Read::chain(&first[..], d);
+            let d = &mut d;
+            return Ok(Funding {
+                pubkey: Option::<PublicKey>::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?,
+                network: Decodable::consensus_decode(d)?,
+                seen_tx: Option::<Transaction>::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?,
+                confirmation_depth: None,
+                seen_height: None,
+            });
+        }
         Ok(Funding {
             pubkey: Option::<PublicKey>::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?,
             network: Decodable::consensus_decode(d)?,
             seen_tx: Option::<Transaction>::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?,
+            confirmation_depth: Decodable::consensus_decode(d)?,
+            seen_height: Decodable::consensus_decode(d)?,
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitcoin::segwitv0::fixtures::setup_funding;
+    use crate::bitcoin::segwitv0::{sign_hash, FundingTx, LockTx};
+    use crate::bitcoin::timelock::CSVTimelock;
+    use crate::blockchain::ChainWatcher;
+    use crate::consensus;
+    use crate::script::{DataLock, DoubleKeys, ScriptPath};
+    use crate::transaction::{
+        self as tx, Broadcastable, Fundable, Linkable, Lockable, Witnessable,
+    };
+
+    use bitcoin::blockdata::script::Script;
+    use bitcoin::blockdata::transaction::{TxIn, TxOut};
+    use bitcoin::secp256k1::{Secp256k1, SecretKey};
+    use bitcoin::Witness;
+
+    #[derive(Debug, thiserror::Error)]
+    #[error("Mock chain error")]
+    struct MockChainError;
+
+    // In-memory chain simulating deposits on watched addresses
+    #[derive(Default)]
+    struct MockChain {
+        txs: Vec<bitcoin::Transaction>,
+    }
+
+    impl MockChain {
+        fn deposit(&mut self, address: &bitcoin::Address, value: u64) {
+            self.txs.push(bitcoin::Transaction {
+                version: 2,
+                lock_time: 0,
+                input: vec![TxIn {
+                    previous_output: OutPoint::null(),
+                    script_sig: Script::default(),
+                    sequence: (1 << 31) as u32,
+                    witness: Witness::new(),
+                }],
+                output: vec![TxOut {
+                    value,
+                    script_pubkey: address.script_pubkey(),
+                }],
+            });
+        }
+    }
+
+    impl ChainWatcher<bitcoin::Address, bitcoin::Transaction, bitcoin::Txid> for MockChain {
+        type Error = MockChainError;
+
+        fn watch_address(
+            &mut self,
+            address: &bitcoin::Address,
+        ) -> Result<Option<bitcoin::Transaction>, MockChainError> {
+            let script = address.script_pubkey();
+            Ok(self
+                .txs
+                .iter()
+                .find(|tx| tx.output.iter().any(|out| out.script_pubkey == script))
+                .cloned())
+        }
+
+        fn get_tx(
+            &self,
+            txid: &bitcoin::Txid,
+        ) -> Result<Option<bitcoin::Transaction>, MockChainError> {
+            Ok(self.txs.iter().find(|tx| &tx.txid() == txid).cloned())
+        }
+
+        fn broadcast(&mut self, tx: bitcoin::Transaction) -> Result<bitcoin::Txid, MockChainError> {
+            if tx.input.is_empty() {
+                return Err(MockChainError);
+            }
+            let txid = tx.txid();
+            self.txs.push(tx);
+            Ok(txid)
+        }
+    }
+
+    #[test]
+    fn update_funding_from_chain_watcher() {
+        let secp = Secp256k1::new();
+        let secret = SecretKey::from_slice(&[1u8; 32]).unwrap();
+        let pubkey = PublicKey::from_secret_key(&secp, &secret);
+        let mut chain = MockChain::default();
+
+        let mut funding = FundingTx::initialize(pubkey, Network::Local).unwrap();
+        let address = funding.get_address().unwrap();
+        assert!(chain.watch_address(&address).unwrap().is_none());
+
+        chain.deposit(&address, 123456789);
+        let tx = chain.watch_address(&address).unwrap().unwrap();
+        let txid = tx.txid();
+        funding.update(tx).unwrap();
+        assert!(funding.was_seen());
+        assert_eq!(
+            funding.get_consumable_output().unwrap().tx_out.value,
+            123456789
+        );
+        assert!(chain.get_tx(&txid).unwrap().is_some());
+
+        let datalock = DataLock {
+            timelock: CSVTimelock::new(10),
+            success: DoubleKeys::new(pubkey, pubkey),
+            failure: DoubleKeys::new(pubkey, pubkey),
+        };
+        let mut lock = LockTx::initialize(
+            &funding,
+            datalock,
+            bitcoin::Amount::from_sat(123456000),
+            None,
+        )
+        .unwrap();
+        let msg = lock.generate_witness_message(ScriptPath::Success).unwrap();
+        lock.add_witness(pubkey, sign_hash(msg, &secret).unwrap())
+            .unwrap();
+        let lock_tx =
+            Broadcastable::<bitcoin::Transaction>::finalize_and_extract(&mut lock).unwrap();
+        let lock_txid = chain.broadcast(lock_tx).unwrap();
+        assert!(chain.get_tx(&lock_txid).unwrap().is_some());
+    }
+
+    #[test]
+    fn verify_funding_address_and_amount() {
+        let secp = Secp256k1::new();
+        let secret = SecretKey::from_slice(&[1u8; 32]).unwrap();
+        let pubkey = PublicKey::from_secret_key(&secp, &secret);
+        let other_pubkey =
+            PublicKey::from_secret_key(&secp, &SecretKey::from_slice(&[2u8; 32]).unwrap());
+
+        let funding = FundingTx::initialize(pubkey, Network::Local).unwrap();
+        let address = funding.get_address().unwrap();
+        let amount = bitcoin::Amount::from_sat(100_000);
+        assert!(matches!(
+            funding.verify_funding(&address, amount),
+            Err(tx::Error::MissingOnchainTransaction)
+        ));
+
+        // The wallet funded the wrong address
+        let funding = setup_funding(other_pubkey, 100_000);
+        assert!(matches!(
+            funding.verify_funding(&address, amount),
+            Err(tx::Error::WrongTemplate(_))
+        ));
+
+        let funding = setup_funding(pubkey, 99_999);
+        assert!(matches!(
+            funding.verify_funding(&address, amount),
+            Err(tx::Error::WrongTemplate(_))
+        ));
+        let funding = setup_funding(pubkey, 100_000);
+        assert!(funding.verify_funding(&address, amount).is_ok());
+    }
+
+    #[test]
+    fn funding_confirmation_depth() {
+        use crate::consensus::Encodable;
+
+        let secp = Secp256k1::new();
+        let secret = SecretKey::from_slice(&[1u8; 32]).unwrap();
+        let pubkey = PublicKey::from_secret_key(&secp, &secret);
+        let mut chain = MockChain::default();
+
+        let mut funding = FundingTx::initialize(pubkey, Network::Mainnet).unwrap();
+        assert_eq!(funding.confirmation_depth(), 6);
+        let address = funding.get_address().unwrap();
+        chain.deposit(&address, 123456789);
+        funding
+            .update(chain.watch_address(&address).unwrap().unwrap())
+            .unwrap();
+        // Seen but not mined yet
+        assert!(!funding.is_confirmed(100));
+
+        funding.set_seen_height(100);
+        // Depth 1 is not enough when the policy requires 6 confirmations
+        assert!(!funding.is_confirmed(100));
+        assert!(!funding.is_confirmed(104));
+        assert!(funding.is_confirmed(105));
+
+        let bytes = consensus::serialize(&funding);
+        let decoded: FundingTx = consensus::deserialize(&bytes).unwrap();
+        assert!(!decoded.is_confirmed(104));
+        assert!(decoded.is_confirmed(105));
+
+        // Fundings encoded without confirmation depth and seen height decode with the defaults
+        let mut legacy = vec![];
+        Some(pubkey)
+            .as_canonical_bytes()
+            .consensus_encode(&mut legacy)
+            .unwrap();
+        Some(Network::Mainnet)
+            .consensus_encode(&mut legacy)
+            .unwrap();
+        let seen_tx = chain.watch_address(&address).unwrap();
+        seen_tx
+            .as_canonical_bytes()
+            .consensus_encode(&mut legacy)
+            .unwrap();
+        let decoded: FundingTx = consensus::deserialize(&legacy).unwrap();
+        assert_eq!(decoded.confirmation_depth(), 6);
+        assert!(decoded.was_seen());
+        assert!(!decoded.is_confirmed(105));
+
+        // Both encodings can be followed by other data in the same stream
+        for mut bytes in [bytes, legacy] {
+            0xffu8.consensus_encode(&mut bytes).unwrap();
+            let mut reader = std::io::Cursor::new(bytes);
+            let _: FundingTx = consensus::Decodable::consensus_decode(&mut reader).unwrap();
+            let trailing: u8 = consensus::Decodable::consensus_decode(&mut reader).unwrap();
+            assert_eq!(trailing, 0xff);
+        }
+
+        funding.set_confirmation_depth(1);
+        assert!(funding.is_confirmed(100));
+        assert!(!funding.is_confirmed(99));
+
+        let testnet = FundingTx::initialize(pubkey, Network::Testnet).unwrap();
+        assert_eq!(testnet.confirmation_depth(), 3);
+    }
+}
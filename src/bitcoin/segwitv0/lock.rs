@@ -14,9 +14,10 @@
 // License along with this library; if not, write to the Free Software
 // Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301, USA
 
+use std::collections::HashSet;
 use std::marker::PhantomData;
 
-use bitcoin::blockdata::transaction::{TxIn, TxOut};
+use bitcoin::blockdata::transaction::{OutPoint, TxIn, TxOut};
 use bitcoin::blockdata::witness::Witness;
 use bitcoin::secp256k1::ecdsa::Signature;
 use bitcoin::secp256k1::PublicKey;
@@ -25,30 +26,101 @@ use bitcoin::Address;
 use bitcoin::Amount;
 use bitcoin::Transaction;
 
+use crate::blockchain::{Fee, FeeStrategy};
 use crate::script;
-use crate::transaction::{Error as FError, Fundable, Lockable};
+use crate::transaction::{Error as FError, Fundable, Lockable, TxLabel};
 
+use crate::bitcoin::fee::SatPerVByte;
 use crate::bitcoin::segwitv0::CoopLock;
 use crate::bitcoin::segwitv0::Sha256dHash;
 use crate::bitcoin::timelock::CSVTimelock;
-use crate::bitcoin::transaction::{Error, MetadataOutput, SubTransaction, Tx};
+use crate::bitcoin::transaction::{
+    self, anchor_script_pubkey, metadata_output, split_extra_outputs, Error, MetadataOutput,
+    SubTransaction, Tx, ANCHOR_AMOUNT,
+};
 
 #[derive(Debug)]
 pub struct Lock;
 
 impl SubTransaction for Lock {
+    const LABEL: TxLabel = TxLabel::Lock;
+
     fn finalize(psbt: &mut PartiallySignedTransaction) -> Result<(), FError> {
-        let (pubkey, full_sig) = psbt.inputs[0]
-            .partial_sigs
+        // Each input spends a funding output with its own key
+        for input in psbt.inputs.iter_mut() {
+            let (pubkey, full_sig) = input
+                .partial_sigs
+                .iter()
+                .next()
+                .ok_or(FError::MissingSignature)?;
+            input.final_script_witness = Some(Witness::from_vec(vec![
+                full_sig.to_vec(),
+                pubkey.to_bytes(),
+            ]));
+        }
+        Ok(())
+    }
+
+    fn signing_keys(psbt: &PartiallySignedTransaction) -> Result<Vec<Vec<PublicKey>>, FError> {
+        // The funding keys are recorded in a proprietary key of each input
+        Ok(psbt
+            .inputs
+            .iter()
+            .map(|input| transaction::signing_key(input).into_iter().collect())
+            .collect())
+    }
+}
+
+impl Tx<Lock> {
+    /// Add an anchor output to the lock transaction, a child transaction spending the anchor pays
+    /// the fee of the lock (CPFP) when used with the [`FeeStrategy::None`] strategy. Fails with
+    /// [`FError::NotEnoughAssets`] if the inputs do not cover the lock and anchor amounts.
+    ///
+    /// [`FeeStrategy::None`]: crate::blockchain::FeeStrategy::None
+    pub fn add_anchor_output(&mut self) -> Result<(), FError> {
+        if self.has_anchor_output() {
+            return Ok(());
+        }
+        let available = self
+            .psbt
+            .inputs
             .iter()
-            .next()
-            .ok_or(FError::MissingSignature)?;
-        psbt.inputs[0].final_script_witness = Some(Witness::from_vec(vec![
-            full_sig.to_vec(),
-            pubkey.to_bytes(),
-        ]));
+            .map(|input| input.witness_utxo.as_ref().map(|txout| txout.value))
+            .sum::<Option<u64>>()
+            .ok_or(FError::MissingWitness)?;
+        let required = self.psbt.unsigned_tx.output[0]
+            .value
+            .checked_add(ANCHOR_AMOUNT)
+            .ok_or(FError::NotEnoughAssets)?;
+        if available < required {
+            return Err(FError::NotEnoughAssets);
+        }
+        self.witness_messages = None;
+        self.psbt.unsigned_tx.output.push(TxOut {
+            value: ANCHOR_AMOUNT,
+            script_pubkey: anchor_script_pubkey(),
+        });
+        self.psbt.outputs.push(Default::default());
         Ok(())
     }
+
+    /// Returns `true` if the lock transaction carries an anchor output.
+    pub fn has_anchor_output(&self) -> bool {
+        matches!(self.extra_outputs(), Some((_, Some(_))))
+    }
+
+    /// Returns the data carried by the metadata output of the lock transaction, if any.
+    pub fn metadata(&self) -> Option<&[u8]> {
+        match self.extra_outputs() {
+            Some((Some(data), _)) => transaction::metadata(data),
+            _ => None,
+        }
+    }
+
+    fn extra_outputs(&self) -> Option<(Option<&TxOut>, Option<&TxOut>)> {
+        let (_, extra) = self.psbt.unsigned_tx.output.split_first()?;
+        split_extra_outputs(extra)
+    }
 }
 
 impl
@@ -64,45 +136,71 @@ impl
         Signature,
     > for Tx<Lock>
 {
-    fn initialize(
-        prev: &impl Fundable<Transaction, MetadataOutput, Address, PublicKey>,
+    fn initialize_with_inputs<F>(
+        prevs: &[F],
         lock: script::DataLock<CSVTimelock, PublicKey>,
         target_amount: Amount,
-    ) -> Result<Self, FError> {
+        metadata: Option<&[u8]>,
+    ) -> Result<Self, FError>
+    where
+        F: Fundable<Transaction, MetadataOutput, Address, PublicKey, Amount>,
+    {
         let script = CoopLock::script(lock);
-        let output_metadata = prev.get_consumable_output()?;
+        let metadata = metadata.map(metadata_output).transpose()?;
+        let outputs_metadata = prevs
+            .iter()
+            .map(|prev| prev.get_consumable_output())
+            .collect::<Result<Vec<_>, _>>()?;
+        if has_duplicate(outputs_metadata.iter().map(|output| output.out_point)) {
+            return Err(FError::DuplicateInput);
+        }
 
-        if output_metadata.tx_out.value < target_amount.as_sat() {
+        let total_amount = outputs_metadata
+            .iter()
+            .try_fold(0u64, |total, output| total.checked_add(output.tx_out.value))
+            .ok_or(FError::NotEnoughAssets)?;
+        if outputs_metadata.is_empty() || total_amount < target_amount.as_sat() {
             return Err(FError::NotEnoughAssets);
         }
 
         let unsigned_tx = bitcoin::Transaction {
             version: 2,
             lock_time: 0,
-            input: vec![TxIn {
-                previous_output: output_metadata.out_point,
-                script_sig: bitcoin::Script::default(),
-                sequence: CSVTimelock::disable(),
-                witness: Witness::new(),
-            }],
-            output: vec![TxOut {
+            input: outputs_metadata
+                .iter()
+                .map(|output| TxIn {
+                    previous_output: output.out_point,
+                    script_sig: bitcoin::Script::default(),
+                    sequence: CSVTimelock::disable(),
+                    witness: Witness::new(),
+                })
+                .collect(),
+            output: std::iter::once(TxOut {
                 value: target_amount.as_sat(),
                 script_pubkey: script.to_v0_p2wsh(),
-            }],
+            })
+            .chain(metadata)
+            .collect(),
         };
 
         let mut psbt =
             PartiallySignedTransaction::from_unsigned_tx(unsigned_tx).map_err(Error::from)?;
 
-        // Set the input witness data and sighash type
-        psbt.inputs[0].witness_utxo = Some(output_metadata.tx_out);
-        psbt.inputs[0].witness_script = output_metadata.script_pubkey;
+        // Set the inputs witness data and sighash type
+        for (input, output) in psbt.inputs.iter_mut().zip(outputs_metadata) {
+            input.witness_utxo = Some(output.tx_out);
+            input.witness_script = output.script_pubkey;
+            if let Some(key) = output.signing_key {
+                transaction::set_signing_key(input, key);
+            }
+        }
 
         // Set the script witness of the output
         psbt.outputs[0].witness_script = Some(script);
 
         Ok(Tx {
             psbt,
+            witness_messages: None,
             _t: PhantomData,
         })
     }
@@ -110,6 +208,8 @@ impl
     fn verify_template(
         &self,
         lock: script::DataLock<CSVTimelock, PublicKey>,
+        metadata: Option<&[u8]>,
+        fee_strategy: &FeeStrategy<SatPerVByte>,
     ) -> Result<(), FError> {
         (self.psbt.unsigned_tx.version == 2)
             .then(|| 0)
@@ -117,15 +217,35 @@ impl
         (self.psbt.unsigned_tx.lock_time == 0)
             .then(|| 0)
             .ok_or(FError::WrongTemplate("LockTime is not set to 0"))?;
-        (self.psbt.unsigned_tx.input.len() == 1)
+        (!self.psbt.unsigned_tx.input.is_empty())
             .then(|| 0)
-            .ok_or(FError::WrongTemplate("Number of inputs is not 1"))?;
-        (self.psbt.unsigned_tx.output.len() == 1)
+            .ok_or(FError::WrongTemplate("No input"))?;
+        (!has_duplicate(
+            self.psbt
+                .unsigned_tx
+                .input
+                .iter()
+                .map(|txin| txin.previous_output),
+        ))
+        .then(|| 0)
+        .ok_or(FError::DuplicateInput)?;
+        self.extra_outputs().ok_or(FError::WrongTemplate(
+            "Outputs are not the lock followed by an optional metadata and anchor",
+        ))?;
+        (self.metadata() == metadata)
             .then(|| 0)
-            .ok_or(FError::WrongTemplate("Number of outputs is not 1"))?;
+            .ok_or(FError::WrongTemplate("Metadata does not match"))?;
+        (!self.has_anchor_output() || matches!(fee_strategy, FeeStrategy::None))
+            .then(|| 0)
+            .ok_or(FError::WrongTemplate(
+                "Anchor output is only allowed with the none fee strategy",
+            ))?;
 
-        let txin = &self.psbt.unsigned_tx.input[0];
-        (txin.sequence == CSVTimelock::disable())
+        self.psbt
+            .unsigned_tx
+            .input
+            .iter()
+            .all(|txin| txin.sequence == CSVTimelock::disable())
             .then(|| 0)
             .ok_or(FError::WrongTemplate("Sequence timelock is not disabled"))?;
 
@@ -135,6 +255,343 @@ impl
             .then(|| 0)
             .ok_or(FError::WrongTemplate("Script pubkey does not match"))?;
 
-        Ok(())
+        self.psbt.validate_rate(fee_strategy)
+    }
+}
+
+// Return true if an outpoint appears more than once.
+fn has_duplicate(out_points: impl Iterator<Item = OutPoint>) -> bool {
+    let mut seen = HashSet::new();
+    out_points
+        .into_iter()
+        .any(|out_point| !seen.insert(out_point))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitcoin::segwitv0::fixtures::{setup_funding, setup_lock, setup_lock_with_amount};
+    use crate::bitcoin::segwitv0::{sign_hash, BuyTx, FundingTx, LockTx};
+    use crate::bitcoin::transaction::ANCHOR_AMOUNT;
+    use crate::bitcoin::BitcoinSegwitV0;
+    use crate::consensus::CanonicalBytes;
+    use crate::crypto::DeriveKeys;
+    use crate::script::{DataLock, DoubleKeys, ScriptPath};
+    use crate::swap::SwapId;
+    use crate::transaction::{
+        self as tx, Broadcastable, Finalizable, Linkable, Lockable, Transaction, Witnessable,
+    };
+
+    use bitcoin::secp256k1::{Secp256k1, SecretKey};
+
+    // Fee strategy accepted by the locks built on the test fundings
+    fn lock_fee_strategy() -> FeeStrategy<SatPerVByte> {
+        FeeStrategy::Range {
+            min_inc: SatPerVByte::from_sat(1),
+            max_inc: SatPerVByte::from_sat(100),
+        }
+    }
+
+    #[test]
+    fn missing_witnesses_of_two_inputs_lock() {
+        let keys: Vec<_> = (0..2)
+            .map(|_| BitcoinSegwitV0::generate_keypair(&mut rand_alt::thread_rng()))
+            .collect();
+        let fundings: Vec<_> = keys
+            .iter()
+            .map(|(_, pubkey)| setup_funding(*pubkey, 100_000))
+            .collect();
+        let datalock = DataLock {
+            timelock: CSVTimelock::new(10),
+            success: DoubleKeys::new(keys[0].1, keys[1].1),
+            failure: DoubleKeys::new(keys[0].1, keys[1].1),
+        };
+        let mut lock = LockTx::initialize_with_inputs(
+            &fundings,
+            datalock,
+            bitcoin::Amount::from_sat(150_000),
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            lock.missing_witnesses().unwrap(),
+            vec![(0, keys[0].1), (1, keys[1].1)]
+        );
+        // The funding keys are not recorded as key derivations without source
+        assert!(lock
+            .as_partial()
+            .inputs
+            .iter()
+            .all(|input| input.bip32_derivation.is_empty()));
+
+        let (secret, pubkey) = keys[0];
+        let msg = lock.generate_input_witness_message(0).unwrap();
+        lock.add_input_witness(0, pubkey, sign_hash(msg, &secret).unwrap())
+            .unwrap();
+        assert_eq!(lock.missing_witnesses().unwrap(), vec![(1, keys[1].1)]);
+        assert!(matches!(
+            lock.finalize(),
+            Err(tx::Error::MissingSignatures(inputs)) if inputs == vec![1]
+        ));
+
+        // The expected keys survive the partial transaction serialization
+        let lock = LockTx::from_partial(lock.to_partial());
+        assert_eq!(lock.missing_witnesses().unwrap(), vec![(1, keys[1].1)]);
+    }
+
+    #[test]
+    fn lock_spends_multiple_funding_outputs() {
+        let secp = Secp256k1::new();
+        let secret = SecretKey::from_slice(&[1u8; 32]).unwrap();
+        let pubkey = PublicKey::from_secret_key(&secp, &secret);
+        let datalock = DataLock {
+            timelock: CSVTimelock::new(10),
+            success: DoubleKeys::new(pubkey, pubkey),
+            failure: DoubleKeys::new(pubkey, pubkey),
+        };
+        let fundings: Vec<FundingTx> = [40_000, 50_000, 60_000]
+            .iter()
+            .map(|value| setup_funding(pubkey, *value))
+            .collect();
+
+        // Not enough assets with the two first outputs, nor without any output
+        assert!(matches!(
+            LockTx::initialize_with_inputs(
+                &fundings[..2],
+                datalock,
+                bitcoin::Amount::from_sat(100_000),
+                None
+            ),
+            Err(tx::Error::NotEnoughAssets)
+        ));
+        assert!(matches!(
+            LockTx::initialize_with_inputs(
+                &[] as &[FundingTx],
+                datalock,
+                bitcoin::Amount::from_sat(0),
+                None
+            ),
+            Err(tx::Error::NotEnoughAssets)
+        ));
+
+        let mut lock = LockTx::initialize_with_inputs(
+            &fundings,
+            datalock,
+            bitcoin::Amount::from_sat(140_000),
+            None,
+        )
+        .unwrap();
+        assert!(lock
+            .verify_template(datalock, None, &lock_fee_strategy())
+            .is_ok());
+        assert!(matches!(
+            lock.verify_template(
+                datalock,
+                None,
+                &FeeStrategy::Fixed(SatPerVByte::from_sat(1))
+            ),
+            Err(tx::Error::FeeOutOfBounds)
+        ));
+        let inputs: Vec<OutPoint> = lock
+            .as_partial()
+            .unsigned_tx
+            .input
+            .iter()
+            .map(|txin| txin.previous_output)
+            .collect();
+        let funding_outputs: Vec<OutPoint> = fundings
+            .iter()
+            .map(|funding| funding.get_consumable_output().unwrap().out_point)
+            .collect();
+        assert_eq!(inputs, funding_outputs);
+
+        // Every input must be signed before finalizing
+        let msg = lock.generate_input_witness_message(0).unwrap();
+        lock.add_input_witness(0, pubkey, sign_hash(msg, &secret).unwrap())
+            .unwrap();
+        assert!(matches!(
+            lock.finalize(),
+            Err(tx::Error::MissingSignatures(inputs)) if inputs == vec![1, 2]
+        ));
+        for index in 1..3 {
+            let msg = lock.generate_input_witness_message(index).unwrap();
+            lock.add_input_witness(index, pubkey, sign_hash(msg, &secret).unwrap())
+                .unwrap();
+        }
+        assert!(lock.finalize_and_extract().is_ok());
+    }
+
+    #[test]
+    fn lock_rejects_duplicate_funding_outputs() {
+        let (_, datalock, _, pubkey, _) = setup_lock();
+        let funding = setup_funding(pubkey, 60_000);
+        let other = setup_funding(pubkey, 70_000);
+
+        // The same output counted twice would reach the target amount
+        assert!(matches!(
+            LockTx::initialize_with_inputs(
+                &[funding.clone(), funding.clone()],
+                datalock,
+                bitcoin::Amount::from_sat(100_000),
+                None
+            ),
+            Err(tx::Error::DuplicateInput)
+        ));
+
+        let mut lock = LockTx::initialize_with_inputs(
+            &[funding, other],
+            datalock,
+            bitcoin::Amount::from_sat(100_000),
+            None,
+        )
+        .unwrap();
+        assert!(lock
+            .verify_template(datalock, None, &lock_fee_strategy())
+            .is_ok());
+        let inputs = &mut lock.psbt.unsigned_tx.input;
+        inputs[1].previous_output = inputs[0].previous_output;
+        assert!(matches!(
+            lock.verify_template(datalock, None, &lock_fee_strategy()),
+            Err(tx::Error::DuplicateInput)
+        ));
+    }
+
+    #[test]
+    fn lock_with_metadata_output() {
+        let (lock, datalock, _, pubkey, _) = setup_lock();
+        assert_eq!(lock.metadata(), None);
+        assert_eq!(lock.as_partial().unsigned_tx.output.len(), 1);
+
+        let funding = setup_funding(pubkey, 123456789);
+        let swap_id = SwapId::random();
+        let lock = LockTx::initialize(
+            &funding,
+            datalock,
+            bitcoin::Amount::from_sat(123456000),
+            Some(&swap_id[..]),
+        )
+        .unwrap();
+        assert_eq!(lock.metadata(), Some(&swap_id[..]));
+        assert!(lock
+            .verify_template(datalock, Some(&swap_id[..]), &lock_fee_strategy())
+            .is_ok());
+        assert_eq!(lock.get_consumable_output().unwrap().out_point.vout, 0);
+        // The metadata must match the expected one
+        assert!(matches!(
+            lock.verify_template(datalock, None, &lock_fee_strategy()),
+            Err(tx::Error::WrongTemplate(_))
+        ));
+        assert!(matches!(
+            lock.verify_template(datalock, Some(&SwapId::random()[..]), &lock_fee_strategy()),
+            Err(tx::Error::WrongTemplate(_))
+        ));
+
+        // The metadata output can be combined with an anchor output paying no fee
+        let mut lock = LockTx::initialize(
+            &funding,
+            datalock,
+            bitcoin::Amount::from_sat(123456789 - ANCHOR_AMOUNT),
+            Some(&swap_id[..]),
+        )
+        .unwrap();
+        lock.add_anchor_output().unwrap();
+        assert!(lock.has_anchor_output());
+        assert_eq!(lock.metadata(), Some(&swap_id[..]));
+        assert!(lock
+            .verify_template(datalock, Some(&swap_id[..]), &FeeStrategy::None)
+            .is_ok());
+
+        // Metadata must fit in a standard OP_RETURN output
+        assert!(matches!(
+            LockTx::initialize(
+                &funding,
+                datalock,
+                bitcoin::Amount::from_sat(123456000),
+                Some(&[0u8; 81]),
+            ),
+            Err(tx::Error::WrongTemplate(_))
+        ));
+    }
+
+    #[test]
+    fn lock_with_anchor_output() {
+        let (mut lock, datalock, _, _, _) = setup_lock_with_amount(123456789 - ANCHOR_AMOUNT);
+        assert!(!lock.has_anchor_output());
+        lock.add_anchor_output().unwrap();
+        assert!(lock.has_anchor_output());
+        // The fee is paid by a child transaction spending the anchor
+        assert!(lock
+            .verify_template(datalock, None, &FeeStrategy::None)
+            .is_ok());
+        // Anchor outputs are only expected with the none fee strategy
+        assert!(matches!(
+            lock.verify_template(datalock, None, &lock_fee_strategy()),
+            Err(tx::Error::WrongTemplate(_))
+        ));
+        assert!(lock
+            .verify_target_amount(bitcoin::Amount::from_sat(123456789 - ANCHOR_AMOUNT))
+            .is_ok());
+        // The lock output is still consumable by the cancel and buy transactions
+        let output = lock.get_consumable_output().unwrap();
+        assert_eq!(output.out_point.vout, 0);
+
+        // Not enough assets to cover the anchor amount
+        let (mut lock, _, _, _, _) = setup_lock_with_amount(123456789);
+        assert!(matches!(
+            lock.add_anchor_output(),
+            Err(tx::Error::NotEnoughAssets)
+        ));
+    }
+
+    #[test]
+    fn mutating_partial_invalidates_cached_witness_message() {
+        let (mut lock, _, _, _, _) = setup_lock();
+        lock.precompute_witness_messages().unwrap();
+        let cached = lock.generate_witness_message(ScriptPath::Success).unwrap();
+        assert_eq!(cached, lock.generate_input_witness_message(0).unwrap());
+
+        lock.as_partial_mut().unsigned_tx.output[0].value -= 1000;
+        let msg = lock.generate_witness_message(ScriptPath::Success).unwrap();
+        assert_ne!(msg, cached);
+        let fresh = LockTx::from_partial(lock.as_partial().clone());
+        assert_eq!(msg, fresh.generate_input_witness_message(0).unwrap());
+    }
+
+    #[test]
+    #[cfg(feature = "strict-encoding")]
+    fn save_and_load_partially_witnessed_lock() {
+        let (mut lock, _, _, pubkey, secret) = setup_lock();
+        let msg = lock.generate_witness_message(ScriptPath::Success).unwrap();
+        lock.add_witness(pubkey, sign_hash(msg, &secret).unwrap())
+            .unwrap();
+
+        let saved = lock.save();
+        let bytes = strict_encoding::strict_serialize(&saved).unwrap();
+        let restored: tx::SavedTransaction<PartiallySignedTransaction> =
+            strict_encoding::strict_deserialize(&bytes).unwrap();
+        assert_eq!(restored, saved);
+        assert_eq!(restored.label, tx::TxLabel::Lock);
+
+        // A saved lock cannot be loaded as another transaction
+        assert!(matches!(
+            BuyTx::load(restored.clone()),
+            Err(tx::Error::LabelMismatch {
+                expected: tx::TxLabel::Buy,
+                found: tx::TxLabel::Lock,
+            })
+        ));
+        let mut restored = LockTx::load(restored).unwrap();
+        assert_eq!(
+            restored.as_partial().as_canonical_bytes(),
+            lock.as_partial().as_canonical_bytes()
+        );
+        assert!(Broadcastable::<bitcoin::Transaction>::finalize_and_extract(&mut restored).is_ok());
+
+        // Unknown versions are rejected
+        let mut bytes = bytes;
+        bytes[0] = 0xff;
+        let res: Result<tx::SavedTransaction<PartiallySignedTransaction>, _> =
+            strict_encoding::strict_deserialize(&bytes);
+        assert!(res.is_err());
     }
 }
@@ -28,7 +28,7 @@ use bitcoin::Transaction;
 use crate::role::SwapRole;
 use crate::script;
 use crate::script::ScriptPath;
-use crate::transaction::{Cancelable, Error, Punishable};
+use crate::transaction::{Cancelable, Error, Punishable, TxLabel};
 
 use crate::bitcoin::segwitv0::PunishLock;
 use crate::bitcoin::segwitv0::Sha256dHash;
@@ -39,6 +39,8 @@ use crate::bitcoin::transaction::{self, MetadataOutput, SubTransaction, Tx};
 pub struct Punish;
 
 impl SubTransaction for Punish {
+    const LABEL: TxLabel = TxLabel::Punish;
+
     fn finalize(psbt: &mut PartiallySignedTransaction) -> Result<(), Error> {
         let script = psbt.inputs[0]
             .witness_script
@@ -51,7 +53,7 @@ impl SubTransaction for Punish {
             .partial_sigs
             .get(&bitcoin::PublicKey::new(
                 *swaplock
-                    .get_pubkey(SwapRole::Alice, ScriptPath::Success)
+                    .get_pubkey(SwapRole::Alice, ScriptPath::Failure)
                     .ok_or(Error::MissingPublicKey)?,
             ))
             .ok_or(Error::MissingSignature)?;
@@ -63,6 +65,18 @@ impl SubTransaction for Punish {
         ]));
         Ok(())
     }
+
+    fn signing_keys(psbt: &PartiallySignedTransaction) -> Result<Vec<Vec<PublicKey>>, Error> {
+        let script = psbt.inputs[0]
+            .witness_script
+            .as_ref()
+            .ok_or(Error::MissingWitness)?;
+        let swaplock = PunishLock::from_script(script)?;
+        let punish = swaplock
+            .get_pubkey(SwapRole::Alice, ScriptPath::Failure)
+            .ok_or(Error::MissingPublicKey)?;
+        Ok(vec![vec![*punish]])
+    }
 }
 
 impl
@@ -119,7 +133,79 @@ impl
 
         Ok(Tx {
             psbt,
+            witness_messages: None,
             _t: PhantomData,
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitcoin::segwitv0::fixtures::{setup_lock, PunishWallet};
+    use crate::bitcoin::segwitv0::{CancelTx, PunishTx};
+    use crate::bitcoin::BitcoinSegwitV0;
+    use crate::crypto::{DeriveKeys, Sign};
+    use crate::script::{DataPunishableLock, DoubleKeys, ScriptPath};
+    use crate::transaction::{
+        self as tx, Broadcastable, Cancelable, Linkable, Punishable, Witnessable,
+    };
+
+    use bitcoin::secp256k1::SecretKey;
+
+    fn setup_punish() -> (PunishTx, CancelTx, PublicKey, SecretKey) {
+        let (lock, datalock, funding_address, _, _) = setup_lock();
+        // Alice, Bob, and the punish keys differ so signing for the wrong path fails
+        let key = || BitcoinSegwitV0::generate_keypair(&mut rand_alt::thread_rng());
+        let ((_, alice), (_, bob), (secret, pubkey)) = (key(), key(), key());
+        let punish_lock = DataPunishableLock {
+            timelock: CSVTimelock::new(10),
+            success: DoubleKeys::new(alice, bob),
+            failure: pubkey,
+        };
+        let cancel = CancelTx::initialize(&lock, datalock, punish_lock).unwrap();
+        let punish = PunishTx::initialize(&cancel, punish_lock, funding_address).unwrap();
+        (punish, cancel, pubkey, secret)
+    }
+
+    #[test]
+    fn generate_punish_witness_validates_against_cancel_output() {
+        let (mut punish, cancel, pubkey, secret) = setup_punish();
+        let mut wallet = PunishWallet(Some(secret));
+        let sig = punish.generate_punish_witness(&mut wallet).unwrap();
+
+        // The punish key must be the failure path key of the cancel output script
+        let script = cancel
+            .get_consumable_output()
+            .unwrap()
+            .script_pubkey
+            .unwrap();
+        let punish_lock = PunishLock::from_script(&script).unwrap();
+        let punish_key = punish_lock
+            .get_pubkey(SwapRole::Alice, ScriptPath::Failure)
+            .unwrap();
+        assert_eq!(punish_key, &pubkey);
+        assert_ne!(
+            punish_lock.get_pubkey(SwapRole::Alice, ScriptPath::Success),
+            Some(&pubkey)
+        );
+
+        let msg = punish
+            .generate_witness_message(ScriptPath::Failure)
+            .unwrap();
+        assert!(wallet.verify_signature(punish_key, msg, &sig).is_ok());
+
+        punish.add_witness(pubkey, sig).unwrap();
+        assert!(Broadcastable::<bitcoin::Transaction>::finalize_and_extract(&mut punish).is_ok());
+    }
+
+    #[test]
+    fn generate_punish_witness_without_key() {
+        let (punish, _, _, _) = setup_punish();
+        let mut wallet = PunishWallet(None);
+        assert!(matches!(
+            punish.generate_punish_witness(&mut wallet),
+            Err(tx::Error::MissingPublicKey)
+        ));
+    }
+}
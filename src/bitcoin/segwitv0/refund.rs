@@ -26,10 +26,12 @@ use bitcoin::Address;
 use bitcoin::Amount;
 use bitcoin::Transaction;
 
+use crate::blockchain::{Fee, FeeStrategy};
 use crate::role::SwapRole;
 use crate::script::ScriptPath;
-use crate::transaction::{Cancelable, Error as FError, Refundable};
+use crate::transaction::{Cancelable, Error as FError, Refundable, TxLabel};
 
+use crate::bitcoin::fee::SatPerVByte;
 use crate::bitcoin::segwitv0::PunishLock;
 use crate::bitcoin::segwitv0::Sha256dHash;
 use crate::bitcoin::timelock::CSVTimelock;
@@ -39,6 +41,8 @@ use crate::bitcoin::transaction::{Error, MetadataOutput, SubTransaction, Tx};
 pub struct Refund;
 
 impl SubTransaction for Refund {
+    const LABEL: TxLabel = TxLabel::Refund;
+
     fn finalize(psbt: &mut PartiallySignedTransaction) -> Result<(), FError> {
         let script = psbt.inputs[0]
             .witness_script
@@ -74,6 +78,23 @@ impl SubTransaction for Refund {
 
         Ok(())
     }
+
+    fn signing_keys(psbt: &PartiallySignedTransaction) -> Result<Vec<Vec<PublicKey>>, FError> {
+        let script = psbt.inputs[0]
+            .witness_script
+            .as_ref()
+            .ok_or(FError::MissingWitness)?;
+        let swaplock = PunishLock::from_script(script)?;
+        Ok(vec![[SwapRole::Alice, SwapRole::Bob]
+            .iter()
+            .map(|role| {
+                swaplock
+                    .get_pubkey(*role, ScriptPath::Success)
+                    .copied()
+                    .ok_or(FError::MissingPublicKey)
+            })
+            .collect::<Result<_, _>>()?])
+    }
 }
 
 impl
@@ -123,17 +144,27 @@ impl
         let mut psbt =
             PartiallySignedTransaction::from_unsigned_tx(unsigned_tx).map_err(Error::from)?;
 
+        // The output must be relayable before any fee is taken from it
+        if psbt.unsigned_tx.output[0].value < psbt.dust_limit().as_sat() {
+            return Err(FError::NotEnoughAssets);
+        }
+
         // Set the input witness data and sighash type
         psbt.inputs[0].witness_utxo = Some(output_metadata.tx_out);
         psbt.inputs[0].witness_script = output_metadata.script_pubkey;
 
         Ok(Tx {
             psbt,
+            witness_messages: None,
             _t: PhantomData,
         })
     }
 
-    fn verify_template(&self, refund_target: Address) -> Result<(), FError> {
+    fn verify_template(
+        &self,
+        refund_target: Address,
+        fee_strategy: &FeeStrategy<SatPerVByte>,
+    ) -> Result<(), FError> {
         (self.psbt.unsigned_tx.version == 2)
             .then(|| 0)
             .ok_or(FError::WrongTemplate("Tx version is not 2"))?;
@@ -158,7 +189,7 @@ impl
             .then(|| 0)
             .ok_or(FError::WrongTemplate("Script pubkey does not match"))?;
 
-        Ok(())
+        self.psbt.validate_rate(fee_strategy)
     }
 
     fn extract_witness(tx: bitcoin::Transaction) -> Signature {
@@ -169,3 +200,51 @@ impl
         ecdsa_sig.sig
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitcoin::segwitv0::fixtures::{
+        assert_dust_fee_rejected, setup_lock, setup_lock_with_amount, setup_punish_lock,
+    };
+    use crate::bitcoin::segwitv0::{CancelTx, RefundTx};
+    use crate::blockchain::FeePriority;
+    use crate::transaction::{self as tx, Cancelable, Refundable, Transaction};
+
+    #[test]
+    fn verify_template_checks_fee_rate() {
+        let (lock, datalock, funding_address, pubkey, _) = setup_lock();
+        let cancel = CancelTx::initialize(&lock, datalock, setup_punish_lock(pubkey)).unwrap();
+        let strategy = FeeStrategy::Fixed(SatPerVByte::from_sat(10));
+        let overpaid = FeeStrategy::Fixed(SatPerVByte::from_sat(5));
+
+        let mut refund = RefundTx::initialize(&cancel, funding_address.clone()).unwrap();
+        refund
+            .as_partial_mut()
+            .set_fee(&strategy, FeePriority::Low)
+            .unwrap();
+        assert!(refund
+            .verify_template(funding_address.clone(), &strategy)
+            .is_ok());
+        assert!(matches!(
+            refund.verify_template(funding_address, &overpaid),
+            Err(tx::Error::FeeOutOfBounds)
+        ));
+    }
+
+    #[test]
+    fn reject_dust_output() {
+        let (lock, datalock, funding_address, pubkey, _) = setup_lock_with_amount(200);
+        let punish_lock = setup_punish_lock(pubkey);
+        let cancel = CancelTx::initialize(&lock, datalock, punish_lock).unwrap();
+        assert!(matches!(
+            RefundTx::initialize(&cancel, funding_address.clone()),
+            Err(tx::Error::NotEnoughAssets)
+        ));
+
+        let (lock, datalock, _, _, _) = setup_lock_with_amount(10_000);
+        let cancel = CancelTx::initialize(&lock, datalock, punish_lock).unwrap();
+        let mut refund = RefundTx::initialize(&cancel, funding_address).unwrap();
+        assert_dust_fee_rejected(refund.as_partial_mut());
+    }
+}
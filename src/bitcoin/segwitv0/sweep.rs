@@ -0,0 +1,233 @@
+// Copyright 2021-2022 Farcaster Devs
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; either
+// version 3 of the License, or (at your option) any later version.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301, USA
+
+use std::marker::PhantomData;
+
+use bitcoin::blockdata::transaction::{TxIn, TxOut};
+use bitcoin::blockdata::witness::Witness;
+use bitcoin::secp256k1::ecdsa::Signature;
+use bitcoin::secp256k1::PublicKey;
+use bitcoin::util::psbt::PartiallySignedTransaction;
+use bitcoin::Address;
+use bitcoin::Amount;
+use bitcoin::Script;
+use bitcoin::Transaction;
+
+use crate::blockchain::{Fee, FeePriority, FeeStrategy, FeeStrategyError};
+use crate::transaction::{Error as FError, Linkable, Sweepable, TxLabel};
+
+use crate::bitcoin::fee::SatPerVByte;
+use crate::bitcoin::segwitv0::Sha256dHash;
+use crate::bitcoin::transaction::{self, Error, MetadataOutput, SubTransaction, Tx};
+
+#[derive(Debug)]
+pub struct Sweep;
+
+impl SubTransaction for Sweep {
+    const LABEL: TxLabel = TxLabel::Sweep;
+
+    fn finalize(psbt: &mut PartiallySignedTransaction) -> Result<(), FError> {
+        let (pubkey, full_sig) = psbt.inputs[0]
+            .partial_sigs
+            .iter()
+            .next()
+            .ok_or(FError::MissingSignature)?;
+        psbt.inputs[0].final_script_witness = Some(Witness::from_vec(vec![
+            full_sig.to_vec(),
+            pubkey.to_bytes(),
+        ]));
+        Ok(())
+    }
+
+    fn signing_keys(psbt: &PartiallySignedTransaction) -> Result<Vec<Vec<PublicKey>>, FError> {
+        // The key owning the swept output is recorded in a proprietary key of the input
+        Ok(vec![transaction::signing_key(&psbt.inputs[0])
+            .into_iter()
+            .collect()])
+    }
+}
+
+impl
+    Sweepable<
+        Address,
+        Transaction,
+        PartiallySignedTransaction,
+        MetadataOutput,
+        Amount,
+        SatPerVByte,
+        Sha256dHash,
+        PublicKey,
+        Signature,
+    > for Tx<Sweep>
+{
+    fn initialize(
+        prev: &impl Linkable<MetadataOutput>,
+        signing_key: PublicKey,
+        destination_target: Address,
+        fee_strategy: &FeeStrategy<SatPerVByte>,
+        fee_politic: FeePriority,
+    ) -> Result<Self, FError> {
+        let output_metadata = prev.get_consumable_output()?;
+
+        // Only outputs paying to the signing key, as a P2WPKH, can be swept
+        let pubkey = bitcoin::PublicKey::new(signing_key);
+        let wpubkey_hash = pubkey.wpubkey_hash().ok_or(FError::MissingPublicKey)?;
+        if output_metadata.tx_out.script_pubkey != Script::new_v0_p2wpkh(&wpubkey_hash) {
+            return Err(FError::WrongTemplate(
+                "Output is not spendable by the signing key",
+            ));
+        }
+
+        let unsigned_tx = bitcoin::Transaction {
+            version: 2,
+            lock_time: 0,
+            input: vec![TxIn {
+                previous_output: output_metadata.out_point,
+                script_sig: bitcoin::Script::default(),
+                sequence: 0,
+                witness: Witness::new(),
+            }],
+            output: vec![TxOut {
+                value: output_metadata.tx_out.value,
+                script_pubkey: destination_target.script_pubkey(),
+            }],
+        };
+
+        let mut psbt =
+            PartiallySignedTransaction::from_unsigned_tx(unsigned_tx).map_err(Error::from)?;
+
+        // Set the input witness data, the script code of a P2WPKH input is the P2PKH script
+        psbt.inputs[0].witness_utxo = Some(output_metadata.tx_out);
+        psbt.inputs[0].witness_script = Some(Script::new_p2pkh(&pubkey.pubkey_hash()));
+        transaction::set_signing_key(&mut psbt.inputs[0], signing_key);
+
+        // The fee is taken from the swept amount, the remaining output must not be dust
+        psbt.set_fee(fee_strategy, fee_politic)
+            .map_err(|e| match e {
+                FeeStrategyError::NotEnoughAssets => FError::NotEnoughAssets,
+                e => FError::new(e),
+            })?;
+
+        Ok(Tx {
+            psbt,
+            witness_messages: None,
+            _t: PhantomData,
+        })
+    }
+
+    fn verify_template(
+        &self,
+        destination_target: Address,
+        fee_strategy: &FeeStrategy<SatPerVByte>,
+    ) -> Result<(), FError> {
+        (self.psbt.unsigned_tx.version == 2)
+            .then(|| 0)
+            .ok_or(FError::WrongTemplate("Tx version is not 2"))?;
+        (self.psbt.unsigned_tx.lock_time == 0)
+            .then(|| 0)
+            .ok_or(FError::WrongTemplate("LockTime is not set to 0"))?;
+        (self.psbt.unsigned_tx.input.len() == 1)
+            .then(|| 0)
+            .ok_or(FError::WrongTemplate("Number of inputs is not 1"))?;
+        (self.psbt.unsigned_tx.output.len() == 1)
+            .then(|| 0)
+            .ok_or(FError::WrongTemplate("Number of outputs is not 1"))?;
+
+        let txout = &self.psbt.unsigned_tx.output[0];
+        let script_pubkey = destination_target.script_pubkey();
+        (txout.script_pubkey == script_pubkey)
+            .then(|| 0)
+            .ok_or(FError::WrongTemplate("Script pubkey does not match"))?;
+
+        self.psbt.validate_rate(fee_strategy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitcoin::segwitv0::fixtures::setup_lock;
+    use crate::bitcoin::segwitv0::{sign_hash, BuyTx, SweepTx};
+    use crate::bitcoin::BitcoinSegwitV0;
+    use crate::blockchain::FeePriority;
+    use crate::crypto::DeriveKeys;
+    use crate::script::ScriptPath;
+    use crate::transaction::{
+        self as tx, Broadcastable, Buyable, Linkable, Sweepable, Transaction, Witnessable,
+    };
+
+    #[test]
+    fn sweep_buy_output_to_new_address() {
+        let (lock, datalock, funding_address, pubkey, secret) = setup_lock();
+        let buy = BuyTx::initialize(&lock, datalock, funding_address).unwrap();
+        let (_, cold_key) = BitcoinSegwitV0::generate_keypair(&mut rand_alt::thread_rng());
+        let cold_address = bitcoin::Address::p2wpkh(
+            &bitcoin::PublicKey::new(cold_key),
+            bitcoin::Network::Bitcoin,
+        )
+        .unwrap();
+        let strategy = FeeStrategy::Range {
+            min_inc: SatPerVByte::from_sat(10),
+            max_inc: SatPerVByte::from_sat(20),
+        };
+
+        let mut sweep = SweepTx::initialize(
+            &buy,
+            pubkey,
+            cold_address.clone(),
+            &strategy,
+            FeePriority::Low,
+        )
+        .unwrap();
+        assert_eq!(
+            sweep.based_on().out_point,
+            buy.get_consumable_output().unwrap().out_point
+        );
+        assert!(sweep
+            .verify_template(cold_address.clone(), &strategy)
+            .is_ok());
+        assert!(matches!(
+            sweep.verify_template(
+                cold_address.clone(),
+                &FeeStrategy::Fixed(SatPerVByte::from_sat(20))
+            ),
+            Err(tx::Error::FeeOutOfBounds)
+        ));
+        assert_eq!(Sweepable::get_label(&sweep), TxLabel::Sweep);
+        assert!(sweep.as_partial().validate_fee(&strategy).unwrap());
+        assert!(!sweep
+            .as_partial()
+            .validate_fee(&FeeStrategy::Fixed(SatPerVByte::from_sat(20)))
+            .unwrap());
+        assert!(sweep.output_amount() < buy.output_amount());
+
+        assert_eq!(sweep.missing_witnesses().unwrap(), vec![(0, pubkey)]);
+        assert_eq!(sweep.based_on().signing_key, Some(pubkey));
+        assert!(sweep.as_partial().inputs[0].bip32_derivation.is_empty());
+        let msg = sweep.generate_witness_message(ScriptPath::Success).unwrap();
+        sweep
+            .add_witness(pubkey, sign_hash(msg, &secret).unwrap())
+            .unwrap();
+        let tx = sweep.finalize_and_extract().unwrap();
+        assert_eq!(tx.input[0].witness.len(), 2);
+
+        // Only outputs paying to the signing key can be swept
+        assert!(matches!(
+            SweepTx::initialize(&buy, cold_key, cold_address, &strategy, FeePriority::Low),
+            Err(tx::Error::WrongTemplate(_))
+        ));
+    }
+}
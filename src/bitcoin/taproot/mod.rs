@@ -21,12 +21,16 @@ use std::convert::TryFrom;
 use std::fmt;
 use std::str::FromStr;
 
-use crate::bitcoin::{Bitcoin, BitcoinTaproot, Btc, Strategy};
+use crate::bitcoin::{random_secret_key, Bitcoin, BitcoinTaproot, Btc, Strategy};
 use crate::consensus::{self, CanonicalBytes};
+use crate::crypto::musig2::KeyAggregate;
 use crate::crypto::{DeriveKeys, SharedKeyId};
 //use crate::role::Arbitrating;
 
-use bitcoin::secp256k1::{schnorr::Signature, KeyPair, XOnlyPublicKey};
+use bitcoin::blockdata::script::Script;
+use bitcoin::secp256k1::{schnorr::Signature, KeyPair, Secp256k1, XOnlyPublicKey};
+use bitcoin::util::taproot::TapBranchHash;
+use rand_alt::{CryptoRng, RngCore};
 
 /// Inner type for the Taproot strategy with on-chain scripts.
 #[derive(Clone, Debug, Copy, Eq, PartialEq)]
@@ -83,6 +87,11 @@ impl DeriveKeys for Bitcoin<Taproot> {
         // No shared key in Bitcoin, transparent ledger
         vec![]
     }
+
+    fn generate_keypair<R: RngCore + CryptoRng>(rng: &mut R) -> (KeyPair, XOnlyPublicKey) {
+        let keypair = KeyPair::from_secret_key(&Secp256k1::signing_only(), random_secret_key(rng));
+        (keypair, XOnlyPublicKey::from_keypair(&keypair))
+    }
 }
 
 impl CanonicalBytes for XOnlyPublicKey {
@@ -94,7 +103,9 @@ impl CanonicalBytes for XOnlyPublicKey {
     where
         Self: Sized,
     {
-        XOnlyPublicKey::from_slice(bytes).map_err(consensus::Error::new)
+        consensus::check_length(bytes, 32)?;
+        XOnlyPublicKey::from_slice(bytes)
+            .map_err(|_| consensus::Error::InvalidEncoding("invalid x-only secp256k1 point"))
     }
 }
 
@@ -116,3 +127,55 @@ impl CanonicalBytes for Signature {
         Signature::from_slice(bytes).map_err(consensus::Error::new)
     }
 }
+
+/// Output script of the Taproot lock with the MuSig2 aggregate key of both participants as
+/// internal key and the script tree committed with `merkle_root`. The cooperative `buy` and
+/// `refund` spend it on the key path with the aggregate key tweaked by
+/// [`KeyAggregate::with_taproot_tweak`].
+pub fn lock_script_pubkey(key_agg: &KeyAggregate, merkle_root: Option<TapBranchHash>) -> Script {
+    Script::new_v1_p2tr(
+        &Secp256k1::verification_only(),
+        key_agg.aggregate_key(),
+        merkle_root,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::musig2::generate_nonce;
+
+    use bitcoin::hashes::Hash;
+    use bitcoin::secp256k1::{Message, PublicKey, SecretKey};
+
+    #[test]
+    fn sign_lock_on_key_path() {
+        let secp = Secp256k1::new();
+        let sk_a = SecretKey::from_slice(&[1u8; 32]).unwrap();
+        let sk_b = SecretKey::from_slice(&[2u8; 32]).unwrap();
+        let pk_a = PublicKey::from_secret_key(&secp, &sk_a);
+        let pk_b = PublicKey::from_secret_key(&secp, &sk_b);
+        let msg = Message::from_slice(&[7u8; 32]).unwrap();
+        let merkle_root = Some(TapBranchHash::from_inner([3u8; 32]));
+
+        let key_agg = KeyAggregate::new(pk_a, pk_b).unwrap();
+        let script_pubkey = lock_script_pubkey(&key_agg, merkle_root);
+        let output_key = XOnlyPublicKey::from_slice(&script_pubkey[2..]).unwrap();
+
+        let key_agg = key_agg.with_taproot_tweak(merkle_root).unwrap();
+        assert_eq!(key_agg.aggregate_key(), output_key);
+        let (secnonce_a, nonce_a) = generate_nonce(&sk_a, &msg, b"session");
+        let (secnonce_b, nonce_b) = generate_nonce(&sk_b, &msg, b"session");
+        let nonces = [nonce_a, nonce_b];
+        let sig_a = key_agg
+            .partial_sign(&sk_a, secnonce_a, &nonces, &msg)
+            .unwrap();
+        let sig_b = key_agg
+            .partial_sign(&sk_b, secnonce_b, &nonces, &msg)
+            .unwrap();
+        let sig = key_agg
+            .aggregate_signatures(&nonces, &msg, &[sig_a, sig_b])
+            .unwrap();
+        assert!(secp.verify_schnorr(&sig, &msg, &output_key).is_ok());
+    }
+}
@@ -22,7 +22,8 @@ use std::fmt::Debug;
 use std::str::FromStr;
 
 /// An `OP_CSV` value (32-bits integer) to use in transactions and scripts.
-#[derive(PartialEq, Eq, PartialOrd, Clone, Debug, Hash, Copy, Display, Serialize, Deserialize)]
+#[derive(PartialEq, Eq, PartialOrd, Clone, Debug, Hash, Copy, Display)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[display("{0} blocks")]
 pub struct CSVTimelock(u32);
 
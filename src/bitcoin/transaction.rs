@@ -19,7 +19,8 @@
 use std::fmt::Debug;
 use std::marker::PhantomData;
 
-use bitcoin::blockdata::script::Script;
+use bitcoin::blockdata::opcodes;
+use bitcoin::blockdata::script::{Builder, Instruction, Script};
 use bitcoin::blockdata::transaction::{EcdsaSighashType, OutPoint, TxIn, TxOut};
 use bitcoin::util::address;
 use bitcoin::util::ecdsa::EcdsaSig;
@@ -34,7 +35,7 @@ use bitcoin::{
 use thiserror::Error;
 
 use crate::consensus::{self, CanonicalBytes};
-use crate::transaction::{Broadcastable, Error as FError, Finalizable, Linkable};
+use crate::transaction::{Broadcastable, Error as FError, Finalizable, Linkable, TxLabel};
 use bitcoin::hashes::sha256d::Hash as Sha256dHash;
 
 #[cfg(feature = "experimental")]
@@ -82,13 +83,140 @@ pub struct MetadataOutput {
     /// A transaction output which defines the value (in satoshis) and the `script_pubkey`.
     pub tx_out: TxOut,
     pub script_pubkey: Option<Script>,
+    /// The public key signing the spending input when the output is locked by a single key,
+    /// e.g. a funding output.
+    pub signing_key: Option<bitcoin::secp256k1::PublicKey>,
+}
+
+/// Amount in satoshis carried by an anchor output, as for Lightning anchor outputs.
+pub const ANCHOR_AMOUNT: u64 = 330;
+
+/// Returns the script pubkey of an anchor output, a P2WSH of `OP_TRUE` anyone can spend in a child
+/// transaction paying the fee of its parent (CPFP).
+pub fn anchor_script_pubkey() -> Script {
+    Builder::new()
+        .push_opcode(opcodes::OP_TRUE)
+        .into_script()
+        .to_v0_p2wsh()
+}
+
+/// Returns `true` if the transaction output is an anchor output.
+pub fn is_anchor(txout: &TxOut) -> bool {
+    txout.value == ANCHOR_AMOUNT && txout.script_pubkey == anchor_script_pubkey()
+}
+
+/// Maximum size in bytes of the data carried by a metadata output, the standard relay limit for
+/// `OP_RETURN` outputs.
+pub const MAX_METADATA_SIZE: usize = 80;
+
+/// Creates a metadata output, an unspendable `OP_RETURN` output carrying `data` with no value.
+/// Fails with [`FError::WrongTemplate`] if `data` exceeds [`MAX_METADATA_SIZE`] bytes.
+pub fn metadata_output(data: &[u8]) -> Result<TxOut, FError> {
+    if data.len() > MAX_METADATA_SIZE {
+        return Err(FError::WrongTemplate("Metadata exceeds the standard size"));
+    }
+    Ok(TxOut {
+        value: 0,
+        script_pubkey: Script::new_op_return(data),
+    })
+}
+
+/// Returns the data carried by the transaction output if it is a metadata output.
+pub fn metadata(txout: &TxOut) -> Option<&[u8]> {
+    if txout.value != 0 || !txout.script_pubkey.is_op_return() {
+        return None;
+    }
+    let mut instructions = txout.script_pubkey.instructions();
+    instructions.next();
+    match (instructions.next(), instructions.next()) {
+        (None, None) => Some(&[]),
+        (Some(Ok(Instruction::PushBytes(data))), None) if data.len() <= MAX_METADATA_SIZE => {
+            Some(data)
+        }
+        _ => None,
+    }
+}
+
+/// Splits the outputs following the first output of a transaction into an optional metadata
+/// output and an optional anchor output, in this order. Returns `None` if any other output is
+/// present.
+pub fn split_extra_outputs(outputs: &[TxOut]) -> Option<(Option<&TxOut>, Option<&TxOut>)> {
+    match outputs {
+        [] => Some((None, None)),
+        [anchor] if is_anchor(anchor) => Some((None, Some(anchor))),
+        [data] if metadata(data).is_some() => Some((Some(data), None)),
+        [data, anchor] if metadata(data).is_some() && is_anchor(anchor) => {
+            Some((Some(data), Some(anchor)))
+        }
+        _ => None,
+    }
+}
+
+/// Prefix of the proprietary keys used to store swap data in the inputs of a
+/// `PartiallySignedTransaction`.
+pub const PSBT_PROPRIETARY_PREFIX: &[u8] = b"farcaster";
+
+/// Proprietary subtype of the key storing the public key expected to sign an input.
+pub const PSBT_SIGNING_KEY_SUBTYPE: u8 = 0x00;
+
+fn signing_key_proprietary_key() -> psbt::raw::ProprietaryKey {
+    psbt::raw::ProprietaryKey {
+        prefix: PSBT_PROPRIETARY_PREFIX.to_vec(),
+        subtype: PSBT_SIGNING_KEY_SUBTYPE,
+        key: vec![],
+    }
+}
+
+/// Records the public key expected to sign the input in a proprietary key of the input.
+pub fn set_signing_key(input: &mut psbt::Input, key: bitcoin::secp256k1::PublicKey) {
+    input
+        .proprietary
+        .insert(signing_key_proprietary_key(), key.serialize().to_vec());
+}
+
+/// Returns the public key expected to sign the input if recorded with [`set_signing_key`].
+pub fn signing_key(input: &psbt::Input) -> Option<bitcoin::secp256k1::PublicKey> {
+    input
+        .proprietary
+        .get(&signing_key_proprietary_key())
+        .and_then(|key| bitcoin::secp256k1::PublicKey::from_slice(key).ok())
 }
 
 /// Defines the inner behaviour of a generic transaction [`Tx`].
 pub trait SubTransaction: Debug {
+    /// The Farcaster transaction identifier of the generic transaction [`Tx`].
+    const LABEL: TxLabel;
+
     /// Defines the behaviour for finalizing the `PartiallySignedTransaction` from a generic
     /// transaction [`Tx`].
     fn finalize(psbt: &mut PartiallySignedTransaction) -> Result<(), FError>;
+
+    /// Returns the public keys expected to sign each input of the `PartiallySignedTransaction`,
+    /// in input order. An input with unknown signers has no key.
+    fn signing_keys(
+        psbt: &PartiallySignedTransaction,
+    ) -> Result<Vec<Vec<bitcoin::secp256k1::PublicKey>>, FError>;
+}
+
+/// Returns the index and the public key of each expected signature not yet added to the
+/// `PartiallySignedTransaction` of the generic transaction [`Tx`].
+pub fn missing_signatures<T: SubTransaction>(
+    psbt: &PartiallySignedTransaction,
+) -> Result<Vec<(usize, bitcoin::secp256k1::PublicKey)>, FError> {
+    Ok(T::signing_keys(psbt)?
+        .into_iter()
+        .zip(psbt.inputs.iter())
+        .enumerate()
+        .flat_map(|(index, (keys, input))| {
+            keys.into_iter()
+                .filter(|key| {
+                    !input
+                        .partial_sigs
+                        .contains_key(&bitcoin::PublicKey::new(*key))
+                })
+                .map(move |key| (index, key))
+        })
+        .collect())
 }
 
 /// A general purpose Bitcoin transaction used in a swap context. This implements
@@ -96,6 +224,9 @@ pub trait SubTransaction: Debug {
 #[derive(Debug)]
 pub struct Tx<T: SubTransaction> {
     pub(crate) psbt: PartiallySignedTransaction,
+    /// Precomputed witness messages of each input, cleared when the partial transaction is
+    /// mutably borrowed.
+    pub(crate) witness_messages: Option<Vec<Sha256dHash>>,
     pub(crate) _t: PhantomData<T>,
 }
 
@@ -105,11 +236,15 @@ impl<T> Transaction<PartiallySignedTransaction, MetadataOutput, Amount> for Tx<T
 where
     T: SubTransaction,
 {
+    const LABEL: TxLabel = T::LABEL;
+
     fn as_partial(&self) -> &PartiallySignedTransaction {
         &self.psbt
     }
 
     fn as_partial_mut(&mut self) -> &mut PartiallySignedTransaction {
+        // The transaction may be modified, cached witness messages can be stale
+        self.witness_messages = None;
         &mut self.psbt
     }
 
@@ -120,6 +255,7 @@ where
     fn from_partial(partial: PartiallySignedTransaction) -> Self {
         Self {
             psbt: partial,
+            witness_messages: None,
             _t: PhantomData,
         }
     }
@@ -129,6 +265,7 @@ where
             out_point: self.psbt.unsigned_tx.input[0].previous_output,
             tx_out: self.psbt.inputs[0].witness_utxo.clone().unwrap(), // FIXME
             script_pubkey: self.psbt.inputs[0].witness_script.clone(),
+            signing_key: signing_key(&self.psbt.inputs[0]),
         }
     }
 
@@ -141,7 +278,19 @@ impl<T> Finalizable for Tx<T>
 where
     T: SubTransaction,
 {
+    /// Fails with [`FError::MissingSignatures`] listing the inputs still missing signatures
+    /// before finalizing, see [`Witnessable::missing_witnesses`] for the expected keys.
+    ///
+    /// [`Witnessable::missing_witnesses`]: crate::transaction::Witnessable::missing_witnesses
     fn finalize(&mut self) -> Result<(), FError> {
+        let mut inputs: Vec<usize> = missing_signatures::<T>(&self.psbt)?
+            .into_iter()
+            .map(|(index, _)| index)
+            .collect();
+        if !inputs.is_empty() {
+            inputs.dedup();
+            return Err(FError::MissingSignatures(inputs));
+        }
         T::finalize(&mut self.psbt)
     }
 }
@@ -160,13 +309,11 @@ where
     T: SubTransaction,
 {
     fn get_consumable_output(&self) -> Result<MetadataOutput, FError> {
-        match self.psbt.unsigned_tx.output.len() {
-            1 => (),
-            2 => {
-                if !self.psbt.unsigned_tx.is_coin_base() {
-                    return Err(FError::new(Error::MultiUTXOUnsuported));
-                }
-            }
+        let outputs = &self.psbt.unsigned_tx.output;
+        // The first output can be followed by a metadata output and an anchor to pay the fee
+        let is_coinbase = self.psbt.unsigned_tx.is_coin_base() && outputs.len() == 2;
+        match outputs.split_first() {
+            Some((_, extra)) if is_coinbase || split_extra_outputs(extra).is_some() => (),
             _ => return Err(FError::new(Error::MultiUTXOUnsuported)),
         }
 
@@ -174,6 +321,7 @@ where
             out_point: OutPoint::new(self.psbt.unsigned_tx.txid(), 0),
             tx_out: self.psbt.unsigned_tx.output[0].clone(),
             script_pubkey: self.psbt.outputs[0].witness_script.clone(),
+            signing_key: None,
         })
     }
 }
@@ -186,28 +334,77 @@ where
 {
     /// ## Safety
     /// This function is used for generating the witness message for all transactions but not
-    /// funding. So implying only 1 input is valid as all templates only have 1 input.
+    /// funding. So implying only 1 input is valid as all templates only have 1 input, except the
+    /// lock that can spend multiple funding outputs, see [`Tx::generate_input_witness_message`].
     fn generate_witness_message(&self, _path: ScriptPath) -> Result<Sha256dHash, FError> {
-        let unsigned_tx = self.psbt.unsigned_tx.clone();
-        let txin = TxInRef::new(&unsigned_tx, 0);
-
-        let witness_utxo = self.psbt.inputs[0]
-            .witness_utxo
-            .clone()
-            .ok_or(FError::MissingWitness)?;
-
-        let script = self.psbt.inputs[0]
-            .witness_script
-            .clone()
-            .ok_or(FError::MissingWitness)?;
+        self.generate_input_witness_message(0)
+    }
+
+    fn add_witness(&mut self, pubkey: PublicKey, sig: Signature) -> Result<(), FError> {
+        self.add_input_witness(0, pubkey, sig)
+    }
+
+    fn missing_witnesses(&self) -> Result<Vec<(usize, PublicKey)>, FError> {
+        missing_signatures::<T>(&self.psbt)
+    }
+}
+
+#[cfg(feature = "experimental")]
+#[cfg_attr(docsrs, doc(cfg(feature = "experimental")))]
+impl<T> Tx<T>
+where
+    T: SubTransaction,
+{
+    /// Generate the witness message to sign for the input at the given index, or return the
+    /// precomputed one, see [`Tx::precompute_witness_messages`]. Fails with
+    /// [`FError::MissingWitness`] if the input does not exist or lacks its witness data.
+    pub fn generate_input_witness_message(&self, index: usize) -> Result<Sha256dHash, FError> {
+        if let Some(msg) = self
+            .witness_messages
+            .as_ref()
+            .and_then(|messages| messages.get(index))
+        {
+            return Ok(*msg);
+        }
+        self.compute_input_witness_message(index)
+    }
+
+    /// Compute and cache the witness messages of all inputs, later calls to generate witness
+    /// messages return the cached values instead of recomputing the signature hashes when
+    /// signing repeatedly. The cache is cleared whenever the partial transaction is mutably
+    /// borrowed with [`Transaction::as_partial_mut`], so a stale message is never returned.
+    pub fn precompute_witness_messages(&mut self) -> Result<(), FError> {
+        let messages = (0..self.psbt.inputs.len())
+            .map(|index| self.compute_input_witness_message(index))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.witness_messages = Some(messages);
+        Ok(())
+    }
+
+    fn compute_input_witness_message(&self, index: usize) -> Result<Sha256dHash, FError> {
+        let input = self.psbt.inputs.get(index).ok_or(FError::MissingWitness)?;
+        let txin = TxInRef::new(&self.psbt.unsigned_tx, index);
+
+        let witness_utxo = input.witness_utxo.clone().ok_or(FError::MissingWitness)?;
+        let script = input.witness_script.clone().ok_or(FError::MissingWitness)?;
         let value = witness_utxo.value;
 
         Ok(signature_hash(txin, &script, value, EcdsaSighashType::All))
     }
 
-    fn add_witness(&mut self, pubkey: PublicKey, sig: Signature) -> Result<(), FError> {
+    /// Add a signature for the input at the given index. Fails with [`FError::MissingWitness`]
+    /// if the input does not exist.
+    pub fn add_input_witness(
+        &mut self,
+        index: usize,
+        pubkey: PublicKey,
+        sig: Signature,
+    ) -> Result<(), FError> {
         let sig_all = EcdsaSig::sighash_all(sig);
-        self.psbt.inputs[0]
+        self.psbt
+            .inputs
+            .get_mut(index)
+            .ok_or(FError::MissingWitness)?
             .partial_sigs
             .insert(bitcoin::PublicKey::new(pubkey), sig_all);
         Ok(())
@@ -24,29 +24,21 @@
 use std::error;
 use std::fmt::{self, Debug};
 use std::io;
+use std::iter;
 use std::str::FromStr;
 
+#[cfg(feature = "strict-encoding")]
 use strict_encoding::{StrictDecode, StrictEncode};
 use thiserror::Error;
 
 use crate::consensus::{self, deserialize, serialize, CanonicalBytes, Decodable, Encodable};
+use crate::trade;
 use crate::transaction::{Buyable, Cancelable, Fundable, Lockable, Punishable, Refundable};
 
 /// The list of supported blockchains (coins) by this library.
-#[derive(
-    Debug,
-    Clone,
-    Copy,
-    Hash,
-    PartialEq,
-    Eq,
-    Parser,
-    Display,
-    Serialize,
-    Deserialize,
-    StrictEncode,
-    StrictDecode,
-)]
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Parser, Display)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "strict-encoding", derive(StrictEncode, StrictDecode))]
 #[display(Debug)]
 pub enum Blockchain {
     /// The Bitcoin (BTC) blockchain.
@@ -67,6 +59,42 @@ impl FromStr for Blockchain {
     }
 }
 
+impl Blockchain {
+    /// Returns the ticker of the blockchain native asset, e.g. `BTC` for Bitcoin.
+    pub fn ticker(&self) -> &'static str {
+        match self {
+            Blockchain::Bitcoin => "BTC",
+            Blockchain::Monero => "XMR",
+        }
+    }
+
+    /// Returns the number of decimals between the atomic unit and the main unit of the native
+    /// asset, e.g. `8` for Bitcoin.
+    pub fn decimals(&self) -> u32 {
+        match self {
+            Blockchain::Bitcoin => 8,
+            Blockchain::Monero => 12,
+        }
+    }
+
+    /// Render an amount given in atomic units of the native asset as a human readable string
+    /// with all the decimals and the ticker, e.g. `0.00005000 BTC`.
+    pub fn format_amount(&self, atomic_units: u64) -> String {
+        format_atomic_units(atomic_units, self.decimals(), self.ticker())
+    }
+}
+
+fn format_atomic_units(units: u64, decimals: u32, ticker: &str) -> String {
+    let factor = 10u64.pow(decimals);
+    format!(
+        "{}.{:0width$} {}",
+        units / factor,
+        units % factor,
+        ticker,
+        width = decimals as usize
+    )
+}
+
 impl Decodable for Blockchain {
     fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
         match Decodable::consensus_decode(d)? {
@@ -102,7 +130,7 @@ pub trait Transactions {
     type Si;
 
     /// Defines the type for the `funding (a)` transaction
-    type Funding: Fundable<Self::Tx, Self::Out, Self::Addr, Self::Pk>;
+    type Funding: Fundable<Self::Tx, Self::Out, Self::Addr, Self::Pk, Self::Amt>;
     /// Defines the type for the `lock (b)` transaction
     type Lock: Lockable<
         Self::Addr,
@@ -165,18 +193,42 @@ pub trait Transactions {
     >;
 }
 
+/// Abstract the connection to a blockchain backend, e.g. Electrum, Bitcoin Core RPC, or a Monero
+/// daemon, used to watch for the funding transaction, retrieve transactions, and broadcast
+/// finalized transactions.
+///
+/// Transactions returned by [`ChainWatcher::watch_address`] can be fed to [`Fundable::update`].
+pub trait ChainWatcher<Addr, Tx, TxId> {
+    /// Error returned by the backend.
+    type Error: error::Error;
+
+    /// Start watching `address` and return the first transaction seen paying to it, if any.
+    fn watch_address(&mut self, address: &Addr) -> Result<Option<Tx>, Self::Error>;
+
+    /// Retrieve a transaction by its identifier, `None` if the transaction is unknown.
+    fn get_tx(&self, txid: &TxId) -> Result<Option<Tx>, Self::Error>;
+
+    /// Broadcast a finalized transaction and return its identifier.
+    fn broadcast(&mut self, tx: Tx) -> Result<TxId, Self::Error>;
+}
+
 /// A fee strategy to be applied on an arbitrating transaction. As described in the specifications
 /// a fee strategy can be: fixed or range. When the fee strategy allows multiple possibilities, a
-/// [`FeePriority`] is used to determine what to apply.
+/// [`FeePriority`] is used to determine what to apply. The none strategy is used when fees are paid
+/// externally, e.g. by a child transaction spending an anchor output.
 ///
 /// A fee strategy is included in a deal, so Alice and Bob can verify that transactions are valid
 /// upon reception by the other participant.
-#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum FeeStrategy<T> {
     /// A fixed strategy with the exact amount to set.
     Fixed(T),
     /// A range with a minimum and maximum (inclusive) possible fees.
     Range { min_inc: T, max_inc: T },
+    /// No fee, transactions must carry an anchor output for a child transaction to pay the fee
+    /// (CPFP).
+    None,
 }
 
 impl<T> FeeStrategy<T>
@@ -188,8 +240,55 @@ where
             Self::Fixed(fee_strat) => value == fee_strat,
             // Check in range including min and max bounds
             Self::Range { min_inc, max_inc } => value >= min_inc && value <= max_inc,
+            // No rate is allowed, zero fee is validated by the fee implementation
+            Self::None => false,
         }
     }
+
+    /// Return `true` if the rate is allowed by the strategy, same as [`Self::check`].
+    pub fn contains(&self, rate: &T) -> bool {
+        self.check(rate)
+    }
+}
+
+/// Addition returning `None` instead of overflowing, used to step through the rates of a
+/// [`FeeStrategy`] without panicking on large values.
+pub trait CheckedAdd: Sized {
+    /// Add `rhs` to `self`, return `None` if the result overflows.
+    fn checked_add(&self, rhs: &Self) -> Option<Self>;
+}
+
+impl CheckedAdd for u64 {
+    fn checked_add(&self, rhs: &Self) -> Option<Self> {
+        u64::checked_add(*self, *rhs)
+    }
+}
+
+impl<T> FeeStrategy<T>
+where
+    T: Clone + PartialOrd + CheckedAdd,
+{
+    /// Iterate over the discrete rates permitted by the strategy, starting at the minimum and
+    /// incrementing by `step` while the maximum is not exceeded. A fixed strategy yields its single
+    /// rate. The maximum is not yielded if `step` does not evenly divide the range, and only the
+    /// minimum is yielded if `step` does not increase the rate. The iteration ends if the next rate
+    /// overflows. The none strategy yields nothing.
+    pub fn iter_rates(&self, step: T) -> impl Iterator<Item = T> {
+        let (mut next, max_inc) = match self {
+            Self::Fixed(rate) => (Some(rate.clone()), Some(rate.clone())),
+            Self::Range { min_inc, max_inc } => (Some(min_inc.clone()), Some(max_inc.clone())),
+            Self::None => (None, None),
+        };
+        iter::from_fn(move || {
+            let rate = next
+                .take()
+                .filter(|rate| max_inc.as_ref().map_or(false, |max_inc| rate <= max_inc))?;
+            next = rate
+                .checked_add(&step)
+                .filter(|following| following > &rate);
+            Some(rate)
+        })
+    }
 }
 
 impl<T> FromStr for FeeStrategy<T>
@@ -199,6 +298,9 @@ where
     type Err = consensus::Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("none") {
+            return Ok(Self::None);
+        }
         let mut parts: Vec<&str> = s.split('-').collect();
         match parts.len() {
             1 => match s.parse::<T>() {
@@ -233,6 +335,7 @@ where
             FeeStrategy::Range { min_inc, max_inc } => {
                 write!(f, "{}-{}", min_inc, max_inc)
             }
+            FeeStrategy::None => write!(f, "none"),
         }
     }
 }
@@ -252,6 +355,7 @@ where
                 len += min_inc.as_canonical_bytes().consensus_encode(writer)?;
                 Ok(len + max_inc.as_canonical_bytes().consensus_encode(writer)?)
             }
+            FeeStrategy::None => 0x03u8.consensus_encode(writer),
         }
     }
 }
@@ -270,6 +374,7 @@ where
                 let max_inc = T::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?;
                 Ok(FeeStrategy::Range { min_inc, max_inc })
             }
+            0x03u8 => Ok(FeeStrategy::None),
             _ => Err(consensus::Error::UnknownType),
         }
     }
@@ -309,6 +414,9 @@ pub enum FeeStrategyError {
     /// Not enough assets to cover the fees.
     #[error("Not enough assets to cover the fees")]
     NotEnoughAssets,
+    /// The none fee strategy is used on a transaction without an anchor output to pay the fee.
+    #[error("Missing anchor output to pay the fee")]
+    MissingAnchorOutput,
     /// Any fee strategy error not part of this list.
     #[error("Other: {0}")]
     Other(Box<dyn error::Error + Sync + Send>),
@@ -339,7 +447,8 @@ impl FeeStrategyError {
 }
 
 /// Defines how to set the fee when a [`FeeStrategy`] allows multiple possibilities.
-#[derive(Debug, Clone, Copy, Display, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Display)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[display(Debug)]
 pub enum FeePriority {
     /// Set the fee at the minimum allowed by the strategy.
@@ -392,6 +501,7 @@ impl FromStr for FeePriority {
 /// use bitcoin::util::psbt::PartiallySignedTransaction;
 /// use farcaster_core::crypto::SharedKeyId;
 /// use farcaster_core::blockchain::{Fee, FeeStrategy, FeePriority, FeeStrategyError};
+/// use farcaster_core::transaction;
 ///
 /// pub struct Psbt(PartiallySignedTransaction);
 /// pub struct SatPerBytes(f32);
@@ -414,6 +524,17 @@ impl FromStr for FeePriority {
 ///     ) -> Result<bool, FeeStrategyError> {
 ///         todo!()
 ///     }
+///
+///     fn validate_rate(
+///         &self,
+///         strategy: &FeeStrategy<SatPerBytes>
+///     ) -> Result<(), transaction::Error> {
+///         todo!()
+///     }
+///
+///     fn dust_limit(&self) -> Self::Amount {
+///         todo!()
+///     }
 /// }
 /// ```
 pub trait Fee {
@@ -434,6 +555,118 @@ pub trait Fee {
     /// Validates that the fee for the given transaction are set accordingly to the strategy.
     fn validate_fee(&self, strategy: &FeeStrategy<Self::FeeUnit>)
         -> Result<bool, FeeStrategyError>;
+
+    /// Computes the effective fee rate paid by the transaction and validates it against the
+    /// strategy. Returns [`transaction::Error::FeeOutOfBounds`] when the rate is out of the
+    /// strategy bounds.
+    ///
+    /// [`transaction::Error::FeeOutOfBounds`]: crate::transaction::Error::FeeOutOfBounds
+    fn validate_rate(
+        &self,
+        strategy: &FeeStrategy<Self::FeeUnit>,
+    ) -> Result<(), crate::transaction::Error>;
+
+    /// Returns the minimum amount the outputs of the transaction must carry to be relayed by the
+    /// network, outputs below this limit are considered dust.
+    fn dust_limit(&self) -> Self::Amount;
+}
+
+/// An amount of assets expressed in its smallest indivisible unit, e.g. satoshi for bitcoin or
+/// piconero for monero. Used to convert amounts between two assets with a [`Price`].
+pub trait AssetUnit: Sized {
+    /// The blockchain of the asset.
+    const BLOCKCHAIN: Blockchain;
+
+    /// Returns the amount in atomic units.
+    fn as_atomic_units(&self) -> u64;
+
+    /// Creates an amount from a number of atomic units.
+    fn from_atomic_units(units: u64) -> Self;
+
+    /// Render the amount as a human readable string with all the decimals of the asset and its
+    /// ticker, e.g. `0.00005000 BTC` or `0.000000000006 XMR`.
+    fn format_amount(&self) -> String {
+        Self::BLOCKCHAIN.format_amount(self.as_atomic_units())
+    }
+}
+
+/// Exchange rate between an arbitrating and an accordant asset, defined by a pair of amounts
+/// considered of equal value. The price is kept as a ratio of atomic units to avoid any loss of
+/// precision, conversions round down to the nearest atomic unit.
+///
+/// ```
+/// use farcaster_core::blockchain::Price;
+///
+/// // 1 BTC is worth 150 XMR
+/// let price = Price::new(bitcoin::Amount::ONE_BTC, monero::Amount::from_pico(150_000_000_000_000))
+///     .unwrap();
+/// let half_btc = bitcoin::Amount::from_sat(50_000_000);
+/// assert_eq!(
+///     price.accordant_amount(half_btc),
+///     Some(monero::Amount::from_pico(75_000_000_000_000))
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Price<Amt, Bmt> {
+    arbitrating: Amt,
+    accordant: Bmt,
+}
+
+impl<Amt, Bmt> Price<Amt, Bmt>
+where
+    Amt: AssetUnit + Copy,
+    Bmt: AssetUnit + Copy,
+{
+    /// Creates a price where `arbitrating` is worth `accordant`. Returns `None` if one of the
+    /// amounts is zero.
+    pub fn new(arbitrating: Amt, accordant: Bmt) -> Option<Self> {
+        if arbitrating.as_atomic_units() == 0 || accordant.as_atomic_units() == 0 {
+            return None;
+        }
+        Some(Self {
+            arbitrating,
+            accordant,
+        })
+    }
+
+    /// Returns the reference amount of arbitrating assets.
+    pub fn arbitrating(&self) -> Amt {
+        self.arbitrating
+    }
+
+    /// Returns the reference amount of accordant assets.
+    pub fn accordant(&self) -> Bmt {
+        self.accordant
+    }
+
+    /// Computes the amount of accordant assets worth `amount` of arbitrating assets. Returns
+    /// `None` if the result overflows.
+    pub fn accordant_amount(&self, amount: Amt) -> Option<Bmt> {
+        convert(
+            amount.as_atomic_units(),
+            self.accordant.as_atomic_units(),
+            self.arbitrating.as_atomic_units(),
+        )
+        .map(Bmt::from_atomic_units)
+    }
+
+    /// Computes the amount of arbitrating assets worth `amount` of accordant assets. Returns
+    /// `None` if the result overflows.
+    pub fn arbitrating_amount(&self, amount: Bmt) -> Option<Amt> {
+        convert(
+            amount.as_atomic_units(),
+            self.arbitrating.as_atomic_units(),
+            self.accordant.as_atomic_units(),
+        )
+        .map(Amt::from_atomic_units)
+    }
+}
+
+// Computes `amount * num / den` without intermediate overflow, fails if the result does not fit
+// in 64 bits.
+fn convert(amount: u64, num: u64, den: u64) -> Option<u64> {
+    let res = (amount as u128).checked_mul(num as u128)? / den as u128;
+    u64::try_from(res).ok()
 }
 
 impl FromStr for Network {
@@ -475,9 +708,8 @@ impl FromStr for Network {
 ///     }
 /// }
 /// ```
-#[derive(
-    Copy, PartialEq, Eq, PartialOrd, Ord, Clone, Hash, Debug, Display, Serialize, Deserialize,
-)]
+#[derive(Copy, PartialEq, Eq, PartialOrd, Ord, Clone, Hash, Debug, Display)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[display(Debug)]
 pub enum Network {
     /// Valuable, real, assets on its production network.
@@ -511,10 +743,104 @@ impl Decodable for Network {
 
 impl_strict_encoding!(Network);
 
+/// Retrieve the [`Network`] an element, such as an address, is bound to. Used to validate that
+/// elements received from a counter-party are consistent with the network agreed in the deal.
+pub trait Networked {
+    /// Returns the network the element is valid on.
+    fn network(&self) -> Network;
+}
+
+/// Maps the generic [`Network`] to the concrete networks of the arbitrating and accordant
+/// blockchains of a swap pair, and back. Used to validate that both blockchains of a swap run on
+/// compatible networks, e.g. Bitcoin testnet with Monero stagenet.
+pub trait NetworkCompat {
+    /// The network type of the arbitrating blockchain.
+    type ArbitratingNetwork: Copy + Into<Network>;
+    /// The network type of the accordant blockchain.
+    type AccordantNetwork: Copy + PartialEq + Into<Network>;
+
+    /// Returns the canonical arbitrating network used for the generic network.
+    fn to_arbitrating_network(network: Network) -> Self::ArbitratingNetwork;
+
+    /// Returns the canonical accordant network used for the generic network.
+    fn to_accordant_network(network: Network) -> Self::AccordantNetwork;
+
+    /// Returns the generic network both blockchain networks agree on. The arbitrating network
+    /// decides the generic network, the accordant network must either map to it or be its
+    /// canonical accordant network, e.g. a local network may use the accordant main network.
+    /// Fails with [`trade::Error::IncompatibleNetworks`] if the networks cannot be paired.
+    fn agreed_network(
+        arbitrating: Self::ArbitratingNetwork,
+        accordant: Self::AccordantNetwork,
+    ) -> Result<Network, trade::Error> {
+        let network: Network = arbitrating.into();
+        if accordant.into() == network || Self::to_accordant_network(network) == accordant {
+            Ok(network)
+        } else {
+            Err(trade::Error::IncompatibleNetworks {
+                arbitrating: network,
+                accordant: accordant.into(),
+            })
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::bitcoin::fee::SatPerVByte;
+    use crate::swap::btcxmr::BtcXmr;
+
+    #[test]
+    fn iterate_over_fee_strategy_rates() {
+        let strategy = FeeStrategy::Range {
+            min_inc: 10u64,
+            max_inc: 20u64,
+        };
+        // The step does not evenly divide the span, the maximum is not reached
+        assert_eq!(
+            strategy.iter_rates(3).collect::<Vec<_>>(),
+            vec![10, 13, 16, 19]
+        );
+        assert_eq!(strategy.iter_rates(5).collect::<Vec<_>>(), vec![10, 15, 20]);
+        assert_eq!(strategy.iter_rates(0).collect::<Vec<_>>(), vec![10]);
+        assert!(strategy.iter_rates(3).all(|rate| strategy.contains(&rate)));
+        assert!(!strategy.contains(&21));
+        assert!(!strategy.contains(&9));
+
+        let strategy = FeeStrategy::Fixed(SatPerVByte::from_sat(20));
+        assert_eq!(
+            strategy
+                .iter_rates(SatPerVByte::from_sat(1))
+                .collect::<Vec<_>>(),
+            vec![SatPerVByte::from_sat(20)]
+        );
+        assert!(strategy.contains(&SatPerVByte::from_sat(20)));
+        assert!(!strategy.contains(&SatPerVByte::from_sat(21)));
+
+        // Stepping past the maximum value ends the iteration instead of overflowing
+        let strategy = FeeStrategy::Range {
+            min_inc: SatPerVByte::from_sat(u64::MAX - 10),
+            max_inc: SatPerVByte::from_sat(u64::MAX),
+        };
+        assert_eq!(
+            strategy
+                .iter_rates(SatPerVByte::from_sat(8))
+                .collect::<Vec<_>>(),
+            vec![
+                SatPerVByte::from_sat(u64::MAX - 10),
+                SatPerVByte::from_sat(u64::MAX - 2)
+            ]
+        );
+        let strategy = FeeStrategy::Range {
+            min_inc: u64::MAX - 1,
+            max_inc: u64::MAX,
+        };
+        assert_eq!(
+            strategy.iter_rates(u64::MAX).collect::<Vec<_>>(),
+            vec![u64::MAX - 1]
+        );
+    }
 
     #[test]
     fn parse_fee_politic() {
@@ -619,6 +945,7 @@ mod tests {
                 min_inc: SatPerVByte::from_sat(1),
                 max_inc: SatPerVByte::from_sat(7),
             },
+            FeeStrategy::None,
         ];
         for strat in strats.iter() {
             assert_eq!(
@@ -628,6 +955,27 @@ mod tests {
         }
     }
 
+    #[test]
+    fn fee_strategy_none_encoding() {
+        let strategy: FeeStrategy<SatPerVByte> = FeeStrategy::None;
+        assert_eq!(serialize(&strategy), vec![0x03]);
+        assert_eq!(
+            deserialize::<FeeStrategy<SatPerVByte>>(&[0x03]).unwrap(),
+            FeeStrategy::None
+        );
+        assert_eq!(
+            FeeStrategy::<SatPerVByte>::from_str("None").unwrap(),
+            FeeStrategy::None
+        );
+        assert!(!strategy.check(&SatPerVByte::from_sat(0)));
+        assert_eq!(strategy.iter_rates(SatPerVByte::from_sat(1)).count(), 0);
+        // Zero rates are rejected, a fixed strategy cannot express a zero fee
+        assert!(deserialize::<FeeStrategy<SatPerVByte>>(&[
+            0x01, 0x08, 0x00, 0, 0, 0, 0, 0, 0, 0, 0
+        ])
+        .is_err());
+    }
+
     #[test]
     fn fee_strategy_check_range() {
         let strategy = FeeStrategy::Range {
@@ -639,4 +987,89 @@ mod tests {
         assert!(strategy.check(&SatPerVByte::from_sat(150)));
         assert!(!strategy.check(&SatPerVByte::from_sat(151)));
     }
+
+    #[test]
+    fn format_amounts() {
+        assert_eq!(bitcoin::Amount::ZERO.format_amount(), "0.00000000 BTC");
+        assert_eq!(
+            bitcoin::Amount::from_sat(5000).format_amount(),
+            "0.00005000 BTC"
+        );
+        assert_eq!(
+            bitcoin::Amount::from_sat(2_100_000_000_000_000).format_amount(),
+            "21000000.00000000 BTC"
+        );
+        assert_eq!(
+            Blockchain::Bitcoin.format_amount(u64::MAX),
+            "184467440737.09551615 BTC"
+        );
+
+        assert_eq!(
+            monero::Amount::from_pico(0).format_amount(),
+            "0.000000000000 XMR"
+        );
+        assert_eq!(
+            monero::Amount::from_pico(6).format_amount(),
+            "0.000000000006 XMR"
+        );
+        assert_eq!(
+            monero::Amount::from_pico(18_446_744_073_709_551_615).format_amount(),
+            "18446744.073709551615 XMR"
+        );
+    }
+
+    #[test]
+    fn btc_xmr_network_pairings() {
+        for network in [Network::Mainnet, Network::Testnet, Network::Local] {
+            let arbitrating = BtcXmr::to_arbitrating_network(network);
+            let accordant = BtcXmr::to_accordant_network(network);
+            assert_eq!(
+                BtcXmr::agreed_network(arbitrating, accordant).unwrap(),
+                network
+            );
+        }
+        let valid = [
+            (
+                bitcoin::Network::Bitcoin,
+                monero::Network::Mainnet,
+                Network::Mainnet,
+            ),
+            (
+                bitcoin::Network::Testnet,
+                monero::Network::Stagenet,
+                Network::Testnet,
+            ),
+            (
+                bitcoin::Network::Testnet,
+                monero::Network::Testnet,
+                Network::Testnet,
+            ),
+            (
+                bitcoin::Network::Signet,
+                monero::Network::Stagenet,
+                Network::Testnet,
+            ),
+            (
+                bitcoin::Network::Regtest,
+                monero::Network::Mainnet,
+                Network::Local,
+            ),
+        ];
+        for (arbitrating, accordant, network) in valid {
+            assert_eq!(
+                BtcXmr::agreed_network(arbitrating, accordant).unwrap(),
+                network
+            );
+        }
+        assert!(matches!(
+            BtcXmr::agreed_network(bitcoin::Network::Bitcoin, monero::Network::Testnet),
+            Err(trade::Error::IncompatibleNetworks {
+                arbitrating: Network::Mainnet,
+                accordant: Network::Testnet,
+            })
+        ));
+        assert!(
+            BtcXmr::agreed_network(bitcoin::Network::Regtest, monero::Network::Stagenet).is_err()
+        );
+    }
 }
@@ -20,6 +20,13 @@
 //! Implementation on blockchain foreign types with [`CanonicalBytes`] must follow the strict
 //! consensus encoding from the blockchain itself, Farcaster core will then wrap the serialization
 //! and treat it as a length prefixed vector of bytes when needed.
+//!
+//! ## Byte order
+//!
+//! All multi-byte integers are encoded in **little-endian** byte order, e.g. the `lock` transaction
+//! label tag `0x0002` is encoded as `02 00`. Vectors of bytes are prefixed with their length encoded
+//! as a little-endian `u16`. Amounts and timelocks of the Bitcoin and Monero blockchains follow the
+//! consensus encoding of their blockchain, which is little-endian too.
 
 use hex::encode as hex_encode;
 use thiserror::Error;
@@ -28,8 +35,27 @@ use std::error;
 use std::io;
 use std::str;
 
+use bitcoin::bech32::{self, FromBase32, ToBase32, Variant};
 use bitcoin::secp256k1::PublicKey;
 
+/// Derive [`CanonicalBytes`] on a struct by concatenating the length prefixed canonical bytes of
+/// its fields in declaration order. Decoding fails if the buffer is not entirely consumed.
+///
+/// ```
+/// use farcaster_core::consensus::CanonicalBytes;
+///
+/// #[derive(Debug, PartialEq, CanonicalBytes)]
+/// struct Wrapper {
+///     id: [u8; 4],
+///     name: String,
+/// }
+///
+/// let wrapper = Wrapper { id: [1, 2, 3, 4], name: "farcaster".into() };
+/// let bytes = wrapper.as_canonical_bytes();
+/// assert_eq!(Wrapper::from_canonical_bytes(&bytes).unwrap(), wrapper);
+/// ```
+pub use farcaster_core_derive::CanonicalBytes;
+
 /// Encoding and decoding errors and data transformation errors (when converting data from one
 /// message type to another).
 #[derive(Error, Debug)]
@@ -49,6 +75,26 @@ pub enum Error {
     /// A generic parsing error.
     #[error("Parsing error: {0}")]
     ParseFailed(&'static str),
+    /// The canonical bytes do not have the length expected for the type.
+    #[error("Invalid length: expected {expected} bytes, found {found}")]
+    InvalidLength {
+        /// The number of bytes expected.
+        expected: usize,
+        /// The number of bytes received.
+        found: usize,
+    },
+    /// The canonical bytes have the correct length but do not represent a valid element, e.g. not
+    /// a valid curve point or scalar.
+    #[error("Invalid encoding: {0}")]
+    InvalidEncoding(&'static str),
+    /// A field of a composite element failed to decode, e.g. a deal with an unknown maker role.
+    #[error("Invalid {field}: {source}")]
+    InvalidField {
+        /// The name of the offending field.
+        field: &'static str,
+        /// The error raised while decoding the field.
+        source: Box<Error>,
+    },
     /// Any Consensus error not part of this list.
     #[error("Consensus error: {0}")]
     Other(Box<dyn error::Error + Send + Sync>),
@@ -64,6 +110,14 @@ impl Error {
         Self::Other(error.into())
     }
 
+    /// Wraps the error into [`Self::InvalidField`] to name the field that failed to decode.
+    pub fn in_field(self, field: &'static str) -> Self {
+        Self::InvalidField {
+            field,
+            source: Box::new(self),
+        }
+    }
+
     /// Consumes the `Error`, returning its inner error (if any).
     ///
     /// If this [`enum@Error`] was constructed via [`new`] then this function will return [`Some`],
@@ -116,6 +170,40 @@ where
     }
 }
 
+/// Check that a canonical bytes representation has the expected length, return an
+/// [`Error::InvalidLength`] otherwise. Used by [`CanonicalBytes`] implementations to distinguish a
+/// corrupted frame from an invalid but well-formed element.
+pub fn check_length(bytes: &[u8], expected: usize) -> Result<(), Error> {
+    match bytes.len() {
+        found if found == expected => Ok(()),
+        found => Err(Error::InvalidLength { expected, found }),
+    }
+}
+
+/// Return the checksummed Bech32m representation of a fixed size hash with the given human
+/// readable prefix.
+pub(crate) fn to_bech32m<T: AsRef<[u8]>>(hrp: &str, hash: &T) -> String {
+    bech32::encode(hrp, hash.as_ref().to_base32(), Variant::Bech32m)
+        .expect("Human readable prefix is valid")
+}
+
+/// Parse a fixed size hash from its checksummed Bech32m representation, fails if the string is
+/// malformed, has another prefix than `hrp`, or does not match its checksum.
+pub(crate) fn from_bech32m<T, const N: usize>(hrp: &str, s: &str) -> Result<T, Error>
+where
+    T: From<[u8; N]>,
+{
+    let (found, data, variant) = bech32::decode(s).map_err(Error::new)?;
+    if found != hrp || variant != Variant::Bech32m {
+        return Err(Error::ParseFailed("Invalid Bech32m prefix or variant"));
+    }
+    let bytes = Vec::<u8>::from_base32(&data).map_err(Error::new)?;
+    check_length(&bytes, N)?;
+    let mut hash = [0u8; N];
+    hash.copy_from_slice(&bytes);
+    Ok(T::from(hash))
+}
+
 /// Encode an object into a vector of bytes. The vector can be [`deserialize`]d to retrieve the
 /// data.
 pub fn serialize<T: Encodable + ?Sized>(data: &T) -> Vec<u8> {
@@ -163,6 +251,14 @@ pub trait Encodable {
     ///
     /// The only errors returned are errors propagated from the writer.
     fn consensus_encode<W: io::Write>(&self, writer: &mut W) -> Result<usize, io::Error>;
+
+    /// Returns the size in bytes of the encoded object without keeping the serialized buffer.
+    /// The object is encoded into a sink discarding the bytes and counting them, useful for
+    /// prefixing a message with its length before streaming it. Fails with the encoding error if
+    /// the object cannot be encoded, e.g. a vector with too many elements.
+    fn serialized_len(&self) -> Result<usize, io::Error> {
+        self.consensus_encode(&mut io::sink())
+    }
 }
 
 /// Data which can be decoded in a consensus-consistent way. Used to implement `StrictDecode` on
@@ -373,6 +469,22 @@ where
     }
 }
 
+impl<const N: usize> CanonicalBytes for [u8; N] {
+    fn as_canonical_bytes(&self) -> Vec<u8> {
+        self.to_vec()
+    }
+
+    fn from_canonical_bytes(bytes: &[u8]) -> Result<Self, Error>
+    where
+        Self: Sized,
+    {
+        check_length(bytes, N)?;
+        let mut array = [0u8; N];
+        array.copy_from_slice(bytes);
+        Ok(array)
+    }
+}
+
 impl CanonicalBytes for String {
     fn as_canonical_bytes(&self) -> Vec<u8> {
         self.as_bytes().into()
@@ -402,7 +514,9 @@ impl Decodable for String {
     }
 }
 
-/// Implement strict encoding by wrapping the de/serialization of consensus encoding.
+/// Implement strict encoding by wrapping the de/serialization of consensus encoding. Expands to
+/// nothing when the `strict-encoding` feature is disabled.
+#[cfg(feature = "strict-encoding")]
 #[macro_export]
 macro_rules! impl_strict_encoding {
     ($thing:ty, $($args:tt)*) => {
@@ -443,6 +557,15 @@ macro_rules! impl_strict_encoding {
     };
 }
 
+/// Implement strict encoding by wrapping the de/serialization of consensus encoding. Expands to
+/// nothing when the `strict-encoding` feature is disabled.
+#[cfg(not(feature = "strict-encoding"))]
+#[macro_export]
+macro_rules! impl_strict_encoding {
+    ($thing:ty, $($args:tt)*) => {};
+    ($thing:ty) => {};
+}
+
 impl CanonicalBytes for PublicKey {
     fn as_canonical_bytes(&self) -> Vec<u8> {
         self.serialize().as_ref().into()
@@ -452,7 +575,161 @@ impl CanonicalBytes for PublicKey {
     where
         Self: Sized,
     {
-        PublicKey::from_slice(bytes).map_err(Error::new)
+        check_length(bytes, 33)?;
+        PublicKey::from_slice(bytes).map_err(|_| Error::InvalidEncoding("invalid secp256k1 point"))
+    }
+}
+
+/// Serde helpers for `#[serde(with = "...")]` fields implementing [`CanonicalBytes`]: the
+/// canonical bytes are rendered as an hex string in human-readable formats, e.g. JSON or YAML,
+/// and as raw bytes in binary formats.
+#[cfg(feature = "serde")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+pub mod serde_hex {
+    use std::fmt;
+    use std::hash::Hash;
+
+    use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::CanonicalBytes;
+    use crate::crypto::{TaggedElement, TaggedElements};
+
+    pub fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        T: CanonicalBytes,
+        S: Serializer,
+    {
+        let bytes = value.as_canonical_bytes();
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&hex::encode(bytes))
+        } else {
+            serializer.serialize_bytes(&bytes)
+        }
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+    where
+        T: CanonicalBytes,
+        D: Deserializer<'de>,
+    {
+        let bytes = if deserializer.is_human_readable() {
+            hex::decode(String::deserialize(deserializer)?).map_err(de::Error::custom)?
+        } else {
+            deserializer.deserialize_bytes(BytesVisitor)?
+        };
+        T::from_canonical_bytes(&bytes).map_err(de::Error::custom)
+    }
+
+    struct BytesVisitor;
+
+    impl<'de> de::Visitor<'de> for BytesVisitor {
+        type Value = Vec<u8>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("canonical bytes")
+        }
+
+        fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Vec<u8>, E> {
+            Ok(v.to_vec())
+        }
+
+        fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<Vec<u8>, E> {
+            Ok(v)
+        }
+
+        fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<Vec<u8>, A::Error> {
+            let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+            while let Some(byte) = seq.next_element()? {
+                bytes.push(byte);
+            }
+            Ok(bytes)
+        }
+    }
+
+    struct Canonical<'a, T>(&'a T);
+
+    impl<'a, T: CanonicalBytes> Serialize for Canonical<'a, T> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serialize(self.0, serializer)
+        }
+    }
+
+    struct Owned<T>(T);
+
+    impl<'de, T: CanonicalBytes> Deserialize<'de> for Owned<T> {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            deserialize(deserializer).map(Owned)
+        }
+    }
+
+    /// Serde helpers for optional fields implementing [`CanonicalBytes`].
+    pub mod option {
+        use super::*;
+
+        pub fn serialize<T, S>(value: &Option<T>, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            T: CanonicalBytes,
+            S: Serializer,
+        {
+            value.as_ref().map(Canonical).serialize(serializer)
+        }
+
+        pub fn deserialize<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+        where
+            T: CanonicalBytes,
+            D: Deserializer<'de>,
+        {
+            Ok(Option::<Owned<T>>::deserialize(deserializer)?.map(|owned| owned.0))
+        }
+    }
+
+    /// Serde helpers for [`TaggedElements`] whose elements implement [`CanonicalBytes`], the tags
+    /// keep their own serde representation.
+    pub mod tagged {
+        use super::*;
+
+        #[derive(Serialize)]
+        #[serde(bound(serialize = "T: Serialize, E: CanonicalBytes"))]
+        struct TaggedRef<'a, T, E> {
+            tag: &'a T,
+            elem: Canonical<'a, E>,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(bound(deserialize = "T: Deserialize<'de>, E: CanonicalBytes"))]
+        struct TaggedOwned<T, E> {
+            tag: T,
+            elem: Owned<E>,
+        }
+
+        pub fn serialize<T, E, S>(
+            value: &TaggedElements<T, E>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error>
+        where
+            T: Serialize,
+            E: CanonicalBytes,
+            S: Serializer,
+        {
+            serializer.collect_seq(value.iter().map(|tagged| TaggedRef {
+                tag: tagged.tag(),
+                elem: Canonical(tagged.elem()),
+            }))
+        }
+
+        pub fn deserialize<'de, T, E, D>(deserializer: D) -> Result<TaggedElements<T, E>, D::Error>
+        where
+            T: Deserialize<'de> + Hash + Eq + Clone,
+            E: CanonicalBytes,
+            D: Deserializer<'de>,
+        {
+            Vec::<TaggedOwned<T, E>>::deserialize(deserializer)?
+                .into_iter()
+                .map(|tagged| TaggedElement::new(tagged.tag, tagged.elem.0))
+                .collect::<Vec<_>>()
+                .try_into()
+                .map_err(de::Error::custom)
+        }
     }
 }
 
@@ -471,6 +748,75 @@ mod tests {
         assert_eq!(deserialize::<u8>(&[0x01]).unwrap(), 0x01);
     }
 
+    #[test]
+    fn serialized_len_matches_serialize() {
+        assert_eq!(
+            0xdeadbeefu32.serialized_len().unwrap(),
+            serialize(&0xdeadbeefu32).len()
+        );
+        let vec: Vec<u8> = vec![0xde, 0xad, 0xbe, 0xef];
+        assert_eq!(vec.serialized_len().unwrap(), serialize(&vec).len());
+        let opt: Option<Vec<u8>> = Some(vec);
+        assert_eq!(opt.serialized_len().unwrap(), serialize(&opt).len());
+        let none: Option<Vec<u8>> = None;
+        assert_eq!(none.serialized_len().unwrap(), serialize(&none).len());
+        // Encoding errors are returned instead of panicking
+        let too_long = vec![0u8; u16::MAX as usize + 1];
+        assert!(too_long.serialized_len().is_err());
+    }
+
+    #[test]
+    fn multi_byte_integers_are_little_endian() {
+        use crate::bitcoin::fee::SatPerVByte;
+        use crate::bitcoin::timelock::CSVTimelock;
+        use crate::blockchain::Blockchain;
+        use crate::crypto::SharedKeyId;
+        use crate::protocol::message::ProtocolVersion;
+        use crate::trade::Version;
+        use crate::transaction::TxLabel;
+
+        assert_eq!(serialize(&0x0102u16), vec![0x02, 0x01]);
+        assert_eq!(serialize(&-2i16), vec![0xfe, 0xff]);
+        assert_eq!(serialize(&0x01020304u32), vec![0x04, 0x03, 0x02, 0x01]);
+        assert_eq!(serialize(&-2i32), vec![0xfe, 0xff, 0xff, 0xff]);
+        assert_eq!(
+            serialize(&0x0102030405060708u64),
+            vec![0x08, 0x07, 0x06, 0x05, 0x04, 0x03, 0x02, 0x01]
+        );
+        // Length prefix of vectors
+        assert_eq!(serialize(&vec![0xffu8; 0x0102])[..2], [0x02, 0x01]);
+
+        // Integer fields used in messages and deals
+        assert_eq!(serialize(&TxLabel::Lock), vec![0x02, 0x00]);
+        assert_eq!(serialize(&TxLabel::AccLock), vec![0x07, 0x00]);
+        assert_eq!(serialize(&SharedKeyId::new(0x0102)), vec![0x02, 0x01]);
+        assert_eq!(serialize(&Version::new(0x0102)), vec![0x02, 0x01]);
+        assert_eq!(
+            serialize(&ProtocolVersion::new(1, 2)),
+            vec![0x01, 0x00, 0x02, 0x00]
+        );
+        assert_eq!(
+            serialize(&Blockchain::Bitcoin),
+            vec![0x00, 0x00, 0x00, 0x80]
+        );
+        assert_eq!(
+            bitcoin::Amount::from_sat(0x0102).as_canonical_bytes(),
+            vec![0x02, 0x01, 0, 0, 0, 0, 0, 0]
+        );
+        assert_eq!(
+            monero::Amount::from_pico(0x0102).as_canonical_bytes(),
+            vec![0x02, 0x01, 0, 0, 0, 0, 0, 0]
+        );
+        assert_eq!(
+            SatPerVByte::from_sat(0x0102).as_canonical_bytes(),
+            vec![0x02, 0x01, 0, 0, 0, 0, 0, 0]
+        );
+        assert_eq!(
+            CSVTimelock::new(0x0102).as_canonical_bytes(),
+            vec![0x02, 0x01, 0, 0]
+        );
+    }
+
     #[test]
     fn simple_vec() {
         let vec: Vec<u8> = vec![0xde, 0xad, 0xbe, 0xef];
@@ -480,4 +826,73 @@ mod tests {
         let vec = vec![0x41; u16::MAX.into()];
         assert_eq!(deserialize::<Vec<u8>>(&serialize(&vec)[..]).unwrap(), vec);
     }
+
+    #[test]
+    fn fixed_array_canonical_bytes() {
+        let array = [0xde, 0xad, 0xbe, 0xef];
+        assert_eq!(array.as_canonical_bytes(), vec![0xde, 0xad, 0xbe, 0xef]);
+        assert_eq!(<[u8; 4]>::from_canonical_bytes(&array).unwrap(), array);
+        assert!(matches!(
+            <[u8; 4]>::from_canonical_bytes(&array[..3]),
+            Err(Error::InvalidLength {
+                expected: 4,
+                found: 3
+            })
+        ));
+    }
+
+    #[derive(Debug, PartialEq, CanonicalBytes)]
+    struct Named<T> {
+        id: [u8; 2],
+        name: String,
+        inner: Option<T>,
+    }
+
+    #[derive(Debug, PartialEq, CanonicalBytes)]
+    struct Tuple([u8; 2], String);
+
+    #[test]
+    fn derived_canonical_bytes() {
+        let named = Named {
+            id: [0x01, 0x02],
+            name: "xmr".into(),
+            inner: Some(Tuple([0x03, 0x04], "btc".into())),
+        };
+        // Hand-written encoding of the fields as length prefixed canonical bytes
+        let mut inner = vec![];
+        [0x03u8, 0x04]
+            .as_canonical_bytes()
+            .consensus_encode(&mut inner)
+            .unwrap();
+        "btc"
+            .to_string()
+            .as_canonical_bytes()
+            .consensus_encode(&mut inner)
+            .unwrap();
+        let mut expected = vec![];
+        [0x01u8, 0x02]
+            .as_canonical_bytes()
+            .consensus_encode(&mut expected)
+            .unwrap();
+        "xmr"
+            .to_string()
+            .as_canonical_bytes()
+            .consensus_encode(&mut expected)
+            .unwrap();
+        inner.consensus_encode(&mut expected).unwrap();
+
+        let bytes = named.as_canonical_bytes();
+        assert_eq!(bytes, expected);
+        assert_eq!(Named::<Tuple>::from_canonical_bytes(&bytes).unwrap(), named);
+
+        // Trailing bytes must be rejected
+        let mut extended = bytes.clone();
+        extended.push(0x00);
+        assert!(matches!(
+            Named::<Tuple>::from_canonical_bytes(&extended),
+            Err(Error::InvalidLength { .. })
+        ));
+        // Truncated bytes must be rejected
+        assert!(Named::<Tuple>::from_canonical_bytes(&bytes[..bytes.len() - 1]).is_err());
+    }
 }
@@ -18,21 +18,29 @@
 //! sign, etc) used to create the generic framework for supporting multiple blockchains under the
 //! same interface.
 
-use std::convert::TryInto;
+use std::collections::HashMap;
+use std::convert::{TryFrom, TryInto};
 use std::error;
 use std::fmt::{self, Debug};
+use std::hash::{Hash, Hasher};
 use std::io;
 
+use rand_alt::{CryptoRng, RngCore};
 use thiserror::Error;
-use tiny_keccak::{Hasher, Keccak};
+use tiny_keccak::{Hasher as _, Keccak};
 
 use crate::consensus::{self, CanonicalBytes, Decodable, Encodable};
 
+#[cfg(feature = "experimental")]
+pub(crate) mod adaptor;
 #[cfg(feature = "experimental")]
 #[cfg_attr(docsrs, doc(cfg(feature = "experimental")))]
 pub mod dleq;
 #[cfg(feature = "experimental")]
 #[cfg_attr(docsrs, doc(cfg(feature = "experimental")))]
+pub mod musig2;
+#[cfg(feature = "experimental")]
+#[cfg_attr(docsrs, doc(cfg(feature = "experimental")))]
 pub mod slip10;
 
 /// List of cryptographic errors that can be encountered in cryptographic operations such as
@@ -45,6 +53,9 @@ pub enum Error {
     /// The key or key identifier does not exists or is missing.
     #[error("The key or key identifier does not exists or is missing")]
     MissingKey,
+    /// A key with the same identifier is already present.
+    #[error("A key with the same identifier is already present")]
+    DuplicateKey,
     /// The signature does not pass the validation tests.
     #[error("The signature does not pass the validation")]
     InvalidSignature,
@@ -104,7 +115,8 @@ impl Error {
 /// Element `E` prefixed with a tag `T`. Used to tag content with some ids. Tag should be `Eq` to
 /// be used in vectors or sets and identify the content. Tags can be [`ArbitratingKeyId`],
 /// [`AccordantKeyId`] or any other type of identifiers.
-#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct TaggedElement<T, E> {
     tag: T,
     elem: E,
@@ -162,19 +174,266 @@ where
     }
 }
 
-/// A vector of `T` tagged elements `E`.
-pub type TaggedElements<T, E> = Vec<TaggedElement<T, E>>;
+/// A collection of `T` tagged elements `E` where each tag is unique. Elements are kept in
+/// insertion order and indexed by tag for constant time lookup. Decoding a collection containing
+/// duplicated tags fails.
+///
+/// The collection behaves as a set: equality and hashing do not depend on the insertion order and
+/// elements are sorted by tag when encoded, two peers building the same collection in different
+/// orders produce the same bytes.
+#[derive(Debug, Clone)]
+pub struct TaggedElements<T, E> {
+    elems: Vec<TaggedElement<T, E>>,
+    index: HashMap<T, usize>,
+}
+
+impl<T, E> TaggedElements<T, E>
+where
+    T: Hash + Eq + Clone,
+{
+    /// Create a new empty collection of tagged elements.
+    pub fn new() -> Self {
+        Self {
+            elems: vec![],
+            index: HashMap::new(),
+        }
+    }
+
+    /// Add the element `E` with the tag `T`, fails with [`Error::DuplicateKey`] if an element is
+    /// already tagged with `T`.
+    pub fn insert(&mut self, tag: T, elem: E) -> Result<(), Error> {
+        if self.index.contains_key(&tag) {
+            return Err(Error::DuplicateKey);
+        }
+        self.index.insert(tag.clone(), self.elems.len());
+        self.elems.push(TaggedElement::new(tag, elem));
+        Ok(())
+    }
+
+    /// Returns the element tagged with `tag` if present.
+    pub fn get(&self, tag: &T) -> Option<&E> {
+        self.index.get(tag).map(|&i| self.elems[i].elem())
+    }
+
+    /// Returns `true` if an element is tagged with `tag`.
+    pub fn contains_tag(&self, tag: &T) -> bool {
+        self.index.contains_key(tag)
+    }
+
+    /// Transform each element while keeping its tag, returns a new collection with the same tags.
+    pub fn map<U, F>(&self, f: F) -> TaggedElements<T, U>
+    where
+        F: Fn(&E) -> U,
+    {
+        TaggedElements {
+            elems: self
+                .elems
+                .iter()
+                .map(|e| TaggedElement::new(e.tag().clone(), f(e.elem())))
+                .collect(),
+            index: self.index.clone(),
+        }
+    }
+}
+
+impl<T, E> TaggedElements<T, E> {
+    /// Returns the number of tagged elements.
+    pub fn len(&self) -> usize {
+        self.elems.len()
+    }
+
+    /// Returns `true` if there is no tagged element.
+    pub fn is_empty(&self) -> bool {
+        self.elems.is_empty()
+    }
+
+    /// Returns an iterator over the tagged elements in insertion order.
+    pub fn iter(&self) -> std::slice::Iter<'_, TaggedElement<T, E>> {
+        self.elems.iter()
+    }
+
+    /// Consumes the collection and returns the tagged elements in insertion order.
+    pub fn into_inner(self) -> Vec<TaggedElement<T, E>> {
+        self.elems
+    }
+
+    // Returns references to the tagged elements sorted by tag, the canonical order.
+    fn canonical_order(&self) -> Vec<&TaggedElement<T, E>>
+    where
+        T: Ord,
+    {
+        let mut elems: Vec<_> = self.elems.iter().collect();
+        elems.sort_by(|a, b| a.tag.cmp(&b.tag));
+        elems
+    }
+}
+
+impl<T, E> TaggedElements<T, E>
+where
+    T: Hash + Ord + Clone,
+    E: Clone,
+{
+    /// Returns a copy of the collection with the elements sorted by tag, the order used when
+    /// encoding the collection.
+    pub fn sorted_by_tag(&self) -> Self {
+        let mut elems = self.elems.clone();
+        elems.sort_by(|a, b| a.tag.cmp(&b.tag));
+        let index = elems
+            .iter()
+            .enumerate()
+            .map(|(i, e)| (e.tag.clone(), i))
+            .collect();
+        Self { elems, index }
+    }
+}
+
+impl<T, E> Default for TaggedElements<T, E>
+where
+    T: Hash + Eq + Clone,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
-/// A vector of [`u16`] tagged keys of type `E`.
-pub type TaggedExtraKeys<E> = Vec<TaggedElement<u16, E>>;
+impl<T, E> TryFrom<Vec<TaggedElement<T, E>>> for TaggedElements<T, E>
+where
+    T: Hash + Eq + Clone,
+{
+    type Error = Error;
+
+    fn try_from(elems: Vec<TaggedElement<T, E>>) -> Result<Self, Self::Error> {
+        let mut res = Self::new();
+        for elem in elems {
+            res.insert(elem.tag, elem.elem)?;
+        }
+        Ok(res)
+    }
+}
+
+impl<'a, T, E> IntoIterator for &'a TaggedElements<T, E> {
+    type Item = &'a TaggedElement<T, E>;
+    type IntoIter = std::slice::Iter<'a, TaggedElement<T, E>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.elems.iter()
+    }
+}
 
-/// A vector of shared keys tagged with [`SharedKeyId`] of type `E`.
-pub type TaggedSharedKeys<E> = Vec<TaggedElement<SharedKeyId, E>>;
+impl<T, E> IntoIterator for TaggedElements<T, E> {
+    type Item = TaggedElement<T, E>;
+    type IntoIter = std::vec::IntoIter<TaggedElement<T, E>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.elems.into_iter()
+    }
+}
+
+impl<T, E> PartialEq for TaggedElements<T, E>
+where
+    T: Hash + Eq,
+    E: PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.elems.len() == other.elems.len()
+            && self.elems.iter().all(|e| {
+                other
+                    .index
+                    .get(&e.tag)
+                    .map_or(false, |&i| other.elems[i].elem == e.elem)
+            })
+    }
+}
+
+impl<T, E> Eq for TaggedElements<T, E>
+where
+    T: Hash + Eq,
+    E: Eq,
+{
+}
+
+impl<T, E> Hash for TaggedElements<T, E>
+where
+    T: Hash + Ord,
+    E: Hash,
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.canonical_order().hash(state)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T, E> serde::Serialize for TaggedElements<T, E>
+where
+    T: serde::Serialize,
+    E: serde::Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serde::Serialize::serialize(&self.elems, serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T, E> serde::Deserialize<'de> for TaggedElements<T, E>
+where
+    T: serde::Deserialize<'de> + Hash + Eq + Clone,
+    E: serde::Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        <Vec<TaggedElement<T, E>> as serde::Deserialize>::deserialize(deserializer)?
+            .try_into()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+impl<T, E> Encodable for TaggedElements<T, E>
+where
+    T: Encodable + Ord,
+    E: CanonicalBytes,
+{
+    #[inline]
+    fn consensus_encode<S: io::Write>(&self, s: &mut S) -> Result<usize, io::Error> {
+        if self.elems.len() > u16::MAX as usize {
+            return Err(io::Error::new(io::ErrorKind::Other, "Value is too long"));
+        }
+        let mut len = (self.elems.len() as u16).consensus_encode(s)?;
+        for elem in self.canonical_order() {
+            len += elem.consensus_encode(s)?;
+        }
+        Ok(len)
+    }
+}
+
+impl<T, E> Decodable for TaggedElements<T, E>
+where
+    T: Decodable + Hash + Eq + Clone,
+    E: CanonicalBytes,
+{
+    #[inline]
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        Vec::<TaggedElement<T, E>>::consensus_decode(d)?
+            .try_into()
+            .map_err(|_| consensus::Error::ParseFailed("Duplicated tag in tagged elements"))
+    }
+}
+
+/// A collection of [`u16`] tagged keys of type `E`.
+pub type TaggedExtraKeys<E> = TaggedElements<u16, E>;
+
+/// A collection of shared keys tagged with [`SharedKeyId`] of type `E`.
+pub type TaggedSharedKeys<E> = TaggedElements<SharedKeyId, E>;
 
 /// List of all possible arbitrating keys as defined for the base protocol in the RFCs. Extra keys
 /// can be defined with [`Self::Extra`] variant and an `u16` identifier. Those keys can be used for
 /// extra off-chain protocol such as multi-signature or multi-party computation schemes.
-#[derive(Debug, Clone, Copy, Display, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Display)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[display(Debug)]
 pub enum ArbitratingKeyId {
     /// Arbitrating key used to fund the [`Lockable`] transaction through [`Fundable`].
@@ -206,7 +465,8 @@ pub enum ArbitratingKeyId {
 
 /// Defines the base accordant key identifier [`Self::Spend`] and all possible extra keys with
 /// [`Self::Extra`] variant containing the `u16` identifier.
-#[derive(Debug, Clone, Copy, Display, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Display)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[display(Debug)]
 pub enum AccordantKeyId {
     /// Accordant bought/sold key over the arbitrating blockchain.
@@ -218,7 +478,8 @@ pub enum AccordantKeyId {
 
 /// Identifier for shared private keys over the arbitrating and accordant blockchains. E.g. the
 /// `view` key needed to parse the Monero blockchain is a shared private key.
-#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Display, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord, Display)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[display(Debug)]
 pub struct SharedKeyId(u16);
 
@@ -252,7 +513,8 @@ impl Decodable for SharedKeyId {
 /// [`Accordant`] blockchain in the swap (e.g. the Monero blockchain).
 ///
 /// [`Accordant`]: crate::role::Accordant
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct AccordantKeys<PublicKey, SharedSecretKey> {
     /// The full accordant spend public key.
     pub public_spend_key: PublicKey,
@@ -263,7 +525,8 @@ pub struct AccordantKeys<PublicKey, SharedSecretKey> {
 }
 
 /// The full set of all keys related to the accordant blockchain available after the reveal round.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct AccordantKeySet<PublicKey, SharedSecretKey> {
     /// Alice's accordant keys (secret and public).
     pub alice: AccordantKeys<PublicKey, SharedSecretKey>,
@@ -273,7 +536,7 @@ pub struct AccordantKeySet<PublicKey, SharedSecretKey> {
 
 fixed_hash::construct_fixed_hash!(
     /// Result of a keccak256 commitment.
-    #[derive(Serialize, Deserialize)]
+    #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
     pub struct KeccakCommitment(32);
 );
 
@@ -302,16 +565,19 @@ impl CanonicalBytes for KeccakCommitment {
     }
 }
 
-/// Engine to produce and validate hash commitments.
+/// Engine to produce and validate hash commitments with the default commitment scheme: the
+/// Keccak-256 hash of the canonical bytes.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Display)]
 #[display(Debug)]
 pub struct CommitmentEngine;
 
-impl Commit<KeccakCommitment> for CommitmentEngine {
-    fn commit_to<T: AsRef<[u8]>>(&self, value: T) -> KeccakCommitment {
+impl CommitScheme for CommitmentEngine {
+    type Commitment = KeccakCommitment;
+
+    fn hash(value: &[u8]) -> KeccakCommitment {
         let mut out = [0u8; 32];
         let mut keccak = Keccak::v256();
-        keccak.update(value.as_ref());
+        keccak.update(value);
         keccak.finalize(&mut out);
         KeccakCommitment::new(out)
     }
@@ -325,7 +591,8 @@ impl Commit<KeccakCommitment> for CommitmentEngine {
 /// ```
 /// use farcaster_core::crypto::DeriveKeys;
 /// use farcaster_core::crypto::SharedKeyId;
-/// use bitcoin::secp256k1::{PublicKey, SecretKey};
+/// use bitcoin::secp256k1::{PublicKey, Secp256k1, SecretKey};
+/// use rand_alt::{CryptoRng, RngCore};
 ///
 /// pub struct Bitcoin;
 ///
@@ -342,6 +609,17 @@ impl Commit<KeccakCommitment> for CommitmentEngine {
 ///         // no shared key needed, transparent blockchain
 ///         vec![]
 ///     }
+///
+///     fn generate_keypair<R: RngCore + CryptoRng>(rng: &mut R) -> (SecretKey, PublicKey) {
+///         let secp = Secp256k1::signing_only();
+///         loop {
+///             let mut bytes = [0u8; 32];
+///             rng.fill_bytes(&mut bytes);
+///             if let Ok(secret) = SecretKey::from_slice(&bytes) {
+///                 return (secret, PublicKey::from_secret_key(&secp, &secret));
+///             }
+///         }
+///     }
 /// }
 /// ```
 pub trait DeriveKeys {
@@ -353,6 +631,10 @@ pub trait DeriveKeys {
 
     /// Return a list of extra shared secret key identifiers to use during the setup phase.
     fn extra_shared_private_keys() -> Vec<SharedKeyId>;
+
+    /// Generate a new random private key and its matching public key.
+    fn generate_keypair<R: RngCore + CryptoRng>(rng: &mut R)
+        -> (Self::PrivateKey, Self::PublicKey);
 }
 
 /// Meta trait regrouping all the needed trait combinations a key manager must implement to manage
@@ -464,6 +746,86 @@ pub trait RecoverSecret<PublicKey, SecretKey, Signature, EncryptedSignature> {
     ) -> SecretKey;
 }
 
+/// Defines the message, signature, and encrypted signature types used by a blockchain.
+pub trait Signatures: DeriveKeys {
+    /// Type of the message to sign, e.g. a transaction signature hash.
+    type Message;
+    /// Type of a regular signature.
+    type Signature;
+    /// Type of an encrypted signature, also called adaptor signature.
+    type EncryptedSignature;
+
+    /// Verify a regular signature for a given message against the public key, return
+    /// [`Error::InvalidSignature`] if not valid.
+    fn verify(
+        key: &Self::PublicKey,
+        msg: Self::Message,
+        sig: &Self::Signature,
+    ) -> Result<(), Error>;
+}
+
+/// Stateless adaptor signature operations over the [`Signatures`] and [`DeriveKeys`] types of a
+/// blockchain. Unlike [`EncSign`] and [`RecoverSecret`], keys are provided directly instead of
+/// being identified and managed by a wallet.
+///
+/// Completing an encrypted signature with the decryption key and publishing it allows the
+/// counter-party to recover the decryption key, this is how the accordant spend key is revealed
+/// during a swap.
+pub trait AdaptorSignature: Signatures + DeriveKeys {
+    /// Sign the message with the signing private key and encrypt the signature with the
+    /// encryption public key.
+    fn encrypt_sign(
+        signing_key: &Self::PrivateKey,
+        encryption_key: &Self::PublicKey,
+        msg: Self::Message,
+    ) -> Result<Self::EncryptedSignature, Error>;
+
+    /// Verify an encrypted signature for a given message against the signing public key and the
+    /// encryption public key, return [`Error::InvalidEncryptedSignature`] if not valid.
+    fn verify_adaptor(
+        signing_key: &Self::PublicKey,
+        encryption_key: &Self::PublicKey,
+        msg: Self::Message,
+        sig: &Self::EncryptedSignature,
+    ) -> Result<(), Error>;
+
+    /// Decrypt the encrypted signature with the decryption private key, producing a valid regular
+    /// signature.
+    fn decrypt_sig(
+        decryption_key: &Self::PrivateKey,
+        sig: Self::EncryptedSignature,
+    ) -> Result<Self::Signature, Error>;
+
+    /// Recover the decryption private key from the encrypted signature and its decrypted
+    /// signature, return [`Error::InvalidSignature`] if the signature is not the decryption of
+    /// the encrypted signature.
+    fn recover_secret(
+        encryption_key: &Self::PublicKey,
+        sig: &Self::Signature,
+        encrypted_sig: &Self::EncryptedSignature,
+    ) -> Result<Self::PrivateKey, Error>;
+}
+
+/// Hash function used to commit to values, e.g. [`CommitmentEngine`] for Keccak-256. Every
+/// scheme is a [`Commit`] engine producing its commitments, interoperating with an
+/// implementation using another hash only requires implementing this trait.
+pub trait CommitScheme {
+    /// The commitment produced by the hash function.
+    type Commitment;
+
+    /// Hash the value into a commitment.
+    fn hash(value: &[u8]) -> Self::Commitment;
+}
+
+impl<S> Commit<S::Commitment> for S
+where
+    S: CommitScheme,
+{
+    fn commit_to<T: AsRef<[u8]>>(&self, value: T) -> S::Commitment {
+        S::hash(value.as_ref())
+    }
+}
+
 /// Commitment generator and verifier. Generated commitments can be validated against candidates,
 /// if correct the commit/reveal process is validated.
 pub trait Commit<Commitment> {
@@ -505,3 +867,106 @@ pub trait ProveCrossGroupDleq<EncryptionKey, AccordantSpendKey, Proof> {
         proof: Proof,
     ) -> Result<(), Error>;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consensus::{deserialize, serialize};
+
+    use bitcoin::hashes::sha256;
+    use bitcoin::hashes::Hash as BitcoinHash;
+
+    struct Sha256Scheme;
+
+    impl CommitScheme for Sha256Scheme {
+        type Commitment = [u8; 32];
+
+        fn hash(value: &[u8]) -> [u8; 32] {
+            <sha256::Hash as BitcoinHash>::hash(value).into_inner()
+        }
+    }
+
+    #[test]
+    fn default_commitment_scheme_vectors() {
+        assert_eq!(
+            CommitmentEngine.commit_to([]).to_fixed_bytes(),
+            hex::decode("c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470")
+                .unwrap()[..]
+        );
+        let key = bitcoin::secp256k1::PublicKey::from_secret_key(
+            &bitcoin::secp256k1::Secp256k1::signing_only(),
+            &bitcoin::secp256k1::SecretKey::from_slice(&[1u8; 32]).unwrap(),
+        );
+        let commitment = CommitmentEngine.commit_to(key.as_canonical_bytes());
+        assert_eq!(
+            commitment.to_fixed_bytes(),
+            hex::decode("2a05fd2ac8aa23fc5d2ef2f62c27a42831389dfbd8ebcd91b7275671e2a75349")
+                .unwrap()[..]
+        );
+        assert!(CommitmentEngine
+            .validate(key.as_canonical_bytes(), commitment)
+            .is_ok());
+    }
+
+    #[test]
+    fn commit_with_another_scheme() {
+        let value = b"farcaster";
+        let commitment = Sha256Scheme.commit_to(value);
+        assert_eq!(
+            commitment,
+            <sha256::Hash as BitcoinHash>::hash(value).into_inner()
+        );
+        assert!(Sha256Scheme.validate(value, commitment).is_ok());
+        assert!(matches!(
+            CommitmentEngine.validate(value, KeccakCommitment::new(commitment)),
+            Err(Error::InvalidCommitment)
+        ));
+    }
+
+    #[test]
+    fn decode_tagged_elements() {
+        let mut elems = TaggedExtraKeys::new();
+        elems.insert(1, KeccakCommitment::new([1u8; 32])).unwrap();
+        elems.insert(2, KeccakCommitment::new([2u8; 32])).unwrap();
+        let res: TaggedExtraKeys<KeccakCommitment> = deserialize(&serialize(&elems)).unwrap();
+        assert_eq!(res, elems);
+        assert_eq!(res.get(&2), Some(&KeccakCommitment::new([2u8; 32])));
+        assert_eq!(res.get(&3), None);
+    }
+
+    #[test]
+    fn fail_to_decode_duplicated_tags() {
+        let elems = vec![
+            TaggedElement::new(1u16, KeccakCommitment::new([1u8; 32])),
+            TaggedElement::new(1u16, KeccakCommitment::new([2u8; 32])),
+        ];
+        let res: Result<TaggedExtraKeys<KeccakCommitment>, _> = deserialize(&serialize(&elems));
+        assert!(matches!(res, Err(consensus::Error::ParseFailed(_))));
+        assert!(matches!(
+            TaggedExtraKeys::try_from(elems),
+            Err(Error::DuplicateKey)
+        ));
+    }
+
+    #[test]
+    fn tagged_elements_encoding_is_order_independent() {
+        let mut a = TaggedSharedKeys::new();
+        a.insert(SharedKeyId::new(2), KeccakCommitment::new([2u8; 32]))
+            .unwrap();
+        a.insert(SharedKeyId::new(1), KeccakCommitment::new([1u8; 32]))
+            .unwrap();
+        let mut b = TaggedSharedKeys::new();
+        b.insert(SharedKeyId::new(1), KeccakCommitment::new([1u8; 32]))
+            .unwrap();
+        b.insert(SharedKeyId::new(2), KeccakCommitment::new([2u8; 32]))
+            .unwrap();
+
+        assert_eq!(serialize(&a), serialize(&b));
+        assert_eq!(a, b);
+        assert_eq!(a.sorted_by_tag().into_inner(), b.clone().into_inner());
+
+        // Encoded in tag order and decoded as is
+        let res: TaggedSharedKeys<KeccakCommitment> = deserialize(&serialize(&a)).unwrap();
+        assert_eq!(res.into_inner(), b.into_inner());
+    }
+}
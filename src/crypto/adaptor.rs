@@ -0,0 +1,51 @@
+// Copyright 2021-2022 Farcaster Devs
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; either
+// version 3 of the License, or (at your option) any later version.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301, USA
+
+//! ECDSA adaptor signature engine over secp256k1 and the conversions between the `secp256k1` and
+//! `secp256kfun` key types, shared by the Bitcoin wallets.
+
+use bitcoin::secp256k1::{PublicKey, SecretKey};
+use ecdsa_fun::adaptor::{Adaptor, HashTranscript};
+use ecdsa_fun::fun::{marker::*, Point, Scalar};
+use ecdsa_fun::{nonce, ECDSA};
+use rand::rngs::ThreadRng;
+use rand_chacha::ChaCha20Rng;
+use sha2::Sha256;
+
+pub(crate) type Transcript = HashTranscript<Sha256, ChaCha20Rng>;
+
+pub(crate) type NonceGen = nonce::Synthetic<Sha256, nonce::GlobalRng<ThreadRng>>;
+
+/// Return the ECDSA adaptor signature engine.
+pub(crate) fn adaptor() -> Adaptor<Transcript, NonceGen> {
+    Adaptor::default()
+}
+
+/// Return the ECDSA signature engine with the same nonce generation as the adaptor engine.
+pub(crate) fn ecdsa() -> ECDSA<NonceGen> {
+    ECDSA::new(NonceGen::default())
+}
+
+// FIXME: use the secp256kfun compat conversions when versions of secp256k1 are aligned
+pub(crate) fn to_scalar(secret_key: &SecretKey) -> Scalar {
+    Scalar::from_bytes_mod_order(*secret_key.as_ref())
+        .mark::<NonZero>()
+        .expect("SecretKey is never zero")
+}
+
+pub(crate) fn to_point(public_key: &PublicKey) -> Point {
+    Point::from_bytes(public_key.serialize()).expect("PublicKey is always a valid point")
+}
@@ -104,7 +104,8 @@ fn H_p() -> secp256k1Point {
     // .expect("Alternate basepoint is invalid")
 }
 
-#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+#[derive(Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 struct PedersenCommitment<Point, Scalar> {
     commitment: Point,
     blinder: Scalar,
@@ -257,7 +258,8 @@ fn key_commitment_secp256k1(
     commitment
 }
 
-#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 struct RingSignature<ScalarCurveA, ScalarCurveB> {
     e_g_0_i: ScalarCurveA,
     e_h_0_i: ScalarCurveB,
@@ -532,7 +534,8 @@ impl
 }
 
 /// A Discrete Logarithm Equality Proof across secp256k1 and curve25519 groups.
-#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[allow(non_snake_case)]
 pub struct DLEQProof {
     c_g: Vec<ed25519Point>,
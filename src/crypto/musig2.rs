@@ -0,0 +1,574 @@
+// Copyright 2021-2022 Farcaster Devs
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; either
+// version 3 of the License, or (at your option) any later version.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301, USA
+
+//! Two-party MuSig2 key aggregation and signing over secp256k1. The aggregated signature is a
+//! BIP340 Schnorr signature valid for the aggregate key, which can be used as the internal key of
+//! a Taproot output and spent on the key path for the cooperative `buy` and `refund` paths.
+//!
+//! The signing session follows two rounds: each participant generates a nonce with
+//! [`generate_nonce`] and sends its [`PublicNonce`], then each participant creates a
+//! [`PartialSignature`] with [`KeyAggregate::partial_sign`]. Partial signatures are validated with
+//! [`KeyAggregate::verify_partial`] and combined into the final signature with
+//! [`KeyAggregate::aggregate_signatures`]. Nonces and partial signatures are exchanged with the
+//! [`MuSigNonce`] and [`MuSigPartialSignature`] protocol messages.
+//!
+//! # Compatibility
+//!
+//! Key aggregation, nonce generation, signing, partial signature verification, and x-only
+//! tweaking follow BIP327, restricted to two participants with the keys sorted as with
+//! `KeySort`. The key aggregation is checked against the BIP327 test vectors. A BIP327 library is
+//! not used as none is available for the `secp256kfun` version used by the adaptor signatures.
+//!
+//! [`MuSigNonce`]: crate::protocol::message::MuSigNonce
+//! [`MuSigPartialSignature`]: crate::protocol::message::MuSigPartialSignature
+
+use std::fmt;
+
+use rand::RngCore;
+use zeroize::Zeroize;
+
+use bitcoin::hashes::Hash;
+use bitcoin::secp256k1::{schnorr::Signature, Message, PublicKey, SecretKey, XOnlyPublicKey};
+use bitcoin::util::taproot::{TapBranchHash, TapTweakHash};
+
+use ecdsa_fun::fun::{Point, Scalar, G};
+use secp256kfun::{g, hash::Tagged, marker::*, s as sc};
+use sha2::{Digest, Sha256};
+
+use crate::consensus::{self, CanonicalBytes};
+use crate::crypto::adaptor::to_point;
+use crate::crypto::Error;
+
+const KEYAGG_LIST_TAG: &[u8] = b"KeyAgg list";
+const KEYAGG_COEF_TAG: &[u8] = b"KeyAgg coefficient";
+const NONCE_COEF_TAG: &[u8] = b"MuSig/noncecoef";
+const CHALLENGE_TAG: &[u8] = b"BIP0340/challenge";
+const NONCE_AUX_TAG: &[u8] = b"MuSig/aux";
+const NONCE_TAG: &[u8] = b"MuSig/nonce";
+
+fn tagged_hash(tag: &[u8], data: &[&[u8]]) -> [u8; 32] {
+    let mut hash = Sha256::default().tagged(tag);
+    for d in data {
+        hash.update(d);
+    }
+    hash.finalize().into()
+}
+
+fn tagged_scalar(tag: &[u8], data: &[&[u8]]) -> Scalar<Public, Zero> {
+    Scalar::from_bytes_mod_order(tagged_hash(tag, data)).mark::<Public>()
+}
+
+// BIP327 KeyAggCoeff of each key, in the given order
+fn key_agg_coefficients(keys: &[PublicKey]) -> Vec<Scalar<Public, Zero>> {
+    let serialized: Vec<[u8; 33]> = keys.iter().map(|k| k.serialize()).collect();
+    let list: Vec<&[u8]> = serialized.iter().map(|k| &k[..]).collect();
+    let list = tagged_hash(KEYAGG_LIST_TAG, &list);
+    // The first key different from the first one has a coefficient of one
+    let second = serialized.iter().find(|k| **k != serialized[0]);
+    serialized
+        .iter()
+        .map(|k| match Some(k) == second {
+            true => Scalar::one().mark::<Public>().mark::<Zero>(),
+            false => tagged_scalar(KEYAGG_COEF_TAG, &[&list, k]),
+        })
+        .collect()
+}
+
+// BIP327 KeyAgg of the keys with their coefficients
+fn key_agg(
+    keys: &[PublicKey],
+    coefficients: &[Scalar<Public, Zero>],
+) -> Result<Point<Normal, Public, NonZero>, Error> {
+    keys.iter()
+        .zip(coefficients.iter())
+        .fold(Point::zero().mark::<Jacobian>(), |acc, (key, a)| {
+            let p = to_point(key);
+            g!(acc + a * p)
+        })
+        .mark::<Normal>()
+        .mark::<NonZero>()
+        .ok_or_else(|| Error::new("Aggregate key is the point at infinity"))
+}
+
+fn to_public_key<T: Normalized, S>(point: &Point<T, S, NonZero>) -> PublicKey {
+    PublicKey::from_slice(&point.to_bytes()).expect("Valid secp256k1 point")
+}
+
+// Serialize a point, the point at infinity is encoded as 33 zero bytes
+fn point_bytes(point: &Point<Normal, Public, Zero>) -> [u8; 33] {
+    (*point)
+        .mark::<NonZero>()
+        .map(|p| p.to_bytes())
+        .unwrap_or([0u8; 33])
+}
+
+fn x_bytes<T: Normalized, S>(point: &Point<T, S, NonZero>) -> [u8; 32] {
+    let mut x = [0u8; 32];
+    x.copy_from_slice(&point.to_bytes()[1..]);
+    x
+}
+
+/// Aggregate key of two participants with the key aggregation coefficients and the accumulated
+/// tweaks needed to sign for it.
+#[derive(Clone, PartialEq)]
+pub struct KeyAggregate {
+    keys: [PublicKey; 2],
+    coefficients: [Scalar<Public, Zero>; 2],
+    aggregate: Point<Normal, Public, NonZero>,
+    // Accumulated negation of the participant keys due to x-only tweaking
+    negated: bool,
+    // Accumulated tweak
+    tweak: Scalar<Public, Zero>,
+}
+
+impl fmt::Debug for KeyAggregate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("KeyAggregate")
+            .field("keys", &self.keys)
+            .field("aggregate_key", &self.aggregate_key())
+            .finish()
+    }
+}
+
+/// Secret nonce generated for a single signing session. It is consumed when signing and must
+/// never be reused, thus it cannot be cloned nor serialized and is zeroed when dropped.
+pub struct SecretNonce {
+    k1: [u8; 32],
+    k2: [u8; 32],
+}
+
+impl Drop for SecretNonce {
+    fn drop(&mut self) {
+        self.k1.zeroize();
+        self.k2.zeroize();
+    }
+}
+
+impl fmt::Debug for SecretNonce {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "SecretNonce(..)")
+    }
+}
+
+/// Public nonce sent to the other participant during the first round of the signing session.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PublicNonce {
+    r1: PublicKey,
+    r2: PublicKey,
+}
+
+impl CanonicalBytes for PublicNonce {
+    fn as_canonical_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.r1.serialize().to_vec();
+        bytes.extend_from_slice(&self.r2.serialize());
+        bytes
+    }
+
+    fn from_canonical_bytes(bytes: &[u8]) -> Result<Self, consensus::Error>
+    where
+        Self: Sized,
+    {
+        consensus::check_length(bytes, 66)?;
+        let invalid = |_| consensus::Error::InvalidEncoding("invalid secp256k1 point");
+        Ok(Self {
+            r1: PublicKey::from_slice(&bytes[..33]).map_err(invalid)?,
+            r2: PublicKey::from_slice(&bytes[33..]).map_err(invalid)?,
+        })
+    }
+}
+
+/// Partial signature created by one participant during the second round of the signing session.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PartialSignature([u8; 32]);
+
+impl PartialSignature {
+    fn to_scalar(self) -> Result<Scalar<Public, Zero>, Error> {
+        Scalar::from_bytes(self.0)
+            .map(|s| s.mark::<Public>())
+            .ok_or(Error::InvalidSignature)
+    }
+}
+
+impl CanonicalBytes for PartialSignature {
+    fn as_canonical_bytes(&self) -> Vec<u8> {
+        self.0.to_vec()
+    }
+
+    fn from_canonical_bytes(bytes: &[u8]) -> Result<Self, consensus::Error>
+    where
+        Self: Sized,
+    {
+        let bytes = <[u8; 32]>::from_canonical_bytes(bytes)?;
+        Scalar::from_bytes(bytes)
+            .map(|_| Self(bytes))
+            .ok_or(consensus::Error::InvalidEncoding(
+                "not a canonical secp256k1 scalar",
+            ))
+    }
+}
+
+/// Generate a fresh nonce pair for the signing session `session_id` of `msg` with `secret`.
+///
+/// As in BIP327 nonce generation, the random bytes drawn from the thread random generator are
+/// masked with the secret key and hashed with the public key, the message, and the session
+/// identifier, a faulty random generator alone does not lead to a nonce reuse across messages or
+/// sessions. The session identifier must be unique for each signing session.
+pub fn generate_nonce(
+    secret: &SecretKey,
+    msg: &Message,
+    session_id: &[u8],
+) -> (SecretNonce, PublicNonce) {
+    let mut rand = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut rand);
+    let mut masked: [u8; 32] = Sha256::default()
+        .tagged(NONCE_AUX_TAG)
+        .chain(rand)
+        .finalize()
+        .into();
+    for (byte, secret_byte) in masked.iter_mut().zip(secret[..].iter()) {
+        *byte ^= secret_byte;
+    }
+    let d = Scalar::from_slice(&secret[..])
+        .and_then(|d| d.mark::<NonZero>())
+        .expect("Valid secret key");
+    let pubkey = to_public_key(&g!(d * G).mark::<Normal>()).serialize();
+
+    let nonce = |index: u8| -> Scalar {
+        let mut hash = Sha256::default().tagged(NONCE_TAG);
+        hash.update(masked);
+        hash.update([pubkey.len() as u8]);
+        hash.update(pubkey);
+        // No aggregate key, the message is always present
+        hash.update([0u8, 1u8]);
+        hash.update((msg.as_ref().len() as u64).to_be_bytes());
+        hash.update(msg.as_ref());
+        hash.update((session_id.len() as u32).to_be_bytes());
+        hash.update(session_id);
+        hash.update([index]);
+        Scalar::from_bytes_mod_order(hash.finalize().into())
+            .mark::<NonZero>()
+            .expect("Nonce is zero with negligible probability")
+    };
+    let (k1, k2) = (nonce(0), nonce(1));
+    rand.zeroize();
+    masked.zeroize();
+
+    let public = PublicNonce {
+        r1: to_public_key(&g!(k1 * G).mark::<Normal>()),
+        r2: to_public_key(&g!(k2 * G).mark::<Normal>()),
+    };
+    let secret = SecretNonce {
+        k1: k1.to_bytes(),
+        k2: k2.to_bytes(),
+    };
+    (secret, public)
+}
+
+// Values shared by all participants in a signing session
+struct Session {
+    b: Scalar<Public, Zero>,
+    e: Scalar<Public, Zero>,
+    r: Point<Normal, Public, NonZero>,
+}
+
+impl KeyAggregate {
+    /// Aggregate the public keys of the two participants. The order of the keys does not matter.
+    pub fn new(a: PublicKey, b: PublicKey) -> Result<Self, Error> {
+        let mut keys = [a, b];
+        keys.sort_by_key(|k| k.serialize());
+        let coefficients = key_agg_coefficients(&keys);
+        let aggregate = key_agg(&keys, &coefficients)?;
+        Ok(Self {
+            keys,
+            coefficients: [coefficients[0], coefficients[1]],
+            aggregate,
+            negated: false,
+            tweak: Scalar::zero().mark::<Public>(),
+        })
+    }
+
+    /// Returns the x-only aggregate key, including the tweaks applied.
+    pub fn aggregate_key(&self) -> XOnlyPublicKey {
+        XOnlyPublicKey::from_slice(&x_bytes(&self.aggregate)).expect("Valid x-only key")
+    }
+
+    /// Apply an x-only tweak to the aggregate key, as done when committing to a Taproot script
+    /// tree.
+    pub fn with_tweak(mut self, tweak: [u8; 32]) -> Result<Self, Error> {
+        let t = Scalar::from_bytes(tweak)
+            .ok_or_else(|| Error::new("Tweak is not a valid scalar"))?
+            .mark::<Public>();
+        let odd = !self.aggregate.is_y_even();
+        let q = self.aggregate.conditional_negate(odd);
+        self.aggregate = g!(q + t * G)
+            .mark::<Normal>()
+            .mark::<NonZero>()
+            .ok_or_else(|| Error::new("Tweaked key is the point at infinity"))?;
+        let mut tweak_acc = self.tweak;
+        tweak_acc.conditional_negate(odd);
+        self.tweak = sc!(t + tweak_acc).mark::<Public>();
+        self.negated ^= odd;
+        Ok(self)
+    }
+
+    /// Tweak the aggregate key with the Taproot commitment to `merkle_root`, the resulting
+    /// [`aggregate_key`](Self::aggregate_key) is the output key of the Taproot lock and the
+    /// signatures are valid on its key path.
+    pub fn with_taproot_tweak(self, merkle_root: Option<TapBranchHash>) -> Result<Self, Error> {
+        let tweak = TapTweakHash::from_key_and_tweak(self.aggregate_key(), merkle_root);
+        self.with_tweak(tweak.into_inner())
+    }
+
+    fn coefficient(&self, key: &PublicKey) -> Result<&Scalar<Public, Zero>, Error> {
+        self.keys
+            .iter()
+            .position(|k| k == key)
+            .map(|i| &self.coefficients[i])
+            .ok_or(Error::MissingKey)
+    }
+
+    // Whether the participant secret keys must be negated when signing
+    fn negate_keys(&self) -> bool {
+        !self.aggregate.is_y_even() ^ self.negated
+    }
+
+    fn session(&self, nonces: &[PublicNonce; 2], msg: &Message) -> Result<Session, Error> {
+        let (r1_0, r1_1) = (to_point(&nonces[0].r1), to_point(&nonces[1].r1));
+        let (r2_0, r2_1) = (to_point(&nonces[0].r2), to_point(&nonces[1].r2));
+        let r1 = g!(r1_0 + r1_1).mark::<Normal>();
+        let r2 = g!(r2_0 + r2_1).mark::<Normal>();
+        let q = x_bytes(&self.aggregate);
+        let b = tagged_scalar(
+            NONCE_COEF_TAG,
+            &[&point_bytes(&r1), &point_bytes(&r2), &q, msg.as_ref()],
+        );
+        // As in BIP327, the generator is used if the aggregate nonce is the point at infinity
+        let r = g!(r1 + b * r2)
+            .mark::<Normal>()
+            .mark::<NonZero>()
+            .unwrap_or_else(|| (*G).mark::<Normal>());
+        let e = tagged_scalar(CHALLENGE_TAG, &[&x_bytes(&r), &q, msg.as_ref()]);
+        Ok(Session { b, e, r })
+    }
+
+    /// Create the partial signature of `msg` with `secret` and the secret nonce generated for
+    /// this session. `nonces` are the public nonces of both participants.
+    pub fn partial_sign(
+        &self,
+        secret: &SecretKey,
+        nonce: SecretNonce,
+        nonces: &[PublicNonce; 2],
+        msg: &Message,
+    ) -> Result<PartialSignature, Error> {
+        let mut d = Scalar::from_slice(&secret[..])
+            .and_then(|d| d.mark::<NonZero>())
+            .ok_or(Error::MissingKey)?;
+        let a = self.coefficient(&to_public_key(&g!(d * G).mark::<Normal>()))?;
+        let Session { b, e, r } = self.session(nonces, msg)?;
+        d.conditional_negate(self.negate_keys());
+        let invalid = || Error::new("Invalid secret nonce");
+        let mut k1 = Scalar::from_bytes(nonce.k1).ok_or_else(invalid)?;
+        let mut k2 = Scalar::from_bytes(nonce.k2).ok_or_else(invalid)?;
+        k1.conditional_negate(!r.is_y_even());
+        k2.conditional_negate(!r.is_y_even());
+        let s = sc!(k1 + b * k2 + e * a * d);
+        Ok(PartialSignature(s.to_bytes()))
+    }
+
+    /// Verify the partial signature of the participant `pubkey` with public nonce `nonce`.
+    pub fn verify_partial(
+        &self,
+        pubkey: &PublicKey,
+        nonce: &PublicNonce,
+        nonces: &[PublicNonce; 2],
+        msg: &Message,
+        sig: &PartialSignature,
+    ) -> Result<(), Error> {
+        let a = self.coefficient(pubkey)?;
+        let Session { b, e, r } = self.session(nonces, msg)?;
+        let s = sig.to_scalar()?;
+        let (r1, r2) = (to_point(&nonce.r1), to_point(&nonce.r2));
+        let ri = g!(r1 + b * r2).mark::<Normal>();
+        let ri = ri.conditional_negate(!r.is_y_even());
+        let p = to_point(pubkey).conditional_negate(self.negate_keys());
+        match g!(s * G) == g!(ri + (e * a) * p) {
+            true => Ok(()),
+            false => Err(Error::InvalidSignature),
+        }
+    }
+
+    /// Combine the partial signatures of both participants into a Schnorr signature valid for
+    /// the [`aggregate_key`](Self::aggregate_key).
+    pub fn aggregate_signatures(
+        &self,
+        nonces: &[PublicNonce; 2],
+        msg: &Message,
+        sigs: &[PartialSignature; 2],
+    ) -> Result<Signature, Error> {
+        let Session { e, r, .. } = self.session(nonces, msg)?;
+        let (s0, s1) = (sigs[0].to_scalar()?, sigs[1].to_scalar()?);
+        let mut t = self.tweak;
+        t.conditional_negate(!self.aggregate.is_y_even());
+        let s = sc!(s0 + s1 + e * t);
+        let mut bytes = x_bytes(&r).to_vec();
+        bytes.extend_from_slice(&s.to_bytes());
+        Signature::from_slice(&bytes).map_err(Error::new)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::str::FromStr;
+
+    use bitcoin::secp256k1::Secp256k1;
+
+    fn keys() -> (SecretKey, PublicKey, SecretKey, PublicKey) {
+        let secp = Secp256k1::new();
+        let sk_a = SecretKey::from_slice(&[1u8; 32]).unwrap();
+        let sk_b = SecretKey::from_slice(&[2u8; 32]).unwrap();
+        (
+            sk_a,
+            PublicKey::from_secret_key(&secp, &sk_a),
+            sk_b,
+            PublicKey::from_secret_key(&secp, &sk_b),
+        )
+    }
+
+    fn sign(agg: &KeyAggregate, msg: &Message) -> Signature {
+        let (sk_a, pk_a, sk_b, pk_b) = keys();
+        let (secnonce_a, nonce_a) = generate_nonce(&sk_a, msg, b"session");
+        let (secnonce_b, nonce_b) = generate_nonce(&sk_b, msg, b"session");
+        let nonces = [nonce_a, nonce_b];
+
+        let sig_a = agg.partial_sign(&sk_a, secnonce_a, &nonces, msg).unwrap();
+        let sig_b = agg.partial_sign(&sk_b, secnonce_b, &nonces, msg).unwrap();
+        assert!(agg
+            .verify_partial(&pk_a, &nonce_a, &nonces, msg, &sig_a)
+            .is_ok());
+        assert!(agg
+            .verify_partial(&pk_b, &nonce_b, &nonces, msg, &sig_b)
+            .is_ok());
+        // A partial signature does not verify for the other participant
+        assert!(agg
+            .verify_partial(&pk_b, &nonce_b, &nonces, msg, &sig_a)
+            .is_err());
+
+        agg.aggregate_signatures(&nonces, msg, &[sig_a, sig_b])
+            .unwrap()
+    }
+
+    #[test]
+    fn partial_signatures_combine_into_valid_signature() {
+        let secp = Secp256k1::new();
+        let (_, pk_a, _, pk_b) = keys();
+        let msg = Message::from_slice(&[42u8; 32]).unwrap();
+
+        let agg = KeyAggregate::new(pk_a, pk_b).unwrap();
+        assert_eq!(
+            agg.aggregate_key(),
+            KeyAggregate::new(pk_b, pk_a).unwrap().aggregate_key()
+        );
+        for _ in 0..8 {
+            let sig = sign(&agg, &msg);
+            assert!(secp
+                .verify_schnorr(&sig, &msg, &agg.aggregate_key())
+                .is_ok());
+        }
+    }
+
+    #[test]
+    fn bip327_key_aggregation_vectors() {
+        let keys: Vec<PublicKey> = [
+            "02F9308A019258C31049344F85F89D5229B531C845836F99B08601F113BCE036F9",
+            "03DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659",
+            "023590A94E768F8E1815C2F24B4D80A8E3149316C3518CE7B7AD338368D038CA66",
+        ]
+        .iter()
+        .map(|k| PublicKey::from_str(k).unwrap())
+        .collect();
+        let vectors: [(&[usize], &str); 4] = [
+            (
+                &[0, 1, 2],
+                "90539EEDE565F5D054F32CC0C220126889ED1E5D193BAF15AEF344FE59D4610C",
+            ),
+            (
+                &[2, 1, 0],
+                "6204DE8B083426DC6EAF9502D27024D53FC826BF7D2012148A0575435DF54B2B",
+            ),
+            (
+                &[0, 0, 0],
+                "B436E3BAD62B8CD409969A224731C193D051162D8C5AE8B109306127DA3AA935",
+            ),
+            (
+                &[0, 0, 1, 1],
+                "69BC22BFA5D106306E48A20679DE1D7389386124D07571D0D872686028C26A3E",
+            ),
+        ];
+        for (indices, expected) in vectors {
+            let keys: Vec<PublicKey> = indices.iter().map(|i| keys[*i]).collect();
+            let aggregate = key_agg(&keys, &key_agg_coefficients(&keys)).unwrap();
+            let expected = XOnlyPublicKey::from_str(&expected.to_lowercase()).unwrap();
+            assert_eq!(x_bytes(&aggregate), expected.serialize());
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "strict-encoding")]
+    fn partial_signature_message() {
+        use crate::protocol::message::MuSigPartialSignature;
+        use crate::swap::SwapId;
+        use crate::transaction::TxLabel;
+
+        let msg = MuSigPartialSignature {
+            swap_id: SwapId::random(),
+            label: TxLabel::Buy,
+            partial_sig: PartialSignature([1u8; 32]),
+        };
+        let bytes = strict_encoding::strict_serialize(&msg).unwrap();
+        let res: MuSigPartialSignature<PartialSignature> =
+            strict_encoding::strict_deserialize(&bytes).unwrap();
+        assert_eq!(res, msg);
+    }
+
+    #[test]
+    fn nonces_are_fresh_for_each_session() {
+        let (sk_a, _, _, _) = keys();
+        let msg = Message::from_slice(&[42u8; 32]).unwrap();
+        let (_, nonce) = generate_nonce(&sk_a, &msg, b"session");
+        assert_ne!(generate_nonce(&sk_a, &msg, b"session").1, nonce);
+        assert_ne!(generate_nonce(&sk_a, &msg, b"other session").1, nonce);
+        assert_ne!(nonce.r1, nonce.r2);
+    }
+
+    #[test]
+    fn canonical_bytes_roundtrip() {
+        let (sk_a, _, _, _) = keys();
+        let msg = Message::from_slice(&[42u8; 32]).unwrap();
+        let (_, nonce) = generate_nonce(&sk_a, &msg, b"session");
+        let bytes = nonce.as_canonical_bytes();
+        assert_eq!(PublicNonce::from_canonical_bytes(&bytes).unwrap(), nonce);
+        assert!(PublicNonce::from_canonical_bytes(&bytes[1..]).is_err());
+
+        let sig = PartialSignature([1u8; 32]);
+        let bytes = sig.as_canonical_bytes();
+        assert_eq!(PartialSignature::from_canonical_bytes(&bytes).unwrap(), sig);
+        assert!(PartialSignature::from_canonical_bytes(&[0xff; 32]).is_err());
+    }
+}
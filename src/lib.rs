@@ -47,12 +47,18 @@
 //! allow defining the set of transaction to build and use during the swap execution.
 //!
 //! ### Features
-//! As default the `experimental` feature is enable.
+//! As default the `experimental`, `taproot`, `serde`, and `strict-encoding` features are enabled.
 //!
 //! - **experimental**: enable experimental cryptography, i.e. not battle tested nor peer reviewed,
-//! use it at your own risks.
+//!   use it at your own risks.
 //! - **taproot**: enable support for Bitcoin Taproot on-chain scripts as the arbitrating engine
-//! method.
+//!   method.
+//! - **serde**: implement `serde` serialization on the library types.
+//! - **strict-encoding**: implement `strict_encoding` on the library types, wrapping their
+//!   consensus encoding.
+//!
+//! Disable the default features to get a compact build speaking only the binary consensus
+//! protocol, e.g. with `default-features = false, features = ["experimental"]`.
 
 #![cfg_attr(docsrs, feature(doc_cfg))]
 #![cfg_attr(feature = "nightly", feature(stmt_expr_attributes))]
@@ -66,12 +72,16 @@
 #[macro_use]
 extern crate amplify;
 
+#[cfg(feature = "serde")]
 #[macro_use]
 extern crate serde;
 
 #[macro_use]
 extern crate clap;
 
+// Allow the derive macros to refer to this crate as `farcaster_core` from within.
+extern crate self as farcaster_core;
+
 use thiserror::Error;
 
 #[macro_use]
@@ -80,6 +90,7 @@ pub mod consensus;
 pub mod bitcoin;
 pub mod blockchain;
 pub mod crypto;
+#[cfg(feature = "serde")]
 pub(crate) mod hash;
 pub mod monero;
 pub mod protocol;
@@ -110,6 +121,20 @@ pub enum Error {
     /// A trade error.
     #[error("Trade error: {0}")]
     Trade(#[from] trade::Error),
+    /// A required parameter is missing for the swap role.
+    #[error("Missing parameter: {0}")]
+    MissingParameter(&'static str),
+    /// A parameter is present but not expected for the swap role.
+    #[error("Unexpected parameter: {0}")]
+    UnexpectedParameter(&'static str),
+    /// Messages grouped together do not relate to the same swap.
+    #[error("Swap identifier mismatch: expected {expected:#x}, found {found:#x}")]
+    SwapIdMismatch {
+        /// The identifier of the swap the messages are grouped for.
+        expected: swap::SwapId,
+        /// The identifier carried by the mismatching message.
+        found: swap::SwapId,
+    },
 }
 
 /// Result of an high level computation such as in Alice and Bob roles executing the protocol,
@@ -17,14 +17,16 @@
 //! Implementation of the Monero blockchain as an accordant blockchain in a swap. This
 //! implementation should work in pair with any other arbitrating implementation, like Bitcoin.
 
-use crate::blockchain::Network;
+use crate::blockchain::{AssetUnit, Blockchain, Network, Networked};
 use crate::consensus::{self, CanonicalBytes, Decodable, Encodable};
 use crate::crypto::{self, AccordantKeySet, AccordantKeys, DeriveKeys, SharedKeyId};
 use crate::role::Accordant;
 
+use curve25519_dalek::scalar::Scalar;
 use monero::util::key::{PrivateKey, PublicKey};
 use monero::Address;
 use monero::Amount;
+use rand_alt::{CryptoRng, RngCore};
 
 use std::fmt::{self, Debug};
 use std::io;
@@ -89,6 +91,25 @@ impl Accordant<PublicKey, PrivateKey, Address> for Monero {
     }
 }
 
+/// Combine the participant's own spend key share with the counter-party share recovered from the
+/// adaptor signature to get the full private spend key of the accordant lock address. The shares
+/// are added modulo the curve order and the resulting public key must match the joint public spend
+/// key agreed during the swap, fails with [`crypto::Error::InvalidAdaptorKey`] otherwise.
+///
+/// The recovered secret is a Monero private key, i.e. a little-endian scalar. Secrets recovered
+/// as secp256k1 secret keys are big-endian and must have their bytes reversed first.
+pub fn combine_spend_key(
+    own_share: &PrivateKey,
+    recovered_secret: &PrivateKey,
+    joint_public_spend: &PublicKey,
+) -> Result<PrivateKey, crypto::Error> {
+    let spend = own_share + recovered_secret;
+    if &PublicKey::from_private_key(&spend) != joint_public_spend {
+        return Err(crypto::Error::InvalidAdaptorKey);
+    }
+    Ok(spend)
+}
+
 impl From<Network> for monero::Network {
     fn from(network: Network) -> Self {
         match network {
@@ -109,6 +130,12 @@ impl From<monero::Network> for Network {
     }
 }
 
+impl Networked for Address {
+    fn network(&self) -> Network {
+        self.network.into()
+    }
+}
+
 impl std::str::FromStr for Monero {
     type Err = crate::consensus::Error;
 
@@ -141,6 +168,18 @@ impl CanonicalBytes for Amount {
     }
 }
 
+impl AssetUnit for Amount {
+    const BLOCKCHAIN: Blockchain = Blockchain::Monero;
+
+    fn as_atomic_units(&self) -> u64 {
+        self.as_pico()
+    }
+
+    fn from_atomic_units(units: u64) -> Self {
+        Amount::from_pico(units)
+    }
+}
+
 impl CanonicalBytes for Address {
     fn as_canonical_bytes(&self) -> Vec<u8> {
         self.as_bytes()
@@ -167,6 +206,11 @@ impl DeriveKeys for Monero {
         // Share one key: the private view key
         vec![SharedKeyId::new(SHARED_VIEW_KEY_ID)]
     }
+
+    fn generate_keypair<R: RngCore + CryptoRng>(rng: &mut R) -> (PrivateKey, PublicKey) {
+        let private_key = PrivateKey::from_scalar(Scalar::random(rng));
+        (private_key, PublicKey::from_private_key(&private_key))
+    }
 }
 
 impl CanonicalBytes for PrivateKey {
@@ -178,7 +222,9 @@ impl CanonicalBytes for PrivateKey {
     where
         Self: Sized,
     {
-        PrivateKey::from_slice(bytes).map_err(consensus::Error::new)
+        consensus::check_length(bytes, 32)?;
+        PrivateKey::from_slice(bytes)
+            .map_err(|_| consensus::Error::InvalidEncoding("not a canonical ed25519 scalar"))
     }
 }
 
@@ -191,6 +237,91 @@ impl CanonicalBytes for PublicKey {
     where
         Self: Sized,
     {
-        PublicKey::from_slice(bytes).map_err(consensus::Error::new)
+        consensus::check_length(bytes, 32)?;
+        PublicKey::from_slice(bytes)
+            .map_err(|_| consensus::Error::InvalidEncoding("not a valid ed25519 point"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn public_key_canonical_bytes_errors() {
+        // y = 2 has no matching x coordinate on the curve
+        let mut not_on_curve = [0u8; 32];
+        not_on_curve[0] = 2;
+        assert!(matches!(
+            PublicKey::from_canonical_bytes(&[0u8; 31]),
+            Err(consensus::Error::InvalidLength {
+                expected: 32,
+                found: 31
+            })
+        ));
+        assert!(matches!(
+            PublicKey::from_canonical_bytes(&not_on_curve),
+            Err(consensus::Error::InvalidEncoding(_))
+        ));
+        let key = PublicKey::from_private_key(&PrivateKey::from_slice(&[1u8; 32]).unwrap());
+        assert_eq!(
+            key,
+            PublicKey::from_canonical_bytes(&key.as_canonical_bytes()).unwrap()
+        );
+    }
+
+    #[test]
+    fn combine_known_spend_key_shares() {
+        let mut one = [0u8; 32];
+        one[0] = 1;
+        let mut two = [0u8; 32];
+        two[0] = 2;
+        let mut three = [0u8; 32];
+        three[0] = 3;
+        // Curve order minus one, little-endian
+        let l_minus_one =
+            hex::decode("ecd3f55c1a631258d69cf7a2def9de1400000000000000000000000000000010")
+                .unwrap();
+
+        let own = PrivateKey::from_slice(&one).unwrap();
+        let recovered = PrivateKey::from_slice(&two).unwrap();
+        let joint = PublicKey::from_private_key(&own) + PublicKey::from_private_key(&recovered);
+        let spend = combine_spend_key(&own, &recovered, &joint).unwrap();
+        assert_eq!(spend, PrivateKey::from_slice(&three).unwrap());
+
+        // The sum is reduced modulo the curve order
+        let own = PrivateKey::from_slice(&l_minus_one).unwrap();
+        let joint = PublicKey::from_private_key(&own) + PublicKey::from_private_key(&recovered);
+        let spend = combine_spend_key(&own, &recovered, &joint).unwrap();
+        assert_eq!(spend, PrivateKey::from_slice(&one).unwrap());
+
+        // A wrong recovered secret does not match the joint public spend key
+        assert!(matches!(
+            combine_spend_key(&own, &own, &joint),
+            Err(crypto::Error::InvalidAdaptorKey)
+        ));
+    }
+
+    #[test]
+    fn private_key_canonical_bytes_errors() {
+        assert!(matches!(
+            PrivateKey::from_canonical_bytes(&[0u8; 31]),
+            Err(consensus::Error::InvalidLength {
+                expected: 32,
+                found: 31
+            })
+        ));
+        assert!(matches!(
+            PrivateKey::from_canonical_bytes(&[0xffu8; 32]),
+            Err(consensus::Error::InvalidEncoding(_))
+        ));
+    }
+
+    #[test]
+    fn generate_matching_keypair() {
+        let (private_key, public_key) = Monero::generate_keypair(&mut rand_alt::thread_rng());
+        assert_eq!(public_key, PublicKey::from_private_key(&private_key));
+        let (other, _) = Monero::generate_keypair(&mut rand_alt::thread_rng());
+        assert_ne!(private_key, other);
     }
 }
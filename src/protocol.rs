@@ -20,9 +20,11 @@
 // For this file we allow having complex types
 #![allow(clippy::type_complexity)]
 
+use std::convert::TryInto;
+use std::hash::Hash;
 use std::io;
 
-use crate::blockchain::{Fee, FeePriority, FeeStrategy, Transactions};
+use crate::blockchain::{Fee, FeePriority, FeeStrategy, Networked, Transactions};
 use crate::consensus::{self, CanonicalBytes, Decodable, Encodable};
 use crate::crypto::{
     self, AccordantKeyId, ArbitratingKeyId, Commit, DeriveKeys, EncSign, KeyGenerator,
@@ -30,18 +32,20 @@ use crate::crypto::{
     TaggedSharedKeys,
 };
 use crate::protocol::message::{
-    BuyProcedureSignature, CommitAliceParameters, CommitBobParameters, CoreArbitratingSetup,
-    RevealAliceParameters, RevealBobParameters,
+    BuyProcedureSignature, CommitAliceParameters, CommitBobParameters, CommitParameters,
+    CoreArbitratingSetup, RevealAliceParameters, RevealBobParameters,
 };
+use crate::role::SwapRole;
 use crate::script::{DataLock, DataPunishableLock, DoubleKeys, ScriptPath};
 use crate::swap::SwapId;
-use crate::trade::Deal;
+use crate::trade::{self, Deal};
 use crate::transaction::{
     Buyable, Cancelable, Chainable, Fundable, Lockable, Punishable, Refundable, Transaction,
     Witnessable,
 };
 use crate::{Error, Res};
 
+pub mod bundle;
 pub mod message;
 
 struct ValidatedCoreTransactions<Px, Ti, Pk> {
@@ -53,7 +57,8 @@ struct ValidatedCoreTransactions<Px, Ti, Pk> {
 
 /// Container for the three main transactions used as the arbitrating engine on-chain. The `lock`,
 /// the lock `cancel`, and the cancel `refund`.
-#[derive(Debug, Clone, Copy, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct CoreArbitratingTransactions<Px> {
     /// Partial transaction raw type representing the lock.
     pub lock: Px,
@@ -81,23 +86,36 @@ impl<Px> CoreArbitratingTransactions<Px> {
 
 /// Container for the set of parameters needed to build or verify some parameters on the
 /// [`CoreArbitratingTransactions`].
-#[derive(Debug, Clone, Copy, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct ArbitratingParameters<Amt, Ti, F> {
     pub arbitrating_amount: Amt,
     pub cancel_timelock: Ti,
     pub punish_timelock: Ti,
     pub fee_strategy: FeeStrategy<F>,
+    /// Metadata embedded in the lock transaction, both participants must agree on it.
+    pub lock_metadata: Option<Vec<u8>>,
+}
+
+impl<Amt, Ti, F> ArbitratingParameters<Amt, Ti, F> {
+    /// Set the metadata to embed in the lock transaction, see [`Lockable::initialize`].
+    pub fn with_lock_metadata(mut self, metadata: &[u8]) -> Self {
+        self.lock_metadata = Some(metadata.to_vec());
+        self
+    }
 }
 
 /// A pair of signatures, one regular and one encrypted.
-#[derive(Debug, Clone, Copy, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct TxSignatures<Sig> {
     pub sig: Sig,
     pub adapted_sig: Sig,
 }
 
 /// The partial `punish` transaction with its signature.
-#[derive(Debug, Clone, Copy, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct FullySignedPunish<Px, Sig> {
     pub punish: Px,
     pub punish_sig: Sig,
@@ -113,18 +131,19 @@ pub struct FullySignedPunish<Px, Sig> {
 ///
 /// Timelocks and fee strategy are only present in the `local` set of parameters and not part of
 /// the reveal process, thus they are optional too.
-#[derive(Debug, Clone, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Parameters<Pk, Qk, Rk, Sk, Addr, Ti, F, Pr> {
     pub buy: Pk,
     pub cancel: Pk,
     pub refund: Pk,
     pub punish: Option<Pk>,
     pub adaptor: Pk,
-    pub extra_arbitrating_keys: Vec<TaggedElement<u16, Pk>>,
-    pub arbitrating_shared_keys: Vec<TaggedElement<SharedKeyId, Rk>>,
+    pub extra_arbitrating_keys: TaggedExtraKeys<Pk>,
+    pub arbitrating_shared_keys: TaggedSharedKeys<Rk>,
     pub spend: Qk,
-    pub extra_accordant_keys: Vec<TaggedElement<u16, Qk>>,
-    pub accordant_shared_keys: Vec<TaggedElement<SharedKeyId, Sk>>,
+    pub extra_accordant_keys: TaggedExtraKeys<Qk>,
+    pub accordant_shared_keys: TaggedSharedKeys<Sk>,
     pub proof: Option<Pr>,
     pub destination_address: Addr,
     pub cancel_timelock: Option<Ti>,
@@ -211,47 +230,34 @@ where
 
 impl_strict_encoding!(Parameters<Pk, Qk, Rk, Sk, Addr, Ti, F, Pr>, Pk: CanonicalBytes, Qk: CanonicalBytes, Rk: CanonicalBytes, Sk: CanonicalBytes, Addr: CanonicalBytes, Ti: CanonicalBytes, F: CanonicalBytes, Pr: CanonicalBytes);
 
-/// Transform a vector of tagged elements `K` into a vector of tagged commitments.
+/// Transform a collection of tagged elements `K` into a collection of tagged commitments.
 /// [`CanonicalBytes`] are used for computing the commitment of each elements.
-pub fn commit_to_vec<T: Clone + Eq, K: CanonicalBytes, C: Clone + Eq>(
+pub fn commit_to_vec<T: Clone + Hash + Eq, K: CanonicalBytes, C: Clone + Eq>(
     wallet: &impl Commit<C>,
-    keys: &[TaggedElement<T, K>],
+    keys: &TaggedElements<T, K>,
 ) -> TaggedElements<T, C> {
-    keys.iter()
-        .map(|tagged_key| {
-            TaggedElement::new(
-                tagged_key.tag().clone(),
-                wallet.commit_to(tagged_key.elem().as_canonical_bytes()),
-            )
-        })
-        .collect()
+    keys.map(|key| wallet.commit_to(key.as_canonical_bytes()))
 }
 
-/// Verifies a vector of tagged commitments against a vector of revealed tagged elements. Fails if
-/// a tag is not found in the commitments or doesn't match the commitment.  [`CanonicalBytes`] are
-/// used for computing the commitment of each elements.
-pub fn verify_vec_of_commitments<T: Eq, K: CanonicalBytes, C: Clone + Eq>(
+/// Verifies a collection of tagged commitments against a collection of revealed tagged elements.
+/// Fails if the revealed tags do not match the committed tags or if an element doesn't match its
+/// commitment. [`CanonicalBytes`] are used for computing the commitment of each elements.
+pub fn verify_vec_of_commitments<T: Clone + Hash + Eq, K: CanonicalBytes, C: Clone + Eq>(
     wallet: &impl Commit<C>,
-    keys: Vec<TaggedElement<T, K>>,
-    commitments: &[TaggedElement<T, C>],
+    keys: &TaggedElements<T, K>,
+    commitments: &TaggedElements<T, C>,
 ) -> Result<(), Error> {
-    keys.into_iter()
-        .map(|tagged_key| {
-            commitments
-                .iter()
-                .find(|tagged_commitment| tagged_commitment.tag() == tagged_key.tag())
-                .map(|tagged_commitment| {
-                    wallet
-                        .validate(
-                            tagged_key.elem().as_canonical_bytes(),
-                            tagged_commitment.elem().clone(),
-                        )
-                        .map_err(Error::Crypto)
-                })
-                .ok_or(Error::Crypto(crypto::Error::InvalidCommitment))
-        })
-        .collect::<Result<Vec<_>, _>>()
-        .map(|_| ())
+    if keys.len() != commitments.len() {
+        return Err(Error::Crypto(crypto::Error::InvalidCommitment));
+    }
+    keys.iter().try_for_each(|tagged_key| {
+        let commitment = commitments
+            .get(tagged_key.tag())
+            .ok_or(Error::Crypto(crypto::Error::InvalidCommitment))?;
+        wallet
+            .validate(tagged_key.elem().as_canonical_bytes(), commitment.clone())
+            .map_err(Error::Crypto)
+    })
 }
 
 impl<Pk, Qk, Rk, Sk, Addr, Ti, F, Pr> Parameters<Pk, Qk, Rk, Sk, Addr, Ti, F, Pr>
@@ -325,6 +331,31 @@ where
         }
     }
 
+    /// Create the role independent commitment message based on the set of parameters. The punish
+    /// key is committed if present, i.e. the message is Alice's commitment, and omitted otherwise.
+    pub fn commit<C: Clone + Eq>(
+        &self,
+        swap_id: SwapId,
+        wallet: &impl Commit<C>,
+    ) -> CommitParameters<C> {
+        CommitParameters {
+            swap_id,
+            buy: wallet.commit_to(self.buy.as_canonical_bytes()),
+            cancel: wallet.commit_to(self.cancel.as_canonical_bytes()),
+            refund: wallet.commit_to(self.refund.as_canonical_bytes()),
+            punish: self
+                .punish
+                .as_ref()
+                .map(|punish| wallet.commit_to(punish.as_canonical_bytes())),
+            adaptor: wallet.commit_to(self.adaptor.as_canonical_bytes()),
+            extra_arbitrating_keys: commit_to_vec(wallet, &self.extra_arbitrating_keys),
+            arbitrating_shared_keys: commit_to_vec(wallet, &self.arbitrating_shared_keys),
+            spend: wallet.commit_to(self.spend.as_canonical_bytes()),
+            extra_accordant_keys: commit_to_vec(wallet, &self.extra_accordant_keys),
+            accordant_shared_keys: commit_to_vec(wallet, &self.accordant_shared_keys),
+        }
+    }
+
     /// Create the reveal protocol message based on the set of parameters.
     pub fn reveal_bob(self, swap_id: SwapId) -> RevealBobParameters<Pk, Qk, Rk, Sk, Addr> {
         RevealBobParameters {
@@ -343,6 +374,120 @@ where
     }
 }
 
+impl<Pk, Qk, Rk, Sk, Addr, Ti, F, Pr> Parameters<Pk, Qk, Rk, Sk, Addr, Ti, F, Pr> {
+    /// Validate the parameters against the deal: the destination address must be on the deal's
+    /// network and the timelocks, if present, must match the ones in the deal. Fails with
+    /// [`trade::Error::NetworkMismatch`] or [`trade::Error::TimelockMismatch`].
+    pub fn verify_against_deal<Amt, Bmt>(&self, deal: &Deal<Amt, Bmt, Ti, F>) -> Result<(), Error>
+    where
+        Addr: Networked,
+        Ti: PartialEq,
+    {
+        let expected = deal.parameters.network;
+        let found = self.destination_address.network();
+        if expected != found {
+            return Err(trade::Error::NetworkMismatch { expected, found }.into());
+        }
+        if let Some(cancel_timelock) = &self.cancel_timelock {
+            if cancel_timelock != &deal.parameters.cancel_timelock {
+                return Err(trade::Error::TimelockMismatch("cancel").into());
+            }
+        }
+        if let Some(punish_timelock) = &self.punish_timelock {
+            if punish_timelock != &deal.parameters.punish_timelock {
+                return Err(trade::Error::TimelockMismatch("punish").into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Fill the timelocks and the fee strategy, left empty by the reveal process, with the values
+    /// agreed in the deal. Fields already set must match the deal, otherwise the parameters are
+    /// left untouched and the merge fails with [`trade::Error::TimelockMismatch`] or
+    /// [`trade::Error::FeeStrategyMismatch`].
+    pub fn merge_deal<Amt, Bmt>(&mut self, deal: &Deal<Amt, Bmt, Ti, F>) -> Result<(), Error>
+    where
+        Ti: Clone + PartialEq,
+        F: Clone + PartialEq,
+    {
+        let params = &deal.parameters;
+        if matches!(&self.cancel_timelock, Some(t) if t != &params.cancel_timelock) {
+            return Err(trade::Error::TimelockMismatch("cancel").into());
+        }
+        if matches!(&self.punish_timelock, Some(t) if t != &params.punish_timelock) {
+            return Err(trade::Error::TimelockMismatch("punish").into());
+        }
+        if matches!(&self.fee_strategy, Some(f) if f != &params.fee_strategy) {
+            return Err(trade::Error::FeeStrategyMismatch.into());
+        }
+        self.cancel_timelock = Some(params.cancel_timelock.clone());
+        self.punish_timelock = Some(params.punish_timelock.clone());
+        self.fee_strategy = Some(params.fee_strategy.clone());
+        Ok(())
+    }
+
+    /// Validate that the parameters are fully populated for the given swap role before using them
+    /// to build transactions: only Alice has a punish key, and the proof, the timelocks, and the
+    /// fee strategy must be set. Fails with [`Error::MissingParameter`] or
+    /// [`Error::UnexpectedParameter`] naming the faulty field.
+    pub fn validate_complete(&self, role: SwapRole) -> Result<(), Error> {
+        match (role, &self.punish) {
+            (SwapRole::Alice, None) => return Err(Error::MissingParameter("punish")),
+            (SwapRole::Bob, Some(_)) => return Err(Error::UnexpectedParameter("punish")),
+            _ => (),
+        }
+        if self.proof.is_none() {
+            return Err(Error::MissingParameter("proof"));
+        }
+        if self.cancel_timelock.is_none() {
+            return Err(Error::MissingParameter("cancel_timelock"));
+        }
+        if self.punish_timelock.is_none() {
+            return Err(Error::MissingParameter("punish_timelock"));
+        }
+        if self.fee_strategy.is_none() {
+            return Err(Error::MissingParameter("fee_strategy"));
+        }
+        Ok(())
+    }
+
+    /// Add an extra arbitrating public key tagged with `tag`, fails with
+    /// [`crypto::Error::DuplicateKey`] if a key with the same tag is already present.
+    pub fn with_extra_arbitrating_key(mut self, tag: u16, key: Pk) -> Result<Self, crypto::Error> {
+        self.extra_arbitrating_keys.insert(tag, key)?;
+        Ok(self)
+    }
+
+    /// Add an arbitrating shared private key tagged with `id`, fails with
+    /// [`crypto::Error::DuplicateKey`] if a key with the same identifier is already present.
+    pub fn with_arbitrating_shared_key(
+        mut self,
+        id: SharedKeyId,
+        key: Rk,
+    ) -> Result<Self, crypto::Error> {
+        self.arbitrating_shared_keys.insert(id, key)?;
+        Ok(self)
+    }
+
+    /// Add an extra accordant public key tagged with `tag`, fails with
+    /// [`crypto::Error::DuplicateKey`] if a key with the same tag is already present.
+    pub fn with_extra_accordant_key(mut self, tag: u16, key: Qk) -> Result<Self, crypto::Error> {
+        self.extra_accordant_keys.insert(tag, key)?;
+        Ok(self)
+    }
+
+    /// Add an accordant shared private key tagged with `id`, fails with
+    /// [`crypto::Error::DuplicateKey`] if a key with the same identifier is already present.
+    pub fn with_accordant_shared_key(
+        mut self,
+        id: SharedKeyId,
+        key: Sk,
+    ) -> Result<Self, crypto::Error> {
+        self.accordant_shared_keys.insert(id, key)?;
+        Ok(self)
+    }
+}
+
 /// Alice, a [`SwapRole`], starts with accordant blockchain assets and exchange them for
 /// arbitrating blockchain assets.
 ///
@@ -443,7 +588,8 @@ where
                 let key = key_gen.get_pubkey(ArbitratingKeyId::Extra(tag))?;
                 Ok(TaggedElement::new(tag, key))
             })
-            .collect();
+            .collect::<Res<Vec<_>>>()
+            .and_then(|keys| Ok(keys.try_into()?));
 
         let arbitrating_shared_keys: Res<TaggedSharedKeys<Rk>> = Ar::extra_shared_private_keys()
             .into_iter()
@@ -451,7 +597,8 @@ where
                 let key = key_gen.get_shared_key(tag)?;
                 Ok(TaggedElement::new(tag, key))
             })
-            .collect();
+            .collect::<Res<Vec<_>>>()
+            .and_then(|keys| Ok(keys.try_into()?));
 
         let extra_accordant_keys: Res<TaggedExtraKeys<Qk>> = Ac::extra_public_keys()
             .into_iter()
@@ -459,7 +606,8 @@ where
                 let key = key_gen.get_pubkey(AccordantKeyId::Extra(tag))?;
                 Ok(TaggedElement::new(tag, key))
             })
-            .collect();
+            .collect::<Res<Vec<_>>>()
+            .and_then(|keys| Ok(keys.try_into()?));
 
         let accordant_shared_keys: Res<TaggedSharedKeys<Sk>> = Ac::extra_shared_private_keys()
             .into_iter()
@@ -467,7 +615,8 @@ where
                 let key = key_gen.get_shared_key(tag)?;
                 Ok(TaggedElement::new(tag, key))
             })
-            .collect();
+            .collect::<Res<Vec<_>>>()
+            .and_then(|keys| Ok(keys.try_into()?));
 
         let (spend, adaptor, proof) = key_gen.generate_proof()?;
 
@@ -518,7 +667,7 @@ where
     ///
     ///  * Parse the refund partial transaction in core arbitrating set
     ///  * Validate the [`Lockable`], [`Cancelable`], [`Refundable`] partial transactions in
-    ///  [`CoreArbitratingTransactions`]
+    ///    [`CoreArbitratingTransactions`]
     ///  * Retrieve Bob's adaptor public key from [`Parameters`]
     ///  * Retrieve Alice's refund public key from [`Parameters`]
     ///  * Generate the witness data and adaptor sign it
@@ -543,7 +692,7 @@ where
     {
         // Verifies the core arbitrating transactions.
         let ValidatedCoreTransactions { refund, .. } =
-            self.validate_core(alice_parameters, bob_parameters, core, arb_params)?;
+            self.validate_core(alice_parameters, bob_parameters, core, &arb_params)?;
 
         // Generate the witness message to sign and adaptor sign with the refund key and the
         // counter-party adaptor.
@@ -577,7 +726,7 @@ where
     ///
     ///  * Parse the [`Cancelable`] partial transaction in [`CoreArbitratingTransactions`]
     ///  * Validate the [`Lockable`], [`Cancelable`], [`Refundable`] partial transactions in
-    ///  [`CoreArbitratingTransactions`]
+    ///    [`CoreArbitratingTransactions`]
     ///  * Retreive Alice's cancel public key from the parameters
     ///  * Generate the witness data and sign it
     ///
@@ -601,7 +750,7 @@ where
     {
         // Verifies the core arbitrating transactions.
         let ValidatedCoreTransactions { cancel, .. } =
-            self.validate_core(alice_parameters, bob_parameters, core, arb_params)?;
+            self.validate_core(alice_parameters, bob_parameters, core, &arb_params)?;
 
         // Generate the witness message to sign and sign with the cancel key.
         let cancel = <Ar::Cancel>::from_partial(cancel);
@@ -638,7 +787,7 @@ where
     ///
     ///  * Parse the [`Buyable`] partial transaction in [`BuyProcedureSignature`]
     ///  * Verify the adaptor witness in [`BuyProcedureSignature`] with the public keys from the
-    ///  parameters
+    ///    parameters
     ///
     pub fn validate_adaptor_buy<Amt, Px, Pk, Qk, Rk, Sk, Ti, F, Pr, S, Ms, Si, EncSig>(
         &self,
@@ -660,7 +809,7 @@ where
     {
         // Verifies the core arbitrating transactions.
         let ValidatedCoreTransactions { lock, .. } =
-            self.validate_core(alice_parameters, bob_parameters, core, arb_params)?;
+            self.validate_core(alice_parameters, bob_parameters, core, &arb_params)?;
         let lock = <Ar::Lock>::from_partial(lock);
 
         let fee_strategy = &arb_params.fee_strategy;
@@ -673,8 +822,7 @@ where
         let buy = <Ar::Buy>::from_partial(partial_buy);
 
         buy.is_build_on_top_of(&lock)?;
-        buy.verify_template(self.destination_address.clone())?;
-        buy.as_partial().validate_fee(fee_strategy)?;
+        buy.verify_template(self.destination_address.clone(), fee_strategy)?;
 
         // Verify the adaptor buy witness
         let msg = buy.generate_witness_message(ScriptPath::Success)?;
@@ -746,7 +894,7 @@ where
     {
         // Verifies the core arbitrating transactions.
         let ValidatedCoreTransactions { lock, .. } =
-            self.validate_core(alice_parameters, bob_parameters, core, arb_params)?;
+            self.validate_core(alice_parameters, bob_parameters, core, &arb_params)?;
         let lock = <Ar::Lock>::from_partial(lock);
 
         let fee_strategy = &arb_params.fee_strategy;
@@ -759,8 +907,7 @@ where
         let buy = <Ar::Buy>::from_partial(partial_buy);
 
         buy.is_build_on_top_of(&lock)?;
-        buy.verify_template(self.destination_address.clone())?;
-        buy.as_partial().validate_fee(fee_strategy)?;
+        buy.verify_template(self.destination_address.clone(), fee_strategy)?;
 
         // Generate the witness message to sign and sign with the buy key.
         let msg = buy.generate_witness_message(ScriptPath::Success)?;
@@ -820,7 +967,7 @@ where
             cancel,
             punish_lock,
             ..
-        } = self.validate_core(alice_parameters, bob_parameters, core, arb_params)?;
+        } = self.validate_core(alice_parameters, bob_parameters, core, &arb_params)?;
         let cancel = <Ar::Cancel>::from_partial(cancel);
 
         let fee_strategy = &arb_params.fee_strategy;
@@ -835,8 +982,7 @@ where
             .set_fee(fee_strategy, self.fee_politic)?;
 
         // Generate the witness message to sign and sign with the punish key.
-        let msg = punish.generate_witness_message(ScriptPath::Failure)?;
-        let punish_sig = wallet.sign(ArbitratingKeyId::Punish, msg)?;
+        let punish_sig = punish.generate_punish_witness(wallet)?;
 
         Ok(FullySignedPunish {
             punish: punish.to_partial(),
@@ -880,7 +1026,7 @@ where
         alice_parameters: &Parameters<Pk, Qk, Rk, Sk, Addr, Ti, F, Pr>,
         bob_parameters: &Parameters<Pk, Qk, Rk, Sk, Addr, Ti, F, Pr>,
         core: &CoreArbitratingTransactions<Px>,
-        arb_params: ArbitratingParameters<Amt, Ti, F>,
+        arb_params: &ArbitratingParameters<Amt, Ti, F>,
     ) -> Res<ValidatedCoreTransactions<Px, Ti, Pk>>
     where
         Ar: Transactions<Addr = Addr, Amt = Amt, Ti = Ti, Ms = Ms, Pk = Pk, Si = Si, Px = Px>,
@@ -912,15 +1058,13 @@ where
             failure: DoubleKeys::new(alice_cancel, bob_cancel),
         };
 
-        // Verify the lock transaction template.
-        lock.verify_template(data_lock)?;
+        // Verify the lock transaction template and that the transaction follows the strategy.
+        let fee_strategy = &arb_params.fee_strategy;
+        lock.verify_template(data_lock, arb_params.lock_metadata.as_deref(), fee_strategy)?;
         // The target amount is dictated from the deal.
         let target_amount = arb_params.arbitrating_amount;
         // Verify the target amount
         lock.verify_target_amount(target_amount)?;
-        // Validate that the transaction follows the strategy.
-        let fee_strategy = &arb_params.fee_strategy;
-        lock.as_partial().validate_fee(fee_strategy)?;
 
         // Get the three keys, Alice and Bob for refund and Alice's punish key. The keys are
         // needed, along with the timelock for the punish, to create the punishable on-chain
@@ -947,9 +1091,7 @@ where
         let cancel = <Ar::Cancel>::from_partial(partial_cancel);
         // Check that the cancel transaction is build on top of the lock.
         cancel.is_build_on_top_of(&lock)?;
-        cancel.verify_template(data_lock, punish_lock)?;
-        // Validate the fee strategy
-        cancel.as_partial().validate_fee(fee_strategy)?;
+        cancel.verify_template(data_lock, punish_lock, fee_strategy)?;
 
         // Extract the partial transaction from the core arbitrating protocol message, this
         // operation should not error if the message is well formed.
@@ -960,9 +1102,7 @@ where
         // Check that the refund transaction is build on top of the cancel transaction.
         refund.is_build_on_top_of(&cancel)?;
         let refund_address = bob_parameters.destination_address.clone();
-        refund.verify_template(refund_address)?;
-        // Validate the fee strategy
-        refund.as_partial().validate_fee(fee_strategy)?;
+        refund.verify_template(refund_address, fee_strategy)?;
 
         Ok(ValidatedCoreTransactions {
             lock: lock.to_partial(),
@@ -1082,7 +1222,8 @@ where
                 let key = key_gen.get_pubkey(ArbitratingKeyId::Extra(tag))?;
                 Ok(TaggedElement::new(tag, key))
             })
-            .collect();
+            .collect::<Res<Vec<_>>>()
+            .and_then(|keys| Ok(keys.try_into()?));
 
         let arbitrating_shared_keys: Res<TaggedSharedKeys<Rk>> = Ar::extra_shared_private_keys()
             .into_iter()
@@ -1090,7 +1231,8 @@ where
                 let key = key_gen.get_shared_key(tag)?;
                 Ok(TaggedElement::new(tag, key))
             })
-            .collect();
+            .collect::<Res<Vec<_>>>()
+            .and_then(|keys| Ok(keys.try_into()?));
 
         let extra_accordant_keys: Res<TaggedExtraKeys<Qk>> = Ac::extra_public_keys()
             .into_iter()
@@ -1098,7 +1240,8 @@ where
                 let key = key_gen.get_pubkey(AccordantKeyId::Extra(tag))?;
                 Ok(TaggedElement::new(tag, key))
             })
-            .collect();
+            .collect::<Res<Vec<_>>>()
+            .and_then(|keys| Ok(keys.try_into()?));
 
         let accordant_shared_keys: Res<TaggedSharedKeys<Sk>> = Ac::extra_shared_private_keys()
             .into_iter()
@@ -1106,7 +1249,8 @@ where
                 let key = key_gen.get_shared_key(tag)?;
                 Ok(TaggedElement::new(tag, key))
             })
-            .collect();
+            .collect::<Res<Vec<_>>>()
+            .and_then(|keys| Ok(keys.try_into()?));
 
         let (spend, adaptor, proof) = key_gen.generate_proof()?;
 
@@ -1156,9 +1300,11 @@ where
     ///  * The [`Fundable`] transaction
     ///  * The [`FeeStrategy`] and the [`FeePriority`]
     ///
-    /// The lock transaction is initialized by passing the [`DataLock`] structure, then the cancel
-    /// transaction is initialized based on the lock transaction with the [`DataPunishableLock`]
-    /// structure, then the punish is initialized based on the cancel transaction.
+    /// The funding transaction must pay the funding address with at least the arbitrating amount,
+    /// see [`Fundable::verify_funding`]. The lock transaction is initialized by passing the
+    /// [`DataLock`] structure, then the cancel transaction is initialized based on the lock
+    /// transaction with the [`DataPunishableLock`] structure, then the punish is initialized based
+    /// on the cancel transaction.
     ///
     /// # Transaction Fee
     ///
@@ -1171,7 +1317,7 @@ where
         &self,
         alice_parameters: &Parameters<Pk, Qk, Rk, Sk, Addr, Ti, F, Pr>,
         bob_parameters: &Parameters<Pk, Qk, Rk, Sk, Addr, Ti, F, Pr>,
-        funding: impl Fundable<Tx, Out, Addr, Pk>,
+        funding: impl Fundable<Tx, Out, Addr, Pk, Amt>,
         arb_params: ArbitratingParameters<Amt, Ti, F>,
     ) -> Res<CoreArbitratingTransactions<Px>>
     where
@@ -1207,14 +1353,24 @@ where
         // The target amount is dictated from the deal.
         let target_amount = arb_params.arbitrating_amount;
 
+        // Verify that the funding transaction seen on-chain pays the funding address with at least
+        // the target amount before building on top of it.
+        let funding_address = funding.get_address()?;
+        funding.verify_funding(&funding_address, target_amount)?;
+
         // Initialize the lockable transaction based on the fundable structure. The lockable
         // transaction prepare the on-chain contract for a buy or a cancel. The amount of available
         // assets is defined as the target by the deal.
-        let lock = <Ar::Lock>::initialize(&funding, cancel_lock, target_amount)?;
+        let lock = <Ar::Lock>::initialize(
+            &funding,
+            cancel_lock,
+            target_amount,
+            arb_params.lock_metadata.as_deref(),
+        )?;
 
-        // Ensure that the transaction contains enough assets to pass the fee validation latter.
+        // Ensure that the transaction pays a fee rate passing the fee validation latter.
         let fee_strategy = &arb_params.fee_strategy;
-        lock.as_partial().validate_fee(fee_strategy)?;
+        lock.as_partial().validate_rate(fee_strategy)?;
 
         // Get the three keys, Alice and Bob for refund and Alice's punish key. The keys are
         // needed, along with the timelock for the punish, to create the punishable on-chain
@@ -1583,3 +1739,62 @@ where
         wallet.recover_secret_key(buy_adaptor_sig, encryption_key, signature)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn empty_parameters() -> Parameters<u8, u8, u8, u8, u8, u8, u8, u8> {
+        Parameters {
+            buy: 1,
+            cancel: 2,
+            refund: 3,
+            punish: None,
+            adaptor: 4,
+            extra_arbitrating_keys: TaggedElements::new(),
+            arbitrating_shared_keys: TaggedElements::new(),
+            spend: 5,
+            extra_accordant_keys: TaggedElements::new(),
+            accordant_shared_keys: TaggedElements::new(),
+            proof: None,
+            destination_address: 6,
+            cancel_timelock: None,
+            punish_timelock: None,
+            fee_strategy: None,
+        }
+    }
+
+    #[test]
+    fn add_extra_keys_to_parameters() {
+        let params = empty_parameters()
+            .with_extra_arbitrating_key(1, 10)
+            .and_then(|p| p.with_extra_arbitrating_key(2, 11))
+            .and_then(|p| p.with_arbitrating_shared_key(SharedKeyId::new(1), 12))
+            .and_then(|p| p.with_extra_accordant_key(1, 13))
+            .and_then(|p| p.with_accordant_shared_key(SharedKeyId::new(1), 14))
+            .unwrap();
+        assert_eq!(params.extra_arbitrating_keys.len(), 2);
+        assert_eq!(params.extra_arbitrating_keys.get(&2), Some(&11));
+        assert_eq!(params.arbitrating_shared_keys.len(), 1);
+        assert_eq!(params.extra_accordant_keys.len(), 1);
+        assert_eq!(params.accordant_shared_keys.len(), 1);
+    }
+
+    #[test]
+    fn reject_duplicate_key_tags() {
+        let params = empty_parameters()
+            .with_extra_arbitrating_key(1, 10)
+            .unwrap();
+        assert!(matches!(
+            params.with_extra_arbitrating_key(1, 11),
+            Err(crypto::Error::DuplicateKey)
+        ));
+        let params = empty_parameters()
+            .with_accordant_shared_key(SharedKeyId::new(1), 10)
+            .unwrap();
+        assert!(matches!(
+            params.with_accordant_shared_key(SharedKeyId::new(1), 11),
+            Err(crypto::Error::DuplicateKey)
+        ));
+    }
+}
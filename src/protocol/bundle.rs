@@ -0,0 +1,239 @@
+// Copyright 2021-2022 Farcaster Devs
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; either
+// version 3 of the License, or (at your option) any later version.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301, USA
+
+//! Bundles group all the protocol messages of one swap step into a single unit that can be
+//! persisted or transmitted at once. Messages in a bundle must all relate to the same swap, see
+//! [`CommitRevealBundle::validate`] and [`ArbitratingSetupBundle::validate`].
+
+use std::io;
+
+use crate::consensus::{self, CanonicalBytes, Decodable, Encodable};
+use crate::protocol::message::{
+    BuyProcedureSignature, CommitAliceParameters, CommitBobParameters, CoreArbitratingSetup,
+    RefundProcedureSignatures, RevealAliceParameters, RevealBobParameters, RevealProof,
+    SwapMessage,
+};
+use crate::swap::SwapId;
+use crate::Error;
+
+/// Commit/reveal step of a swap participant: the commitment to the parameters, the revealed
+/// parameters, and the cross-group discrete logarithm proof.
+///
+/// - `Co` the commit message type, e.g. [`CommitAliceParameters`]
+/// - `Re` the reveal message type, e.g. [`RevealAliceParameters`]
+/// - `Pr` the proof type
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "Co: serde::Serialize, Re: serde::Serialize, Pr: CanonicalBytes",
+        deserialize = "Co: serde::Deserialize<'de>, Re: serde::Deserialize<'de>, Pr: CanonicalBytes"
+    ))
+)]
+pub struct CommitRevealBundle<Co, Re, Pr> {
+    /// The commitment to the parameters.
+    pub commit: Co,
+    /// The revealed parameters.
+    pub reveal: Re,
+    /// The revealed cross-group discrete logarithm zero-knowledge proof.
+    pub proof: RevealProof<Pr>,
+}
+
+/// Commit/reveal bundle of Alice.
+pub type AliceCommitRevealBundle<C, Pk, Qk, Rk, Sk, Addr, Pr> =
+    CommitRevealBundle<CommitAliceParameters<C>, RevealAliceParameters<Pk, Qk, Rk, Sk, Addr>, Pr>;
+
+/// Commit/reveal bundle of Bob.
+pub type BobCommitRevealBundle<C, Pk, Qk, Rk, Sk, Addr, Pr> =
+    CommitRevealBundle<CommitBobParameters<C>, RevealBobParameters<Pk, Qk, Rk, Sk, Addr>, Pr>;
+
+impl<Co, Re, Pr> CommitRevealBundle<Co, Re, Pr>
+where
+    Co: SwapMessage,
+    Re: SwapMessage,
+{
+    /// Returns the swap identifier of the commit message.
+    pub fn swap_id(&self) -> SwapId {
+        self.commit.swap_id()
+    }
+
+    /// Validate that all the messages of the bundle relate to the same swap, fails with
+    /// [`Error::SwapIdMismatch`] otherwise.
+    pub fn validate(&self) -> Result<(), Error> {
+        validate_swap_ids(
+            self.swap_id(),
+            &[self.reveal.swap_id(), self.proof.swap_id()],
+        )
+    }
+}
+
+impl<Co, Re, Pr> Encodable for CommitRevealBundle<Co, Re, Pr>
+where
+    Co: Encodable,
+    Re: Encodable,
+    Pr: CanonicalBytes,
+{
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        let mut len = self.commit.consensus_encode(s)?;
+        len += self.reveal.consensus_encode(s)?;
+        Ok(len + self.proof.consensus_encode(s)?)
+    }
+}
+
+impl<Co, Re, Pr> Decodable for CommitRevealBundle<Co, Re, Pr>
+where
+    Co: Decodable,
+    Re: Decodable,
+    Pr: CanonicalBytes,
+{
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        Ok(Self {
+            commit: Decodable::consensus_decode(d)?,
+            reveal: Decodable::consensus_decode(d)?,
+            proof: Decodable::consensus_decode(d)?,
+        })
+    }
+}
+
+impl_strict_encoding!(CommitRevealBundle<Co, Re, Pr>, Co: Encodable + Decodable, Re: Encodable + Decodable, Pr: CanonicalBytes);
+
+/// Arbitrating setup step of a swap: the core arbitrating transactions with Bob's cancel
+/// signature, Alice's refund procedure signatures, and Bob's buy procedure signature.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "Px: CanonicalBytes, Sig: CanonicalBytes, EncSig: CanonicalBytes",
+        deserialize = "Px: CanonicalBytes, Sig: CanonicalBytes, EncSig: CanonicalBytes"
+    ))
+)]
+pub struct ArbitratingSetupBundle<Px, Sig, EncSig> {
+    /// The core arbitrating setup sent by Bob.
+    pub core_arbitrating_setup: CoreArbitratingSetup<Px, Sig>,
+    /// The refund procedure signatures sent by Alice.
+    pub refund_procedure_signatures: RefundProcedureSignatures<Sig, EncSig>,
+    /// The buy procedure signature sent by Bob.
+    pub buy_procedure_signature: BuyProcedureSignature<Px, EncSig>,
+}
+
+impl<Px, Sig, EncSig> ArbitratingSetupBundle<Px, Sig, EncSig> {
+    /// Returns the swap identifier of the core arbitrating setup message.
+    pub fn swap_id(&self) -> SwapId {
+        self.core_arbitrating_setup.swap_id
+    }
+
+    /// Validate that all the messages of the bundle relate to the same swap, fails with
+    /// [`Error::SwapIdMismatch`] otherwise.
+    pub fn validate(&self) -> Result<(), Error> {
+        validate_swap_ids(
+            self.swap_id(),
+            &[
+                self.refund_procedure_signatures.swap_id,
+                self.buy_procedure_signature.swap_id,
+            ],
+        )
+    }
+}
+
+impl<Px, Sig, EncSig> Encodable for ArbitratingSetupBundle<Px, Sig, EncSig>
+where
+    Px: CanonicalBytes,
+    Sig: CanonicalBytes,
+    EncSig: CanonicalBytes,
+{
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        let mut len = self.core_arbitrating_setup.consensus_encode(s)?;
+        len += self.refund_procedure_signatures.consensus_encode(s)?;
+        Ok(len + self.buy_procedure_signature.consensus_encode(s)?)
+    }
+}
+
+impl<Px, Sig, EncSig> Decodable for ArbitratingSetupBundle<Px, Sig, EncSig>
+where
+    Px: CanonicalBytes,
+    Sig: CanonicalBytes,
+    EncSig: CanonicalBytes,
+{
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        Ok(Self {
+            core_arbitrating_setup: Decodable::consensus_decode(d)?,
+            refund_procedure_signatures: Decodable::consensus_decode(d)?,
+            buy_procedure_signature: Decodable::consensus_decode(d)?,
+        })
+    }
+}
+
+impl_strict_encoding!(ArbitratingSetupBundle<Px, Sig, EncSig>, Px: CanonicalBytes, Sig: CanonicalBytes, EncSig: CanonicalBytes);
+
+fn validate_swap_ids(expected: SwapId, swap_ids: &[SwapId]) -> Result<(), Error> {
+    match swap_ids.iter().find(|&&found| found != expected) {
+        Some(&found) => Err(Error::SwapIdMismatch { expected, found }),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Bundle = ArbitratingSetupBundle<[u8; 4], [u8; 2], [u8; 3]>;
+
+    fn bundle(swap_id: SwapId) -> Bundle {
+        ArbitratingSetupBundle {
+            core_arbitrating_setup: CoreArbitratingSetup {
+                swap_id,
+                lock: [1; 4],
+                cancel: [2; 4],
+                refund: [3; 4],
+                cancel_sig: [4; 2],
+            },
+            refund_procedure_signatures: RefundProcedureSignatures {
+                swap_id,
+                cancel_sig: [5; 2],
+                refund_adaptor_sig: [6; 3],
+            },
+            buy_procedure_signature: BuyProcedureSignature {
+                swap_id,
+                buy: [7; 4],
+                buy_adaptor_sig: [8; 3],
+            },
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "strict-encoding")]
+    fn encode_arbitrating_setup_bundle() {
+        let bundle = bundle(SwapId::random());
+        assert!(bundle.validate().is_ok());
+        let bytes = strict_encoding::strict_serialize(&bundle).unwrap();
+        let res: Bundle = strict_encoding::strict_deserialize(&bytes).unwrap();
+        assert_eq!(res, bundle);
+    }
+
+    #[test]
+    fn reject_bundle_with_mismatched_swap_ids() {
+        let swap_id = SwapId::random();
+        let mut bundle = bundle(swap_id);
+        let other = SwapId::random();
+        bundle.buy_procedure_signature.swap_id = other;
+        assert!(matches!(
+            bundle.validate(),
+            Err(Error::SwapIdMismatch { expected, found }) if expected == swap_id && found == other
+        ));
+    }
+}
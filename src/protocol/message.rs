@@ -17,53 +17,167 @@
 //! Protocol messages exchanged between swap daemons at each step of the swap protocol. These
 //! messages are untrusted and must be validated uppon reception by each swap participant.
 
+use std::convert::TryFrom;
 use std::fmt;
 use std::io;
 
+#[cfg(feature = "serde")]
+use crate::consensus::serde_hex;
 use crate::consensus::{self, CanonicalBytes, Decodable, Encodable};
-use crate::crypto::{Commit, SharedKeyId, TaggedElement};
+use crate::crypto::{
+    self, AdaptorSignature, Commit, ProveCrossGroupDleq, Sign, TaggedExtraKeys, TaggedSharedKeys,
+};
 use crate::protocol::Parameters;
 use crate::protocol::{verify_vec_of_commitments, CoreArbitratingTransactions};
+use crate::role::SwapRole;
+use crate::script::ScriptPath;
 use crate::swap::SwapId;
+use crate::transaction::{self, Buyable, Chainable, Linkable, Transaction, TxLabel, Witnessable};
 use crate::Error;
 
+/// A protocol message related to a swap, identified by its swap identifier.
+pub trait SwapMessage {
+    /// Returns the swap identifier related to this message.
+    fn swap_id(&self) -> SwapId;
+}
+
+macro_rules! impl_swap_message {
+    ($thing:ty $(, $args:ident)*) => {
+        impl<$($args),*> SwapMessage for $thing {
+            fn swap_id(&self) -> SwapId {
+                self.swap_id
+            }
+        }
+    };
+}
+
+impl_swap_message!(CommitAliceParameters<C>, C);
+impl_swap_message!(CommitBobParameters<C>, C);
+impl_swap_message!(CommitParameters<C>, C);
+impl_swap_message!(RevealProof<Pr>, Pr);
+impl_swap_message!(RevealAliceParameters<Pk, Qk, Rk, Sk, Addr>, Pk, Qk, Rk, Sk, Addr);
+impl_swap_message!(RevealBobParameters<Pk, Qk, Rk, Sk, Addr>, Pk, Qk, Rk, Sk, Addr);
+impl_swap_message!(CoreArbitratingSetup<Px, Sig>, Px, Sig);
+impl_swap_message!(RefundProcedureSignatures<Sig, EncSig>, Sig, EncSig);
+impl_swap_message!(BuyProcedureSignature<Px, EncSig>, Px, EncSig);
+impl_swap_message!(MuSigNonce<N>, N);
+impl_swap_message!(MuSigPartialSignature<Ps>, Ps);
+impl_swap_message!(Abort);
+impl_swap_message!(Hello);
+impl_swap_message!(VersionAck);
+impl_swap_message!(RequestRetransmit);
+
 /// Forces Alice to commit to the result of her cryptographic setup before receiving Bob's setup.
 /// This is done to remove adaptive behavior in the cryptographic parameters.
-#[derive(Clone, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(serialize = "C: CanonicalBytes", deserialize = "C: CanonicalBytes"))
+)]
 pub struct CommitAliceParameters<C> {
     /// The swap identifier related to this message.
     pub swap_id: SwapId,
     /// Commitment to the buy public key.
+    #[cfg_attr(feature = "serde", serde(with = "serde_hex"))]
     pub buy: C,
     /// Commitment to the cancel public key.
+    #[cfg_attr(feature = "serde", serde(with = "serde_hex"))]
     pub cancel: C,
     /// Commitment to the refund public key.
+    #[cfg_attr(feature = "serde", serde(with = "serde_hex"))]
     pub refund: C,
     /// Commitment to the punish public key.
+    #[cfg_attr(feature = "serde", serde(with = "serde_hex"))]
     pub punish: C,
     /// Commitment to the adaptor public key.
+    #[cfg_attr(feature = "serde", serde(with = "serde_hex"))]
     pub adaptor: C,
     /// Commitments to the extra arbitrating public keys.
-    pub extra_arbitrating_keys: Vec<TaggedElement<u16, C>>,
+    #[cfg_attr(feature = "serde", serde(with = "serde_hex::tagged"))]
+    pub extra_arbitrating_keys: TaggedExtraKeys<C>,
     /// Commitments to the arbitrating shared keys.
-    pub arbitrating_shared_keys: Vec<TaggedElement<SharedKeyId, C>>,
+    #[cfg_attr(feature = "serde", serde(with = "serde_hex::tagged"))]
+    pub arbitrating_shared_keys: TaggedSharedKeys<C>,
     /// Commitment to the spend public key.
+    #[cfg_attr(feature = "serde", serde(with = "serde_hex"))]
     pub spend: C,
     /// Commitments to the extra accordant public keys.
-    pub extra_accordant_keys: Vec<TaggedElement<u16, C>>,
+    #[cfg_attr(feature = "serde", serde(with = "serde_hex::tagged"))]
+    pub extra_accordant_keys: TaggedExtraKeys<C>,
     /// Commitments to the accordant shared keys.
-    pub accordant_shared_keys: Vec<TaggedElement<SharedKeyId, C>>,
+    #[cfg_attr(feature = "serde", serde(with = "serde_hex::tagged"))]
+    pub accordant_shared_keys: TaggedSharedKeys<C>,
 }
 
 impl<C> CommitAliceParameters<C>
 where
     C: Eq + Clone + CanonicalBytes,
 {
+    /// Verifies that the revealed parameters match the commitments.
     pub fn verify_with_reveal<Pk, Qk, Rk, Sk, Addr>(
         &self,
         wallet: &impl Commit<C>,
         reveal: RevealAliceParameters<Pk, Qk, Rk, Sk, Addr>,
     ) -> Result<(), Error>
+    where
+        Pk: CanonicalBytes,
+        Qk: CanonicalBytes,
+        Rk: CanonicalBytes,
+        Sk: CanonicalBytes,
+        Addr: CanonicalBytes,
+    {
+        self.verify_reveal(wallet, &reveal)
+    }
+
+    /// Verifies that the revealed parameters match the commitments and, on success, consumes the
+    /// reveal to return the corresponding [`Parameters`].
+    pub fn verify_into_parameters<Pk, Qk, Rk, Sk, Addr, Ti, F, Pr>(
+        &self,
+        wallet: &impl Commit<C>,
+        reveal: RevealAliceParameters<Pk, Qk, Rk, Sk, Addr>,
+    ) -> Result<Parameters<Pk, Qk, Rk, Sk, Addr, Ti, F, Pr>, Error>
+    where
+        Pk: CanonicalBytes,
+        Qk: CanonicalBytes,
+        Rk: CanonicalBytes,
+        Sk: CanonicalBytes,
+        Addr: CanonicalBytes,
+    {
+        self.verify_reveal(wallet, &reveal)?;
+        Ok(reveal.into_parameters())
+    }
+
+    /// Pairs the commitment with the revealed parameters: checks that both messages relate to the
+    /// same swap, failing with [`Error::SwapIdMismatch`] before verifying any commitment, then
+    /// verifies that the revealed parameters match the commitments.
+    pub fn pair_with<Pk, Qk, Rk, Sk, Addr>(
+        &self,
+        wallet: &impl Commit<C>,
+        reveal: RevealAliceParameters<Pk, Qk, Rk, Sk, Addr>,
+    ) -> Result<VerifiedReveal<RevealAliceParameters<Pk, Qk, Rk, Sk, Addr>>, Error>
+    where
+        Pk: CanonicalBytes,
+        Qk: CanonicalBytes,
+        Rk: CanonicalBytes,
+        Sk: CanonicalBytes,
+        Addr: CanonicalBytes,
+    {
+        if reveal.swap_id != self.swap_id {
+            return Err(Error::SwapIdMismatch {
+                expected: self.swap_id,
+                found: reveal.swap_id,
+            });
+        }
+        self.verify_reveal(wallet, &reveal)?;
+        Ok(VerifiedReveal(reveal))
+    }
+
+    fn verify_reveal<Pk, Qk, Rk, Sk, Addr>(
+        &self,
+        wallet: &impl Commit<C>,
+        reveal: &RevealAliceParameters<Pk, Qk, Rk, Sk, Addr>,
+    ) -> Result<(), Error>
     where
         Pk: CanonicalBytes,
         Qk: CanonicalBytes,
@@ -78,23 +192,23 @@ where
         wallet.validate(reveal.adaptor.as_canonical_bytes(), self.adaptor.clone())?;
         verify_vec_of_commitments(
             wallet,
-            reveal.extra_arbitrating_keys,
+            &reveal.extra_arbitrating_keys,
             &self.extra_arbitrating_keys,
         )?;
         verify_vec_of_commitments(
             wallet,
-            reveal.arbitrating_shared_keys,
+            &reveal.arbitrating_shared_keys,
             &self.arbitrating_shared_keys,
         )?;
         wallet.validate(reveal.spend.as_canonical_bytes(), self.spend.clone())?;
         verify_vec_of_commitments(
             wallet,
-            reveal.extra_accordant_keys,
+            &reveal.extra_accordant_keys,
             &self.extra_accordant_keys,
         )?;
         verify_vec_of_commitments(
             wallet,
-            reveal.accordant_shared_keys,
+            &reveal.accordant_shared_keys,
             &self.accordant_shared_keys,
         )
     }
@@ -153,39 +267,112 @@ impl_strict_encoding!(CommitAliceParameters<C>, C: CanonicalBytes);
 
 /// Forces Bob to commit to the result of his cryptographic setup before receiving Alice's setup.
 /// This is done to remove adaptive behavior in the cryptographic parameters.
-#[derive(Clone, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(serialize = "C: CanonicalBytes", deserialize = "C: CanonicalBytes"))
+)]
 pub struct CommitBobParameters<C> {
     /// The swap identifier related to this message.
     pub swap_id: SwapId,
     /// Commitment to the buy public key.
+    #[cfg_attr(feature = "serde", serde(with = "serde_hex"))]
     pub buy: C,
     /// Commitment to the cancel public key.
+    #[cfg_attr(feature = "serde", serde(with = "serde_hex"))]
     pub cancel: C,
     /// Commitment to the refund public key.
+    #[cfg_attr(feature = "serde", serde(with = "serde_hex"))]
     pub refund: C,
     /// Commitment to the adaptor public key.
+    #[cfg_attr(feature = "serde", serde(with = "serde_hex"))]
     pub adaptor: C,
     /// Commitments to the extra arbitrating public keys.
-    pub extra_arbitrating_keys: Vec<TaggedElement<u16, C>>,
+    #[cfg_attr(feature = "serde", serde(with = "serde_hex::tagged"))]
+    pub extra_arbitrating_keys: TaggedExtraKeys<C>,
     /// Commitments to the arbitrating shared keys.
-    pub arbitrating_shared_keys: Vec<TaggedElement<SharedKeyId, C>>,
+    #[cfg_attr(feature = "serde", serde(with = "serde_hex::tagged"))]
+    pub arbitrating_shared_keys: TaggedSharedKeys<C>,
     /// Commitment to the spend public key.
+    #[cfg_attr(feature = "serde", serde(with = "serde_hex"))]
     pub spend: C,
     /// Commitments to the extra accordant public keys.
-    pub extra_accordant_keys: Vec<TaggedElement<u16, C>>,
+    #[cfg_attr(feature = "serde", serde(with = "serde_hex::tagged"))]
+    pub extra_accordant_keys: TaggedExtraKeys<C>,
     /// Commitments to the accordant shared keys.
-    pub accordant_shared_keys: Vec<TaggedElement<SharedKeyId, C>>,
+    #[cfg_attr(feature = "serde", serde(with = "serde_hex::tagged"))]
+    pub accordant_shared_keys: TaggedSharedKeys<C>,
 }
 
 impl<C> CommitBobParameters<C>
 where
     C: Eq + Clone + CanonicalBytes,
 {
+    /// Verifies that the revealed parameters match the commitments.
     pub fn verify_with_reveal<Pk, Qk, Rk, Sk, Addr>(
         &self,
         wallet: &impl Commit<C>,
         reveal: RevealBobParameters<Pk, Qk, Rk, Sk, Addr>,
     ) -> Result<(), Error>
+    where
+        Pk: CanonicalBytes,
+        Qk: CanonicalBytes,
+        Rk: CanonicalBytes,
+        Sk: CanonicalBytes,
+        Addr: CanonicalBytes,
+    {
+        self.verify_reveal(wallet, &reveal)
+    }
+
+    /// Verifies that the revealed parameters match the commitments and, on success, consumes the
+    /// reveal to return the corresponding [`Parameters`].
+    pub fn verify_into_parameters<Pk, Qk, Rk, Sk, Addr, Ti, F, Pr>(
+        &self,
+        wallet: &impl Commit<C>,
+        reveal: RevealBobParameters<Pk, Qk, Rk, Sk, Addr>,
+    ) -> Result<Parameters<Pk, Qk, Rk, Sk, Addr, Ti, F, Pr>, Error>
+    where
+        Pk: CanonicalBytes,
+        Qk: CanonicalBytes,
+        Rk: CanonicalBytes,
+        Sk: CanonicalBytes,
+        Addr: CanonicalBytes,
+    {
+        self.verify_reveal(wallet, &reveal)?;
+        Ok(reveal.into_parameters())
+    }
+
+    /// Pairs the commitment with the revealed parameters: checks that both messages relate to the
+    /// same swap, failing with [`Error::SwapIdMismatch`] before verifying any commitment, then
+    /// verifies that the revealed parameters match the commitments.
+    pub fn pair_with<Pk, Qk, Rk, Sk, Addr>(
+        &self,
+        wallet: &impl Commit<C>,
+        reveal: RevealBobParameters<Pk, Qk, Rk, Sk, Addr>,
+    ) -> Result<VerifiedReveal<RevealBobParameters<Pk, Qk, Rk, Sk, Addr>>, Error>
+    where
+        Pk: CanonicalBytes,
+        Qk: CanonicalBytes,
+        Rk: CanonicalBytes,
+        Sk: CanonicalBytes,
+        Addr: CanonicalBytes,
+    {
+        if reveal.swap_id != self.swap_id {
+            return Err(Error::SwapIdMismatch {
+                expected: self.swap_id,
+                found: reveal.swap_id,
+            });
+        }
+        self.verify_reveal(wallet, &reveal)?;
+        Ok(VerifiedReveal(reveal))
+    }
+
+    fn verify_reveal<Pk, Qk, Rk, Sk, Addr>(
+        &self,
+        wallet: &impl Commit<C>,
+        reveal: &RevealBobParameters<Pk, Qk, Rk, Sk, Addr>,
+    ) -> Result<(), Error>
     where
         Pk: CanonicalBytes,
         Qk: CanonicalBytes,
@@ -199,23 +386,23 @@ where
         wallet.validate(reveal.adaptor.as_canonical_bytes(), self.adaptor.clone())?;
         verify_vec_of_commitments(
             wallet,
-            reveal.extra_arbitrating_keys,
+            &reveal.extra_arbitrating_keys,
             &self.extra_arbitrating_keys,
         )?;
         verify_vec_of_commitments(
             wallet,
-            reveal.arbitrating_shared_keys,
+            &reveal.arbitrating_shared_keys,
             &self.arbitrating_shared_keys,
         )?;
         wallet.validate(reveal.spend.as_canonical_bytes(), self.spend.clone())?;
         verify_vec_of_commitments(
             wallet,
-            reveal.extra_accordant_keys,
+            &reveal.extra_accordant_keys,
             &self.extra_accordant_keys,
         )?;
         verify_vec_of_commitments(
             wallet,
-            reveal.accordant_shared_keys,
+            &reveal.accordant_shared_keys,
             &self.accordant_shared_keys,
         )
     }
@@ -270,12 +457,249 @@ where
 
 impl_strict_encoding!(CommitBobParameters<C>, C: CanonicalBytes);
 
+/// Role independent commitment message, Alice commits to her punish key while Bob does not. Allows
+/// a single code path to build and handle commitments for both roles. Converts from and into
+/// [`CommitAliceParameters`] and [`CommitBobParameters`] and encodes exactly like them.
+///
+/// The encoding does not carry the role, so this message is encode-only: there is no
+/// [`Decodable`] implementation. Convert it with [`TryFrom`] into the role specific message before
+/// sending it, the receiver decodes the message of its counterparty role.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(serialize = "C: CanonicalBytes", deserialize = "C: CanonicalBytes"))
+)]
+pub struct CommitParameters<C> {
+    /// The swap identifier related to this message.
+    pub swap_id: SwapId,
+    /// Commitment to the buy public key.
+    #[cfg_attr(feature = "serde", serde(with = "serde_hex"))]
+    pub buy: C,
+    /// Commitment to the cancel public key.
+    #[cfg_attr(feature = "serde", serde(with = "serde_hex"))]
+    pub cancel: C,
+    /// Commitment to the refund public key.
+    #[cfg_attr(feature = "serde", serde(with = "serde_hex"))]
+    pub refund: C,
+    /// Commitment to the punish public key, only present for Alice.
+    #[cfg_attr(feature = "serde", serde(with = "serde_hex::option"))]
+    pub punish: Option<C>,
+    /// Commitment to the adaptor public key.
+    #[cfg_attr(feature = "serde", serde(with = "serde_hex"))]
+    pub adaptor: C,
+    /// Commitments to the extra arbitrating public keys.
+    #[cfg_attr(feature = "serde", serde(with = "serde_hex::tagged"))]
+    pub extra_arbitrating_keys: TaggedExtraKeys<C>,
+    /// Commitments to the arbitrating shared keys.
+    #[cfg_attr(feature = "serde", serde(with = "serde_hex::tagged"))]
+    pub arbitrating_shared_keys: TaggedSharedKeys<C>,
+    /// Commitment to the spend public key.
+    #[cfg_attr(feature = "serde", serde(with = "serde_hex"))]
+    pub spend: C,
+    /// Commitments to the extra accordant public keys.
+    #[cfg_attr(feature = "serde", serde(with = "serde_hex::tagged"))]
+    pub extra_accordant_keys: TaggedExtraKeys<C>,
+    /// Commitments to the accordant shared keys.
+    #[cfg_attr(feature = "serde", serde(with = "serde_hex::tagged"))]
+    pub accordant_shared_keys: TaggedSharedKeys<C>,
+}
+
+impl<C> CommitParameters<C> {
+    /// Returns the swap role committing, Alice if the punish commitment is present, Bob otherwise.
+    pub fn role(&self) -> SwapRole {
+        match self.punish {
+            Some(_) => SwapRole::Alice,
+            None => SwapRole::Bob,
+        }
+    }
+}
+
+impl<C> From<CommitAliceParameters<C>> for CommitParameters<C> {
+    fn from(commit: CommitAliceParameters<C>) -> Self {
+        Self {
+            swap_id: commit.swap_id,
+            buy: commit.buy,
+            cancel: commit.cancel,
+            refund: commit.refund,
+            punish: Some(commit.punish),
+            adaptor: commit.adaptor,
+            extra_arbitrating_keys: commit.extra_arbitrating_keys,
+            arbitrating_shared_keys: commit.arbitrating_shared_keys,
+            spend: commit.spend,
+            extra_accordant_keys: commit.extra_accordant_keys,
+            accordant_shared_keys: commit.accordant_shared_keys,
+        }
+    }
+}
+
+impl<C> From<CommitBobParameters<C>> for CommitParameters<C> {
+    fn from(commit: CommitBobParameters<C>) -> Self {
+        Self {
+            swap_id: commit.swap_id,
+            buy: commit.buy,
+            cancel: commit.cancel,
+            refund: commit.refund,
+            punish: None,
+            adaptor: commit.adaptor,
+            extra_arbitrating_keys: commit.extra_arbitrating_keys,
+            arbitrating_shared_keys: commit.arbitrating_shared_keys,
+            spend: commit.spend,
+            extra_accordant_keys: commit.extra_accordant_keys,
+            accordant_shared_keys: commit.accordant_shared_keys,
+        }
+    }
+}
+
+impl<C> TryFrom<CommitParameters<C>> for CommitAliceParameters<C> {
+    type Error = Error;
+
+    fn try_from(commit: CommitParameters<C>) -> Result<Self, Error> {
+        Ok(Self {
+            swap_id: commit.swap_id,
+            buy: commit.buy,
+            cancel: commit.cancel,
+            refund: commit.refund,
+            punish: commit.punish.ok_or(Error::MissingParameter("punish"))?,
+            adaptor: commit.adaptor,
+            extra_arbitrating_keys: commit.extra_arbitrating_keys,
+            arbitrating_shared_keys: commit.arbitrating_shared_keys,
+            spend: commit.spend,
+            extra_accordant_keys: commit.extra_accordant_keys,
+            accordant_shared_keys: commit.accordant_shared_keys,
+        })
+    }
+}
+
+impl<C> TryFrom<CommitParameters<C>> for CommitBobParameters<C> {
+    type Error = Error;
+
+    fn try_from(commit: CommitParameters<C>) -> Result<Self, Error> {
+        if commit.punish.is_some() {
+            return Err(Error::UnexpectedParameter("punish"));
+        }
+        Ok(Self {
+            swap_id: commit.swap_id,
+            buy: commit.buy,
+            cancel: commit.cancel,
+            refund: commit.refund,
+            adaptor: commit.adaptor,
+            extra_arbitrating_keys: commit.extra_arbitrating_keys,
+            arbitrating_shared_keys: commit.arbitrating_shared_keys,
+            spend: commit.spend,
+            extra_accordant_keys: commit.extra_accordant_keys,
+            accordant_shared_keys: commit.accordant_shared_keys,
+        })
+    }
+}
+
+impl<C> fmt::Display for CommitParameters<C>
+where
+    C: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl<C> Encodable for CommitParameters<C>
+where
+    C: CanonicalBytes,
+{
+    /// Encodes the commitments as [`CommitAliceParameters`] when the punish commitment is present
+    /// and as [`CommitBobParameters`] otherwise.
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        let mut len = self.swap_id.consensus_encode(s)?;
+        len += self.buy.as_canonical_bytes().consensus_encode(s)?;
+        len += self.cancel.as_canonical_bytes().consensus_encode(s)?;
+        len += self.refund.as_canonical_bytes().consensus_encode(s)?;
+        if let Some(punish) = &self.punish {
+            len += punish.as_canonical_bytes().consensus_encode(s)?;
+        }
+        len += self.adaptor.as_canonical_bytes().consensus_encode(s)?;
+        len += self.extra_arbitrating_keys.consensus_encode(s)?;
+        len += self.arbitrating_shared_keys.consensus_encode(s)?;
+        len += self.spend.as_canonical_bytes().consensus_encode(s)?;
+        len += self.extra_accordant_keys.consensus_encode(s)?;
+        Ok(len + self.accordant_shared_keys.consensus_encode(s)?)
+    }
+}
+
+/// Revealed parameters verified against their commitment for the same swap, obtained with
+/// [`CommitAliceParameters::pair_with`] or [`CommitBobParameters::pair_with`].
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct VerifiedReveal<R>(R);
+
+impl<R> VerifiedReveal<R> {
+    /// Returns the verified reveal message.
+    pub fn reveal(&self) -> &R {
+        &self.0
+    }
+
+    /// Consumes the wrapper, returning the verified reveal message.
+    pub fn into_inner(self) -> R {
+        self.0
+    }
+}
+
+impl<Pk, Qk, Rk, Sk, Addr> VerifiedReveal<RevealAliceParameters<Pk, Qk, Rk, Sk, Addr>> {
+    /// Consumes the verified reveal to return the corresponding [`Parameters`].
+    pub fn into_parameters<Ti, F, Pr>(self) -> Parameters<Pk, Qk, Rk, Sk, Addr, Ti, F, Pr> {
+        self.0.into_parameters()
+    }
+}
+
+impl<Pk, Qk, Rk, Sk, Addr> VerifiedReveal<RevealBobParameters<Pk, Qk, Rk, Sk, Addr>> {
+    /// Consumes the verified reveal to return the corresponding [`Parameters`].
+    pub fn into_parameters<Ti, F, Pr>(self) -> Parameters<Pk, Qk, Rk, Sk, Addr, Ti, F, Pr> {
+        self.0.into_parameters()
+    }
+}
+
+/// Label a failed signature verification with the transaction it was made for, other crypto
+/// errors are returned as is.
+fn signature_error(label: TxLabel) -> impl FnOnce(crypto::Error) -> Error {
+    move |e| match e {
+        crypto::Error::InvalidSignature | crypto::Error::InvalidEncryptedSignature => {
+            transaction::Error::InvalidSignature(label).into()
+        }
+        e => e.into(),
+    }
+}
+
+fn verify_reveal_proof<Pk, Qk, Pr>(
+    wallet: &mut impl ProveCrossGroupDleq<Pk, Qk, Pr>,
+    swap_id: SwapId,
+    adaptor: &Pk,
+    spend: &Qk,
+    proof: &RevealProof<Pr>,
+) -> Result<(), Error>
+where
+    Pr: Clone,
+{
+    if proof.swap_id != swap_id {
+        return Err(Error::SwapIdMismatch {
+            expected: swap_id,
+            found: proof.swap_id,
+        });
+    }
+    wallet
+        .verify_proof(spend, adaptor, proof.proof.clone())
+        .map_err(Into::into)
+}
+
 /// Reveals the zero-knowledge proof for the discrete logarithm across curves.
-#[derive(Clone, Copy, Hash, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Clone, Copy, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(serialize = "Pr: CanonicalBytes", deserialize = "Pr: CanonicalBytes"))
+)]
 pub struct RevealProof<Pr> {
     /// The swap identifier related to this message.
     pub swap_id: SwapId,
     /// Reveal the cross-group discrete logarithm zero-knowledge proof.
+    #[cfg_attr(feature = "serde", serde(with = "serde_hex"))]
     pub proof: Pr,
 }
 
@@ -328,30 +752,48 @@ impl_strict_encoding!(RevealProof<Pr>, Pr: CanonicalBytes);
 /// - `Rk` the arbitrating Shared Secret Key type
 /// - `Qk` the accordant Public Key type
 /// - `Sk` the accordant Shared Secret Key type
-#[derive(Clone, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "Pk: CanonicalBytes, Qk: CanonicalBytes, Rk: CanonicalBytes, Sk: CanonicalBytes, Addr: serde::Serialize",
+        deserialize = "Pk: CanonicalBytes, Qk: CanonicalBytes, Rk: CanonicalBytes, Sk: CanonicalBytes, Addr: serde::Deserialize<'de>"
+    ))
+)]
 pub struct RevealAliceParameters<Pk, Qk, Rk, Sk, Addr> {
     /// The swap identifier related to this message.
     pub swap_id: SwapId,
     /// Reveal the buy public key.
+    #[cfg_attr(feature = "serde", serde(with = "serde_hex"))]
     pub buy: Pk,
     /// Reveal the cancel public key.
+    #[cfg_attr(feature = "serde", serde(with = "serde_hex"))]
     pub cancel: Pk,
     /// Reveal the refund public key.
+    #[cfg_attr(feature = "serde", serde(with = "serde_hex"))]
     pub refund: Pk,
     /// Reveal the punish public key.
+    #[cfg_attr(feature = "serde", serde(with = "serde_hex"))]
     pub punish: Pk,
     /// Reveal the adaptor public key.
+    #[cfg_attr(feature = "serde", serde(with = "serde_hex"))]
     pub adaptor: Pk,
     /// Reveal the vector of extra arbitrating public keys.
-    pub extra_arbitrating_keys: Vec<TaggedElement<u16, Pk>>,
+    #[cfg_attr(feature = "serde", serde(with = "serde_hex::tagged"))]
+    pub extra_arbitrating_keys: TaggedExtraKeys<Pk>,
     /// Reveal the vector of extra arbitrating shared keys.
-    pub arbitrating_shared_keys: Vec<TaggedElement<SharedKeyId, Rk>>,
+    #[cfg_attr(feature = "serde", serde(with = "serde_hex::tagged"))]
+    pub arbitrating_shared_keys: TaggedSharedKeys<Rk>,
     /// Reveal the spend public key.
+    #[cfg_attr(feature = "serde", serde(with = "serde_hex"))]
     pub spend: Qk,
     /// Reveal the vector of extra accordant public keys.
-    pub extra_accordant_keys: Vec<TaggedElement<u16, Qk>>,
+    #[cfg_attr(feature = "serde", serde(with = "serde_hex::tagged"))]
+    pub extra_accordant_keys: TaggedExtraKeys<Qk>,
     /// Reveal the vector of extra accordant shared keys.
-    pub accordant_shared_keys: Vec<TaggedElement<SharedKeyId, Sk>>,
+    #[cfg_attr(feature = "serde", serde(with = "serde_hex::tagged"))]
+    pub accordant_shared_keys: TaggedSharedKeys<Sk>,
     /// Reveal the destination address.
     pub address: Addr,
 }
@@ -378,6 +820,22 @@ impl<Pk, Qk, Rk, Sk, Addr> RevealAliceParameters<Pk, Qk, Rk, Sk, Addr> {
     }
 }
 
+impl<Pk, Qk, Rk, Sk, Addr> RevealAliceParameters<Pk, Qk, Rk, Sk, Addr> {
+    /// Verify that the revealed proof relates to the same swap and proves that the revealed
+    /// adaptor and spend public keys share the same discrete logarithm across curves. Fails with
+    /// [`Error::SwapIdMismatch`] or with the cryptographic error of the proof verification.
+    pub fn verify_proof<Pr>(
+        &self,
+        wallet: &mut impl ProveCrossGroupDleq<Pk, Qk, Pr>,
+        proof: &RevealProof<Pr>,
+    ) -> Result<(), Error>
+    where
+        Pr: Clone,
+    {
+        verify_reveal_proof(wallet, self.swap_id, &self.adaptor, &self.spend, proof)
+    }
+}
+
 impl<Pk, Qk, Rk, Sk, Addr> fmt::Display for RevealAliceParameters<Pk, Qk, Rk, Sk, Addr>
 where
     Pk: fmt::Debug,
@@ -451,28 +909,45 @@ impl_strict_encoding!(RevealAliceParameters<Pk, Qk, Rk, Sk, Addr>, Pk: Canonical
 /// - `Rk` the arbitrating Shared Secret Key type
 /// - `Qk` the accordant Public Key type
 /// - `Sk` the accordant Shared Secret Key type
-#[derive(Clone, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "Pk: CanonicalBytes, Qk: CanonicalBytes, Rk: CanonicalBytes, Sk: CanonicalBytes, Addr: serde::Serialize",
+        deserialize = "Pk: CanonicalBytes, Qk: CanonicalBytes, Rk: CanonicalBytes, Sk: CanonicalBytes, Addr: serde::Deserialize<'de>"
+    ))
+)]
 pub struct RevealBobParameters<Pk, Qk, Rk, Sk, Addr> {
     /// The swap identifier related to this message.
     pub swap_id: SwapId,
     /// Reveal the buy public key.
+    #[cfg_attr(feature = "serde", serde(with = "serde_hex"))]
     pub buy: Pk,
     /// Reveal the cancel public key.
+    #[cfg_attr(feature = "serde", serde(with = "serde_hex"))]
     pub cancel: Pk,
     /// Reveal the refund public key.
+    #[cfg_attr(feature = "serde", serde(with = "serde_hex"))]
     pub refund: Pk,
     /// Reveal the adaptor public key.
+    #[cfg_attr(feature = "serde", serde(with = "serde_hex"))]
     pub adaptor: Pk,
     /// Reveal the vector of extra arbitrating public keys.
-    pub extra_arbitrating_keys: Vec<TaggedElement<u16, Pk>>,
+    #[cfg_attr(feature = "serde", serde(with = "serde_hex::tagged"))]
+    pub extra_arbitrating_keys: TaggedExtraKeys<Pk>,
     /// Reveal the vector of extra arbitrating shared keys.
-    pub arbitrating_shared_keys: Vec<TaggedElement<SharedKeyId, Rk>>,
+    #[cfg_attr(feature = "serde", serde(with = "serde_hex::tagged"))]
+    pub arbitrating_shared_keys: TaggedSharedKeys<Rk>,
     /// Reveal the spend public key.
+    #[cfg_attr(feature = "serde", serde(with = "serde_hex"))]
     pub spend: Qk,
     /// Reveal the vector of extra accordant public keys.
-    pub extra_accordant_keys: Vec<TaggedElement<u16, Qk>>,
+    #[cfg_attr(feature = "serde", serde(with = "serde_hex::tagged"))]
+    pub extra_accordant_keys: TaggedExtraKeys<Qk>,
     /// Reveal the vector of extra accordant shared keys.
-    pub accordant_shared_keys: Vec<TaggedElement<SharedKeyId, Sk>>,
+    #[cfg_attr(feature = "serde", serde(with = "serde_hex::tagged"))]
+    pub accordant_shared_keys: TaggedSharedKeys<Sk>,
     /// The refund Bitcoin address.
     pub address: Addr,
 }
@@ -499,6 +974,22 @@ impl<Pk, Qk, Rk, Sk, Addr> RevealBobParameters<Pk, Qk, Rk, Sk, Addr> {
     }
 }
 
+impl<Pk, Qk, Rk, Sk, Addr> RevealBobParameters<Pk, Qk, Rk, Sk, Addr> {
+    /// Verify that the revealed proof relates to the same swap and proves that the revealed
+    /// adaptor and spend public keys share the same discrete logarithm across curves. Fails with
+    /// [`Error::SwapIdMismatch`] or with the cryptographic error of the proof verification.
+    pub fn verify_proof<Pr>(
+        &self,
+        wallet: &mut impl ProveCrossGroupDleq<Pk, Qk, Pr>,
+        proof: &RevealProof<Pr>,
+    ) -> Result<(), Error>
+    where
+        Pr: Clone,
+    {
+        verify_reveal_proof(wallet, self.swap_id, &self.adaptor, &self.spend, proof)
+    }
+}
+
 impl<Pk, Qk, Rk, Sk, Addr> fmt::Display for RevealBobParameters<Pk, Qk, Rk, Sk, Addr>
 where
     Pk: fmt::Debug,
@@ -571,21 +1062,81 @@ impl_strict_encoding!(RevealBobParameters<Pk, Qk, Rk, Sk, Addr>, Pk: CanonicalBy
 /// [`Lockable`]: crate::transaction::Lockable
 /// [`Cancelable`]: crate::transaction::Cancelable
 /// [`Refundable`]: crate::transaction::Refundable
-#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "Px: CanonicalBytes, Sig: CanonicalBytes",
+        deserialize = "Px: CanonicalBytes, Sig: CanonicalBytes"
+    ))
+)]
 pub struct CoreArbitratingSetup<Px, Sig> {
     /// The swap identifier related to this message.
     pub swap_id: SwapId,
     /// The arbitrating `lock (b)` transaction.
+    #[cfg_attr(feature = "serde", serde(with = "serde_hex"))]
     pub lock: Px,
     /// The arbitrating `cancel (d)` transaction.
+    #[cfg_attr(feature = "serde", serde(with = "serde_hex"))]
     pub cancel: Px,
     /// The arbitrating `refund (e)` transaction.
+    #[cfg_attr(feature = "serde", serde(with = "serde_hex"))]
     pub refund: Px,
     /// The `Bc` `cancel (d)` signature.
+    #[cfg_attr(feature = "serde", serde(with = "serde_hex"))]
     pub cancel_sig: Sig,
 }
 
 impl<Px, Sig> CoreArbitratingSetup<Px, Sig> {
+    /// Create the arbitrating setup from the `lock`, `cancel`, and `refund` transactions and
+    /// Bob's `cancel` signature. Verifies that `cancel` builds on top of `lock` and `refund` on top
+    /// of `cancel`, fails with [`transaction::Error::InvalidTransactionChain`] otherwise.
+    pub fn from_transactions<Out, Amt, L, C, R>(
+        swap_id: SwapId,
+        lock: L,
+        cancel: C,
+        refund: R,
+        cancel_sig: Sig,
+    ) -> Result<Self, transaction::Error>
+    where
+        Out: Eq,
+        L: Transaction<Px, Out, Amt> + Linkable<Out>,
+        C: Transaction<Px, Out, Amt> + Linkable<Out>,
+        R: Transaction<Px, Out, Amt>,
+    {
+        cancel.is_build_on_top_of(&lock)?;
+        refund.is_build_on_top_of(&cancel)?;
+        Ok(Self {
+            swap_id,
+            lock: lock.to_partial(),
+            cancel: cancel.to_partial(),
+            refund: refund.to_partial(),
+            cancel_sig,
+        })
+    }
+
+    /// Verify that `cancel_sig` is a valid signature of the `cancel (d)` transaction for the
+    /// given public key. The witness message is regenerated from the partial cancel transaction
+    /// on the failure path, the same message signed by Bob and validated by Alice. Fails with
+    /// [`transaction::Error::InvalidSignature`] labeled [`TxLabel::Cancel`].
+    pub fn verify_cancel_sig<C, S, Out, Amt, Ms, Pk>(
+        &self,
+        wallet: &S,
+        key: &Pk,
+    ) -> Result<(), Error>
+    where
+        Px: Clone,
+        C: Transaction<Px, Out, Amt> + Witnessable<Ms, Pk, Sig>,
+        S: Sign<Pk, Ms, Sig>,
+    {
+        let cancel = C::from_partial(self.cancel.clone());
+        let msg = cancel.generate_witness_message(ScriptPath::Failure)?;
+        wallet
+            .verify_signature(key, msg, &self.cancel_sig)
+            .map_err(signature_error(TxLabel::Cancel))
+    }
+
     /// Transform the arbitrating setup into a core arbitrating transaction structure used in
     /// protocol methods on Alice and Bob.
     pub fn into_arbitrating_tx(self) -> CoreArbitratingTransactions<Px> {
@@ -640,23 +1191,55 @@ where
 impl_strict_encoding!(CoreArbitratingSetup<Px, Sig>, Px: CanonicalBytes, Sig: CanonicalBytes);
 
 /// Protocol message is intended to transmit [`SwapRole::Alice`]'s signature for the [`Cancelable`]
-/// transaction and Alice's adaptor signature for the [`Refundable`] transaction. Uppon reception
+/// transaction and Alice's adaptor signature for the [`Refundable`] transaction. Upon reception
 /// [`SwapRole::Bob`] must validate the signatures.
 ///
 /// [`SwapRole::Alice`]: crate::role::SwapRole::Alice
 /// [`SwapRole::Bob`]: crate::role::SwapRole::Bob
 /// [`Cancelable`]: crate::transaction::Cancelable
 /// [`Refundable`]: crate::transaction::Refundable
-#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "Sig: CanonicalBytes, EncSig: CanonicalBytes",
+        deserialize = "Sig: CanonicalBytes, EncSig: CanonicalBytes"
+    ))
+)]
 pub struct RefundProcedureSignatures<Sig, EncSig> {
     /// The swap identifier related to this message.
     pub swap_id: SwapId,
     /// The `Ac` `cancel (d)` signature.
+    #[cfg_attr(feature = "serde", serde(with = "serde_hex"))]
     pub cancel_sig: Sig,
     /// The `Ar(Tb)` `refund (e)` adaptor signature.
+    #[cfg_attr(feature = "serde", serde(with = "serde_hex"))]
     pub refund_adaptor_sig: EncSig,
 }
 
+impl<Sig, EncSig> RefundProcedureSignatures<Sig, EncSig> {
+    /// Verify that `refund_adaptor_sig` is a valid adaptor signature of the given `refund (e)`
+    /// partial transaction for the signing key, encrypted with the encryption key. Fails with
+    /// [`transaction::Error::InvalidSignature`] labeled [`TxLabel::Refund`].
+    pub fn verify_refund_adaptor_sig<R, Ar, Px, Out, Amt>(
+        &self,
+        refund: &Px,
+        signing_key: &Ar::PublicKey,
+        encryption_key: &Ar::PublicKey,
+    ) -> Result<(), Error>
+    where
+        Px: Clone,
+        Ar: AdaptorSignature<EncryptedSignature = EncSig>,
+        R: Transaction<Px, Out, Amt> + Witnessable<Ar::Message, Ar::PublicKey, Ar::Signature>,
+    {
+        let refund = R::from_partial(refund.clone());
+        let msg = refund.generate_witness_message(ScriptPath::Success)?;
+        Ar::verify_adaptor(signing_key, encryption_key, msg, &self.refund_adaptor_sig)
+            .map_err(signature_error(TxLabel::Refund))
+    }
+}
+
 impl<Sig, EncSig> fmt::Display for RefundProcedureSignatures<Sig, EncSig>
 where
     Sig: fmt::Debug,
@@ -700,21 +1283,75 @@ where
 impl_strict_encoding!(RefundProcedureSignatures<Sig, EncSig>, Sig: CanonicalBytes, EncSig: CanonicalBytes);
 
 /// Protocol message intended to transmit [`SwapRole::Bob`]'s adaptor signature for the [`Buyable`]
-/// transaction and the transaction itself. Uppon reception Alice must validate the transaction and
+/// transaction and the transaction itself. Upon reception Alice must validate the transaction and
 /// the adaptor signature.
 ///
 /// [`SwapRole::Bob`]: crate::role::SwapRole::Bob
 /// [`Buyable`]: crate::transaction::Buyable
-#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "Px: CanonicalBytes, EncSig: CanonicalBytes",
+        deserialize = "Px: CanonicalBytes, EncSig: CanonicalBytes"
+    ))
+)]
 pub struct BuyProcedureSignature<Px, EncSig> {
     /// The swap identifier related to this message.
     pub swap_id: SwapId,
     /// The arbitrating `buy (c)` transaction.
+    #[cfg_attr(feature = "serde", serde(with = "serde_hex"))]
     pub buy: Px,
     /// The `Bb(Ta)` `buy (c)` adaptor signature.
+    #[cfg_attr(feature = "serde", serde(with = "serde_hex"))]
     pub buy_adaptor_sig: EncSig,
 }
 
+impl<Px, EncSig> BuyProcedureSignature<Px, EncSig> {
+    /// Verify that `buy_adaptor_sig` is a valid adaptor signature of the `buy (c)` transaction
+    /// for the signing key, encrypted with the encryption key. Fails with
+    /// [`transaction::Error::InvalidSignature`] labeled [`TxLabel::Buy`].
+    pub fn verify_buy_sig<B, Ar, Out, Amt>(
+        &self,
+        signing_key: &Ar::PublicKey,
+        encryption_key: &Ar::PublicKey,
+    ) -> Result<(), Error>
+    where
+        Px: Clone,
+        Ar: AdaptorSignature<EncryptedSignature = EncSig>,
+        B: Transaction<Px, Out, Amt> + Witnessable<Ar::Message, Ar::PublicKey, Ar::Signature>,
+    {
+        let buy = B::from_partial(self.buy.clone());
+        let msg = buy.generate_witness_message(ScriptPath::Success)?;
+        Ar::verify_adaptor(signing_key, encryption_key, msg, &self.buy_adaptor_sig)
+            .map_err(signature_error(TxLabel::Buy))
+    }
+
+    /// Recover the secret used to encrypt `buy_adaptor_sig` from the finalized `buy (c)`
+    /// transaction seen on-chain. Fails with [`transaction::Error::MissingWitness`] if the
+    /// transaction is not the expected `buy (c)`.
+    pub fn recover_secret<B, Ar, Addr, Tx, Out, Amt, Ti>(
+        &self,
+        encryption_key: &Ar::PublicKey,
+        onchain_tx: Tx,
+    ) -> Result<Ar::PrivateKey, Error>
+    where
+        Px: Clone,
+        Out: Eq,
+        Ar: AdaptorSignature<EncryptedSignature = EncSig>,
+        B: Buyable<Addr, Tx, Px, Out, Amt, Ti, Ar::Message, Ar::PublicKey, Ar::Signature>,
+    {
+        let buy = B::from_partial(self.buy.clone());
+        let sig = buy.extract_onchain_witness(onchain_tx)?;
+        Ok(Ar::recover_secret(
+            encryption_key,
+            &sig,
+            &self.buy_adaptor_sig,
+        )?)
+    }
+}
+
 impl<Px, EncSig> fmt::Display for BuyProcedureSignature<Px, EncSig>
 where
     Px: fmt::Debug,
@@ -757,11 +1394,119 @@ where
 
 impl_strict_encoding!(BuyProcedureSignature<Px, EncSig>, Px: consensus::CanonicalBytes, EncSig: consensus::CanonicalBytes);
 
+/// Protocol message intended to transmit a participant's public nonce for a MuSig2 signing session
+/// on a cooperative transaction, first round of the session.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(serialize = "N: CanonicalBytes", deserialize = "N: CanonicalBytes"))
+)]
+pub struct MuSigNonce<N> {
+    /// The swap identifier related to this message.
+    pub swap_id: SwapId,
+    /// The transaction the signing session is for.
+    pub label: TxLabel,
+    /// The public nonce of the sender.
+    #[cfg_attr(feature = "serde", serde(with = "serde_hex"))]
+    pub nonce: N,
+}
+
+impl<N> fmt::Display for MuSigNonce<N>
+where
+    N: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl<N> Encodable for MuSigNonce<N>
+where
+    N: CanonicalBytes,
+{
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        let mut len = self.swap_id.consensus_encode(s)?;
+        len += self.label.consensus_encode(s)?;
+        Ok(len + self.nonce.as_canonical_bytes().consensus_encode(s)?)
+    }
+}
+
+impl<N> Decodable for MuSigNonce<N>
+where
+    N: CanonicalBytes,
+{
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        Ok(Self {
+            swap_id: Decodable::consensus_decode(d)?,
+            label: Decodable::consensus_decode(d)?,
+            nonce: N::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?,
+        })
+    }
+}
+
+impl_strict_encoding!(MuSigNonce<N>, N: CanonicalBytes);
+
+/// Protocol message intended to transmit a participant's MuSig2 partial signature on a
+/// cooperative transaction, second round of the session. Upon reception the partial signature
+/// must be validated before being aggregated.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(serialize = "Ps: CanonicalBytes", deserialize = "Ps: CanonicalBytes"))
+)]
+pub struct MuSigPartialSignature<Ps> {
+    /// The swap identifier related to this message.
+    pub swap_id: SwapId,
+    /// The transaction the partial signature is for.
+    pub label: TxLabel,
+    /// The partial signature of the sender.
+    #[cfg_attr(feature = "serde", serde(with = "serde_hex"))]
+    pub partial_sig: Ps,
+}
+
+impl<Ps> fmt::Display for MuSigPartialSignature<Ps>
+where
+    Ps: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl<Ps> Encodable for MuSigPartialSignature<Ps>
+where
+    Ps: CanonicalBytes,
+{
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        let mut len = self.swap_id.consensus_encode(s)?;
+        len += self.label.consensus_encode(s)?;
+        Ok(len + self.partial_sig.as_canonical_bytes().consensus_encode(s)?)
+    }
+}
+
+impl<Ps> Decodable for MuSigPartialSignature<Ps>
+where
+    Ps: CanonicalBytes,
+{
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        Ok(Self {
+            swap_id: Decodable::consensus_decode(d)?,
+            label: Decodable::consensus_decode(d)?,
+            partial_sig: Ps::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?,
+        })
+    }
+}
+
+impl_strict_encoding!(MuSigPartialSignature<Ps>, Ps: CanonicalBytes);
+
 /// Optional courtesy message from either [`SwapRole`] to inform the counterparty
 /// that they have aborted the swap with an `OPTIONAL` message body to provide the reason.
 ///
 /// [`SwapRole`]: crate::role::SwapRole
-#[derive(Clone, Debug, Hash, Display, Serialize, Deserialize)]
+#[derive(Clone, Debug, Hash, Display)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[display(Debug)]
 pub struct Abort {
     /// The swap identifier related to this message.
@@ -770,6 +1515,16 @@ pub struct Abort {
     pub error_body: Option<String>,
 }
 
+impl Abort {
+    /// Create an abort message with the given reason as error body.
+    pub fn with_reason(swap_id: SwapId, reason: AbortReason) -> Self {
+        Self {
+            swap_id,
+            error_body: Some(reason.to_string()),
+        }
+    }
+}
+
 impl Encodable for Abort {
     fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
         let len = self.swap_id.consensus_encode(s)?;
@@ -787,3 +1542,358 @@ impl Decodable for Abort {
 }
 
 impl_strict_encoding!(Abort);
+
+/// Typed reasons to abort a swap, transmitted as the error body of an [`Abort`] message.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, Display)]
+pub enum AbortReason {
+    /// The protocol versions of the peers are not compatible.
+    #[display("Incompatible protocol version: local {local}, remote {remote}")]
+    IncompatibleVersion {
+        /// The local protocol version.
+        local: ProtocolVersion,
+        /// The protocol version announced by the peer.
+        remote: ProtocolVersion,
+    },
+}
+
+/// Version of the swap protocol messages, two peers can only run a swap if their versions are
+/// [compatible](ProtocolVersion::is_compatible).
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Display)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[display("v{major}.{minor}")]
+pub struct ProtocolVersion {
+    /// Major version, incremented on breaking changes of the messages.
+    pub major: u16,
+    /// Minor version, incremented on backward compatible changes.
+    pub minor: u16,
+}
+
+impl ProtocolVersion {
+    /// The protocol version implemented by this library.
+    pub const CURRENT: Self = Self::new(1, 0);
+
+    /// Create a new protocol version.
+    pub const fn new(major: u16, minor: u16) -> Self {
+        Self { major, minor }
+    }
+
+    /// Two versions are compatible if they share the same major version.
+    pub fn is_compatible(&self, other: &Self) -> bool {
+        self.major == other.major
+    }
+}
+
+impl Encodable for ProtocolVersion {
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        let len = self.major.consensus_encode(s)?;
+        Ok(len + self.minor.consensus_encode(s)?)
+    }
+}
+
+impl Decodable for ProtocolVersion {
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        Ok(Self {
+            major: Decodable::consensus_decode(d)?,
+            minor: Decodable::consensus_decode(d)?,
+        })
+    }
+}
+
+impl_strict_encoding!(ProtocolVersion);
+
+/// First message sent by each peer before any other swap message, announces the
+/// [`ProtocolVersion`] the sender runs.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, Display)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[display(Debug)]
+pub struct Hello {
+    /// The swap identifier related to this message.
+    pub swap_id: SwapId,
+    /// The protocol version of the sender.
+    pub version: ProtocolVersion,
+}
+
+impl Hello {
+    /// Create a hello message announcing [`ProtocolVersion::CURRENT`].
+    pub fn new(swap_id: SwapId) -> Self {
+        Self {
+            swap_id,
+            version: ProtocolVersion::CURRENT,
+        }
+    }
+
+    /// Answer the hello message given the `local` protocol version: acknowledge the version if
+    /// compatible, otherwise return an [`Abort`] message with
+    /// [`AbortReason::IncompatibleVersion`] to send to the peer.
+    pub fn respond(&self, local: ProtocolVersion) -> Result<VersionAck, Abort> {
+        match local.is_compatible(&self.version) {
+            true => Ok(VersionAck {
+                swap_id: self.swap_id,
+                version: local,
+            }),
+            false => Err(Abort::with_reason(
+                self.swap_id,
+                AbortReason::IncompatibleVersion {
+                    local,
+                    remote: self.version,
+                },
+            )),
+        }
+    }
+}
+
+impl Encodable for Hello {
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        let len = self.swap_id.consensus_encode(s)?;
+        Ok(len + self.version.consensus_encode(s)?)
+    }
+}
+
+impl Decodable for Hello {
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        Ok(Self {
+            swap_id: Decodable::consensus_decode(d)?,
+            version: Decodable::consensus_decode(d)?,
+        })
+    }
+}
+
+impl_strict_encoding!(Hello);
+
+/// Acknowledges a compatible [`Hello`] message with the [`ProtocolVersion`] of the responder.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, Display)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[display(Debug)]
+pub struct VersionAck {
+    /// The swap identifier related to this message.
+    pub swap_id: SwapId,
+    /// The protocol version of the sender.
+    pub version: ProtocolVersion,
+}
+
+impl Encodable for VersionAck {
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        let len = self.swap_id.consensus_encode(s)?;
+        Ok(len + self.version.consensus_encode(s)?)
+    }
+}
+
+impl Decodable for VersionAck {
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        Ok(Self {
+            swap_id: Decodable::consensus_decode(d)?,
+            version: Decodable::consensus_decode(d)?,
+        })
+    }
+}
+
+impl_strict_encoding!(VersionAck);
+
+/// Requests the counterparty to send again the messages it sent after the last one received,
+/// used to recover from messages dropped by the transport. Messages sent during a swap are
+/// numbered by a per-swap sequence starting at `0`, see [`MessageLog`].
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, Display)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[display(Debug)]
+pub struct RequestRetransmit {
+    /// The swap identifier related to this message.
+    pub swap_id: SwapId,
+    /// The sequence number of the last message received from the counterparty, `None` if no
+    /// message has been received yet.
+    pub last_seen: Option<u32>,
+}
+
+impl Encodable for RequestRetransmit {
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        let len = self.swap_id.consensus_encode(s)?;
+        Ok(len + self.last_seen.consensus_encode(s)?)
+    }
+}
+
+impl Decodable for RequestRetransmit {
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        Ok(Self {
+            swap_id: Decodable::consensus_decode(d)?,
+            last_seen: Decodable::consensus_decode(d)?,
+        })
+    }
+}
+
+impl_strict_encoding!(RequestRetransmit);
+
+/// Log of the messages sent to the counterparty during a swap, in sending order. The position of
+/// a message in the log is its sequence number, used to answer [`RequestRetransmit`] messages.
+///
+/// `M` is the type dispatching all the protocol messages, e.g. an enum over the messages of this
+/// module.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MessageLog<M> {
+    swap_id: SwapId,
+    sent: Vec<M>,
+}
+
+impl<M> MessageLog<M>
+where
+    M: SwapMessage,
+{
+    /// Create an empty log for the swap.
+    pub fn new(swap_id: SwapId) -> Self {
+        Self {
+            swap_id,
+            sent: vec![],
+        }
+    }
+
+    /// Record a message sent to the counterparty and return its sequence number. Fails with
+    /// [`Error::SwapIdMismatch`] if the message relates to another swap.
+    pub fn record(&mut self, msg: M) -> Result<u32, Error> {
+        if msg.swap_id() != self.swap_id {
+            return Err(Error::SwapIdMismatch {
+                expected: self.swap_id,
+                found: msg.swap_id(),
+            });
+        }
+        self.sent.push(msg);
+        Ok(self.sent.len() as u32 - 1)
+    }
+
+    /// Returns the sequence number of the last recorded message, `None` if the log is empty.
+    pub fn last_sequence(&self) -> Option<u32> {
+        self.sent.len().checked_sub(1).map(|seq| seq as u32)
+    }
+
+    /// Answer a retransmission request with the messages sent after the last one seen by the
+    /// counterparty, in sending order. A request for messages not produced yet is ignored and
+    /// answered with no message, they will be sent when produced. Fails with
+    /// [`Error::SwapIdMismatch`] if the request relates to another swap.
+    pub fn respond(&self, request: &RequestRetransmit) -> Result<&[M], Error> {
+        if request.swap_id != self.swap_id {
+            return Err(Error::SwapIdMismatch {
+                expected: self.swap_id,
+                found: request.swap_id,
+            });
+        }
+        let next = request.last_seen.map_or(0, |seq| seq as usize + 1);
+        Ok(self.sent.get(next..).unwrap_or(&[]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(feature = "strict-encoding")]
+    fn compatible_versions() {
+        let swap_id = SwapId::random();
+        let hello = Hello {
+            swap_id,
+            version: ProtocolVersion::new(1, 3),
+        };
+        let ack = hello.respond(ProtocolVersion::new(1, 0)).unwrap();
+        assert_eq!(ack.swap_id, swap_id);
+        assert_eq!(ack.version, ProtocolVersion::new(1, 0));
+        assert!(Hello::new(swap_id)
+            .respond(ProtocolVersion::CURRENT)
+            .is_ok());
+
+        let bytes = strict_encoding::strict_serialize(&hello).unwrap();
+        let res: Hello = strict_encoding::strict_deserialize(&bytes).unwrap();
+        assert_eq!(res, hello);
+        let bytes = strict_encoding::strict_serialize(&ack).unwrap();
+        let res: VersionAck = strict_encoding::strict_deserialize(&bytes).unwrap();
+        assert_eq!(res, ack);
+    }
+
+    #[cfg(feature = "strict-encoding")]
+    #[derive(Clone, Debug, PartialEq)]
+    enum Msg {
+        Hello(Hello),
+        VersionAck(VersionAck),
+    }
+
+    #[cfg(feature = "strict-encoding")]
+    impl SwapMessage for Msg {
+        fn swap_id(&self) -> SwapId {
+            match self {
+                Msg::Hello(m) => m.swap_id,
+                Msg::VersionAck(m) => m.swap_id,
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "strict-encoding")]
+    fn retransmit_dropped_messages() {
+        let swap_id = SwapId::random();
+        let mut log = MessageLog::new(swap_id);
+        assert_eq!(log.last_sequence(), None);
+        let hello = Msg::Hello(Hello::new(swap_id));
+        let ack = Msg::VersionAck(VersionAck {
+            swap_id,
+            version: ProtocolVersion::CURRENT,
+        });
+        assert_eq!(log.record(hello.clone()).unwrap(), 0);
+        // This message is dropped by the transport
+        assert_eq!(log.record(ack.clone()).unwrap(), 1);
+        assert_eq!(log.last_sequence(), Some(1));
+        assert!(matches!(
+            log.record(Msg::Hello(Hello::new(SwapId::random()))),
+            Err(Error::SwapIdMismatch { .. })
+        ));
+
+        // The counterparty only received the first message and requests the rest
+        let request = RequestRetransmit {
+            swap_id,
+            last_seen: Some(0),
+        };
+        let bytes = strict_encoding::strict_serialize(&request).unwrap();
+        let request: RequestRetransmit = strict_encoding::strict_deserialize(&bytes).unwrap();
+        assert_eq!(log.respond(&request).unwrap(), std::slice::from_ref(&ack));
+
+        let request = RequestRetransmit {
+            swap_id,
+            last_seen: None,
+        };
+        assert_eq!(log.respond(&request).unwrap(), &[hello, ack]);
+
+        // Messages not produced yet are ignored
+        for last_seen in [1, 5] {
+            let request = RequestRetransmit {
+                swap_id,
+                last_seen: Some(last_seen),
+            };
+            assert!(log.respond(&request).unwrap().is_empty());
+        }
+
+        let request = RequestRetransmit {
+            swap_id: SwapId::random(),
+            last_seen: None,
+        };
+        assert!(matches!(
+            log.respond(&request),
+            Err(Error::SwapIdMismatch { expected, .. }) if expected == swap_id
+        ));
+    }
+
+    #[test]
+    fn incompatible_versions() {
+        let hello = Hello {
+            swap_id: SwapId::random(),
+            version: ProtocolVersion::new(2, 0),
+        };
+        let abort = hello.respond(ProtocolVersion::new(1, 0)).unwrap_err();
+        assert_eq!(abort.swap_id, hello.swap_id);
+        assert_eq!(
+            abort.error_body,
+            Some(
+                AbortReason::IncompatibleVersion {
+                    local: ProtocolVersion::new(1, 0),
+                    remote: ProtocolVersion::new(2, 0),
+                }
+                .to_string()
+            )
+        );
+    }
+}
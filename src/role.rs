@@ -29,7 +29,8 @@ use crate::crypto::{self, AccordantKeySet};
 /// Possible roles during the trade setup. Trade roles are orthogonal to swap roles:
 /// any trade role can transition into any swap role, but the the particular transition
 /// that will happen is agreed upon & set in the deal.
-#[derive(Display, Debug, Clone, Hash, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Display, Debug, Clone, Hash, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[display(Debug)]
 pub enum TradeRole {
     /// The maker role creates the deal during the trade setup and waits for incoming connections.
@@ -83,7 +84,8 @@ impl FromStr for TradeRole {
 
 /// Possible roles during the swap phase. When the trade setup is completed [`TradeRole`] will
 /// transition into swap role according to the [`Deal`](crate::trade::Deal).
-#[derive(Display, Debug, Clone, Copy, Hash, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Display, Debug, Clone, Copy, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[display(Debug)]
 pub enum SwapRole {
     /// Alice, the swap role, is the role starting with accordant blockchain assets and exchange
@@ -21,7 +21,8 @@ use std::fmt;
 /// Store public keys for swap participants, one public key per [`SwapRole`] in the protocol.
 ///
 /// [`SwapRole`]: crate::role::SwapRole
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct DoubleKeys<Pk> {
     /// Public key associated to Alice swap role.
     pub alice: Pk,
@@ -46,7 +47,8 @@ where
 }
 
 /// Define the path selected for a failable script.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[display(Debug)]
 pub enum ScriptPath {
     /// The success path in the script.
@@ -60,7 +62,8 @@ pub enum ScriptPath {
 /// path used in [`Buyable`].
 ///
 /// [`Buyable`]: crate::transaction::Buyable
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct DataLock<Ti, Pk> {
     pub timelock: Ti,
     pub success: DoubleKeys<Pk>,
@@ -86,7 +89,8 @@ where
 /// mechanisms in [`Cancelable`].
 ///
 /// [`Cancelable`]: crate::transaction::Cancelable
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct DataPunishableLock<Ti, Pk> {
     pub timelock: Ti,
     pub success: DoubleKeys<Pk>,
@@ -107,7 +111,7 @@ where
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "serde"))]
 mod tests {
     use super::*;
     use bitcoin::secp256k1::PublicKey;
@@ -18,20 +18,41 @@
 //! and its concrete instances of swaps.
 
 use std::io;
-use std::str::FromStr;
 
 use crate::consensus::{self, Decodable, Encodable};
+#[cfg(feature = "serde")]
 use crate::hash::HashString;
 
+#[cfg(feature = "serde")]
 use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 
 pub mod btcxmr;
+pub mod store;
 
 fixed_hash::construct_fixed_hash!(
     /// A unique swap identifier represented as an 32 bytes hash.
     pub struct SwapId(32);
 );
 
+impl SwapId {
+    /// Human readable prefix of the checksummed swap identifier representation.
+    pub const HRP: &'static str = "swap";
+
+    /// Return the checksummed representation of the swap identifier: a Bech32m string prefixed
+    /// with `swap`. Unlike the default display, which truncates the hex value, this form is
+    /// complete and typo-resistant.
+    pub fn to_bech32(&self) -> String {
+        consensus::to_bech32m(Self::HRP, self)
+    }
+
+    /// Parse a swap identifier from its checksummed representation, fails if the string is
+    /// malformed, has the wrong prefix, or does not match its checksum.
+    pub fn from_bech32(s: &str) -> Result<Self, consensus::Error> {
+        consensus::from_bech32m(Self::HRP, s)
+    }
+}
+
+#[cfg(feature = "serde")]
 impl Serialize for SwapId {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -41,12 +62,16 @@ impl Serialize for SwapId {
     }
 }
 
+#[cfg(feature = "serde")]
 impl<'de> Deserialize<'de> for SwapId {
     fn deserialize<D>(deserializer: D) -> Result<SwapId, D::Error>
     where
         D: Deserializer<'de>,
     {
-        SwapId::from_str(&deserializer.deserialize_string(HashString)?).map_err(de::Error::custom)
+        deserializer
+            .deserialize_string(HashString)?
+            .parse()
+            .map_err(de::Error::custom)
     }
 }
 
@@ -65,11 +90,94 @@ impl Decodable for SwapId {
 
 impl_strict_encoding!(SwapId);
 
+/// Phases of a swap from the point of view of a participant, used to track the swaps of a daemon
+/// in a [`SwapStore`](store::SwapStore). Once in a terminal phase, [`SwapPhase::Completed`] or
+/// [`SwapPhase::Aborted`], nothing is left to do for the swap.
+#[derive(Display, Debug, Clone, Copy, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[display(Debug)]
+pub enum SwapPhase {
+    /// The participants are exchanging their parameters and the arbitrating transactions.
+    Setup,
+    /// The arbitrating funds are locked, the swap can be bought or cancelled.
+    Locked,
+    /// The swap has been cancelled on-chain, the funds can be refunded or punished.
+    Cancelled,
+    /// The funds have been bought, refunded, or punished.
+    Completed,
+    /// The swap stopped before any funds were locked.
+    Aborted,
+}
+
+impl SwapPhase {
+    /// Return `true` if the phase is terminal, i.e. the swap is completed or aborted.
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, Self::Completed | Self::Aborted)
+    }
+}
+
+impl Encodable for SwapPhase {
+    fn consensus_encode<W: io::Write>(&self, writer: &mut W) -> Result<usize, io::Error> {
+        match self {
+            SwapPhase::Setup => 0x01u8.consensus_encode(writer),
+            SwapPhase::Locked => 0x02u8.consensus_encode(writer),
+            SwapPhase::Cancelled => 0x03u8.consensus_encode(writer),
+            SwapPhase::Completed => 0x04u8.consensus_encode(writer),
+            SwapPhase::Aborted => 0x05u8.consensus_encode(writer),
+        }
+    }
+}
+
+impl Decodable for SwapPhase {
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        match Decodable::consensus_decode(d)? {
+            0x01u8 => Ok(SwapPhase::Setup),
+            0x02u8 => Ok(SwapPhase::Locked),
+            0x03u8 => Ok(SwapPhase::Cancelled),
+            0x04u8 => Ok(SwapPhase::Completed),
+            0x05u8 => Ok(SwapPhase::Aborted),
+            _ => Err(consensus::Error::UnknownType),
+        }
+    }
+}
+
+impl_strict_encoding!(SwapPhase);
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    use bitcoin::bech32::{self, ToBase32, Variant};
+    use std::str::FromStr;
+
+    #[test]
+    fn checksummed_swapid_round_trip() {
+        let swap_id =
+            SwapId::from_str("0x1baf1b36075de25a0f8e914b36759cac6f5d825622f8ccee597d87d4850c0d38")
+                .expect("Valid hex string");
+        let s = swap_id.to_bech32();
+        assert!(s.starts_with("swap1"));
+        assert_eq!(SwapId::from_bech32(&s).unwrap(), swap_id);
+        assert_eq!(SwapId::from_bech32(&s.to_uppercase()).unwrap(), swap_id);
+    }
+
+    #[test]
+    fn reject_corrupted_checksummed_swapid() {
+        let s = SwapId::random().to_bech32();
+        // Flip one character of the data part
+        let mut corrupted: Vec<char> = s.chars().collect();
+        corrupted[10] = if corrupted[10] == 'q' { 'p' } else { 'q' };
+        let corrupted: String = corrupted.into_iter().collect();
+        assert!(SwapId::from_bech32(&corrupted).is_err());
+        // Truncated
+        assert!(SwapId::from_bech32(&s[..s.len() - 1]).is_err());
+        // Wrong prefix
+        let other = bech32::encode("deal", [0u8; 32].to_base32(), Variant::Bech32m).unwrap();
+        assert!(SwapId::from_bech32(&other).is_err());
+    }
+
     #[test]
+    #[cfg(feature = "serde")]
     fn serialize_swapid_in_yaml() {
         let swap_id =
             SwapId::from_str("0x1baf1b36075de25a0f8e914b36759cac6f5d825622f8ccee597d87d4850c0d38")
@@ -82,6 +190,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "serde")]
     fn deserialize_swapid_from_yaml() {
         let s = "---\n\"0x1baf1b36075de25a0f8e914b36759cac6f5d825622f8ccee597d87d4850c0d38\"\n";
         let swap_id = serde_yaml::from_str(&s).expect("Decode swap id from yaml");
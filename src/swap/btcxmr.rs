@@ -18,6 +18,7 @@
 //! accordant blockchain.
 
 use crate::bitcoin::{fee::SatPerVByte, timelock::CSVTimelock, BitcoinSegwitV0};
+use crate::blockchain::{Network, NetworkCompat};
 use crate::consensus::{self, Decodable, Encodable};
 use crate::crypto::{
     self,
@@ -26,7 +27,10 @@ use crate::crypto::{
     SharedKeyId,
 };
 #[cfg(feature = "experimental")]
-use crate::crypto::{EncSign, RecoverSecret, Sign};
+use crate::crypto::{
+    adaptor::{ecdsa, to_point, to_scalar},
+    AdaptorSignature, EncSign, RecoverSecret, Sign,
+};
 use crate::monero::Monero;
 use crate::protocol;
 use crate::trade;
@@ -34,22 +38,6 @@ use crate::{blockchain::Blockchain, crypto::dleq::DLEQProof};
 
 use monero::cryptonote::hash::Hash;
 
-#[cfg(feature = "experimental")]
-use ecdsa_fun::{
-    adaptor::{Adaptor, HashTranscript},
-    fun::{Point, Scalar},
-    nonce, ECDSA,
-};
-// FIXME: when secp256kfun as new crates.io release
-#[cfg(feature = "experimental")]
-use rand::rngs::ThreadRng;
-#[cfg(feature = "experimental")]
-use rand_chacha::ChaCha20Rng;
-#[cfg(feature = "experimental")]
-use secp256kfun::marker::*;
-#[cfg(feature = "experimental")]
-use sha2::Sha256;
-
 use bitcoin::secp256k1::{PublicKey, Secp256k1, SecretKey};
 use bitcoin::util::psbt::PartiallySignedTransaction;
 #[cfg(feature = "experimental")]
@@ -60,11 +48,23 @@ use std::str::FromStr;
 
 pub mod message;
 
-#[cfg(feature = "experimental")]
-type Transcript = HashTranscript<Sha256, ChaCha20Rng>;
+/// Bitcoin-Monero atomic swap pair, Bitcoin is the arbitrating blockchain and Monero the
+/// accordant blockchain.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct BtcXmr;
 
-#[cfg(feature = "experimental")]
-type NonceGen = nonce::Synthetic<Sha256, nonce::GlobalRng<ThreadRng>>;
+impl NetworkCompat for BtcXmr {
+    type ArbitratingNetwork = bitcoin::Network;
+    type AccordantNetwork = monero::Network;
+
+    fn to_arbitrating_network(network: Network) -> bitcoin::Network {
+        network.into()
+    }
+
+    fn to_accordant_network(network: Network) -> monero::Network {
+        network.into()
+    }
+}
 
 /// Fully defined type for Bitcoin-Monero atomic swap sets of parameters.
 pub type Parameters = protocol::Parameters<
@@ -355,28 +355,13 @@ impl Sign<PublicKey, Sha256dHash, Signature> for KeyManager {
         key: ArbitratingKeyId,
         msg: Sha256dHash,
     ) -> Result<Signature, crypto::Error> {
-        let secret_key = self.get_or_derive_bitcoin_key(key)?;
-
-        // FIXME: when new version is released on crates.io
-        // let secret_key = Scalar::from(secret_key);
-        let secret_key = Scalar::from_slice(&secret_key[..])
-            .unwrap()
-            .mark::<NonZero>()
-            .expect("SecretKey is never zero");
+        let secret_key = to_scalar(&self.get_or_derive_bitcoin_key(key)?);
         let message_hash: &[u8; 32] = {
             use bitcoin::hashes::Hash;
             msg.as_inner()
         };
-
-        let nonce_gen = nonce::Synthetic::<Sha256, nonce::GlobalRng<ThreadRng>>::default();
-        let ecdsa = ECDSA::new(nonce_gen);
-
-        // FIXME
-        // Ok(ecdsa.sign(&secret_key, message_hash).into())
-        Ok(
-            Signature::from_compact(ecdsa.sign(&secret_key, message_hash).to_bytes().as_ref())
-                .unwrap(),
-        )
+        let sig = ecdsa().sign(&secret_key, message_hash);
+        Signature::from_compact(sig.to_bytes().as_ref()).map_err(crypto::Error::new)
     }
 
     fn verify_signature(
@@ -402,23 +387,7 @@ impl EncSign<PublicKey, Sha256dHash, Signature, EncryptedSignature> for KeyManag
         msg: Sha256dHash,
     ) -> Result<EncryptedSignature, crypto::Error> {
         let secret_key = self.get_or_derive_bitcoin_key(signing_key)?;
-
-        let engine = Adaptor::<Transcript, NonceGen>::default();
-        // FIXME
-        // let secret_signing_key = Scalar::from(secret_key);
-        let secret_signing_key = Scalar::from_slice(&secret_key[..])
-            .unwrap()
-            .mark::<NonZero>()
-            .expect("SecretKey is never zero");
-        // FIXME
-        // let encryption_key = Point::from(*encryption_key);
-        let encryption_key = Point::from_bytes(encryption_key.serialize()).unwrap();
-        let message_hash: &[u8; 32] = {
-            use bitcoin::hashes::Hash;
-            msg.as_inner()
-        };
-
-        Ok(engine.encrypted_sign(&secret_signing_key, &encryption_key, message_hash))
+        BitcoinSegwitV0::encrypt_sign(&secret_key, encryption_key, msg)
     }
 
     fn verify_encrypted_signature(
@@ -428,26 +397,7 @@ impl EncSign<PublicKey, Sha256dHash, Signature, EncryptedSignature> for KeyManag
         msg: Sha256dHash,
         sig: &EncryptedSignature,
     ) -> Result<(), crypto::Error> {
-        let engine = Adaptor::<Transcript, NonceGen>::default();
-        // FIXME
-        // let verification_key = Point::from(*signing_key);
-        // let encryption_key = Point::from(*encryption_key);
-        let verification_key = Point::from_bytes(signing_key.serialize()).unwrap();
-        let encryption_key = Point::from_bytes(encryption_key.serialize()).unwrap();
-        let message_hash: &[u8; 32] = {
-            use bitcoin::hashes::Hash;
-            msg.as_inner()
-        };
-
-        match engine.verify_encrypted_signature(
-            &verification_key,
-            &encryption_key,
-            message_hash,
-            sig,
-        ) {
-            true => Ok(()),
-            false => Err(crypto::Error::InvalidEncryptedSignature),
-        }
+        BitcoinSegwitV0::verify_adaptor(signing_key, encryption_key, msg, sig)
     }
 
     fn decrypt_signature(
@@ -463,24 +413,7 @@ impl EncSign<PublicKey, Sha256dHash, Signature, EncryptedSignature> for KeyManag
         little_endian_secret_bytes.reverse();
         let secret_key = SecretKey::from_slice(little_endian_secret_bytes.as_ref())
             .map_err(crypto::Error::new)?;
-
-        let adaptor = Adaptor::<Transcript, NonceGen>::default();
-        // FIXME
-        // let decryption_key = Scalar::from(secret_key);
-        let decryption_key = Scalar::from_slice(&secret_key[..])
-            .unwrap()
-            .mark::<NonZero>()
-            .expect("SecretKey is never zero");
-
-        // FIXME
-        // Ok(adaptor.decrypt_signature(&decryption_key, sig).into())
-        Ok(Signature::from_compact(
-            adaptor
-                .decrypt_signature(&decryption_key, sig)
-                .to_bytes()
-                .as_ref(),
-        )
-        .unwrap())
+        BitcoinSegwitV0::decrypt_sig(&secret_key, sig)
     }
 }
 
@@ -493,21 +426,8 @@ impl RecoverSecret<PublicKey, SecretKey, Signature, EncryptedSignature> for KeyM
         encryption_key: &PublicKey,
         sig: Signature,
     ) -> SecretKey {
-        let adaptor = Adaptor::<Transcript, NonceGen>::default();
-        // FIXME
-        // let encryption_key = Point::from(*encryption_key);
-        //let signature = ecdsa_fun::Signature::from(sig);
-        let encryption_key = Point::from_bytes(encryption_key.serialize()).unwrap();
-        let signature = ecdsa_fun::Signature::from_bytes(sig.serialize_compact()).unwrap();
-
-        match adaptor.recover_decryption_key(&encryption_key, &signature, &encrypted_sig) {
-            // FIXME
-            // Some(decryption_key) => decryption_key.into(),
-            Some(decryption_key) => {
-                SecretKey::from_slice(decryption_key.to_bytes().as_ref()).unwrap()
-            }
-            None => panic!("signature is not the decryption of our original encrypted signature"),
-        }
+        BitcoinSegwitV0::recover_secret(encryption_key, &sig, &encrypted_sig)
+            .expect("signature is the decryption of our original encrypted signature")
     }
 }
 
@@ -547,9 +467,7 @@ impl ProveCrossGroupDleq<PublicKey, monero::PublicKey, DLEQProof> for KeyManager
                 .point
                 .decompress()
                 .expect("Valid point to decompress"),
-            // FIXME
-            //ecdsa_fun::fun::Point::from(*encryption_key),
-            Point::from_bytes(encryption_key.serialize()).unwrap(),
+            to_point(encryption_key),
         )
     }
 }
@@ -0,0 +1,230 @@
+// Copyright 2021-2022 Farcaster Devs
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; either
+// version 3 of the License, or (at your option) any later version.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301, USA
+
+//! Lightweight in-memory store of the swaps tracked by a daemon. Each swap is kept in a
+//! [`SwapRecord`] holding its current [`SwapPhase`] and serialized artifacts, e.g. consensus
+//! encoded [`SavedTransaction`](crate::transaction::SavedTransaction)s, until it is pruned once
+//! terminal.
+
+use std::collections::hash_map::{self, HashMap};
+use std::io;
+
+use crate::consensus::{self, Decodable, Encodable};
+use crate::swap::{SwapId, SwapPhase};
+
+/// The state of a swap saved by a daemon: its current phase, the time of its last update, and the
+/// serialized artifacts needed to resume it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SwapRecord {
+    /// The swap identifier.
+    pub swap_id: SwapId,
+    /// The current phase of the swap.
+    pub phase: SwapPhase,
+    /// Time of the last update of the record, in seconds since the Unix epoch.
+    pub updated_at: u64,
+    /// Serialized artifacts of the swap, e.g. saved transactions or bundles.
+    pub artifacts: Vec<Vec<u8>>,
+}
+
+impl SwapRecord {
+    /// Current version of the swap record encoding.
+    pub const VERSION: u16 = 1;
+
+    /// Create a new record without artifacts.
+    pub fn new(swap_id: SwapId, phase: SwapPhase, updated_at: u64) -> Self {
+        Self {
+            swap_id,
+            phase,
+            updated_at,
+            artifacts: vec![],
+        }
+    }
+
+    /// Move the swap to a new phase at the given time.
+    pub fn update_phase(&mut self, phase: SwapPhase, updated_at: u64) {
+        self.phase = phase;
+        self.updated_at = updated_at;
+    }
+
+    /// Return `true` if the swap is in a terminal phase.
+    pub fn is_terminal(&self) -> bool {
+        self.phase.is_terminal()
+    }
+}
+
+impl Encodable for SwapRecord {
+    fn consensus_encode<W: io::Write>(&self, writer: &mut W) -> Result<usize, io::Error> {
+        let mut len = Self::VERSION.consensus_encode(writer)?;
+        len += self.swap_id.consensus_encode(writer)?;
+        len += self.phase.consensus_encode(writer)?;
+        len += self.updated_at.consensus_encode(writer)?;
+        Ok(len + self.artifacts.consensus_encode(writer)?)
+    }
+}
+
+impl Decodable for SwapRecord {
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        let version: u16 = Decodable::consensus_decode(d)?;
+        if version != Self::VERSION {
+            return Err(consensus::Error::ParseFailed(
+                "Unsupported swap record version",
+            ));
+        }
+        Ok(Self {
+            swap_id: Decodable::consensus_decode(d)?,
+            phase: Decodable::consensus_decode(d)?,
+            updated_at: Decodable::consensus_decode(d)?,
+            artifacts: Decodable::consensus_decode(d)?,
+        })
+    }
+}
+
+impl_strict_encoding!(SwapRecord);
+
+/// In-memory store of [`SwapRecord`]s indexed by swap identifier. Records of swaps in a terminal
+/// phase are reclaimed with [`SwapStore::prune`].
+#[derive(Debug, Clone, Default)]
+pub struct SwapStore {
+    records: HashMap<SwapId, SwapRecord>,
+}
+
+impl SwapStore {
+    /// Create an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Insert a record, replacing and returning the previous record of the same swap if any.
+    pub fn insert(&mut self, record: SwapRecord) -> Option<SwapRecord> {
+        self.records.insert(record.swap_id, record)
+    }
+
+    /// Return the record of a swap.
+    pub fn get(&self, swap_id: &SwapId) -> Option<&SwapRecord> {
+        self.records.get(swap_id)
+    }
+
+    /// Return a mutable reference to the record of a swap.
+    pub fn get_mut(&mut self, swap_id: &SwapId) -> Option<&mut SwapRecord> {
+        self.records.get_mut(swap_id)
+    }
+
+    /// Remove and return the record of a swap.
+    pub fn remove(&mut self, swap_id: &SwapId) -> Option<SwapRecord> {
+        self.records.remove(swap_id)
+    }
+
+    /// Return the number of records in the store.
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    /// Return `true` if the store has no records.
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Iterate over the records of the store, in arbitrary order.
+    pub fn iter(&self) -> hash_map::Values<'_, SwapId, SwapRecord> {
+        self.records.values()
+    }
+
+    /// Drop the records of swaps in a terminal phase last updated strictly before
+    /// `completed_before`, in seconds since the Unix epoch. In-flight swaps are always kept.
+    /// Return the pruned records.
+    pub fn prune(&mut self, completed_before: u64) -> Vec<SwapRecord> {
+        let pruned: Vec<SwapId> = self
+            .records
+            .values()
+            .filter(|record| record.is_terminal() && record.updated_at < completed_before)
+            .map(|record| record.swap_id)
+            .collect();
+        pruned
+            .iter()
+            .filter_map(|swap_id| self.records.remove(swap_id))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consensus::{deserialize, serialize};
+
+    fn record(phase: SwapPhase, updated_at: u64) -> SwapRecord {
+        SwapRecord::new(SwapId::random(), phase, updated_at)
+    }
+
+    #[test]
+    fn prune_only_terminal_swaps() {
+        let mut store = SwapStore::new();
+        let in_flight = [
+            record(SwapPhase::Setup, 10),
+            record(SwapPhase::Locked, 10),
+            record(SwapPhase::Cancelled, 10),
+        ];
+        let completed = record(SwapPhase::Completed, 10);
+        let aborted = record(SwapPhase::Aborted, 20);
+        let recently_completed = record(SwapPhase::Completed, 100);
+        for r in in_flight
+            .iter()
+            .chain([&completed, &aborted, &recently_completed])
+        {
+            assert!(store.insert(r.clone()).is_none());
+        }
+
+        let mut pruned: Vec<SwapId> = store.prune(50).iter().map(|r| r.swap_id).collect();
+        pruned.sort();
+        let mut expected = vec![completed.swap_id, aborted.swap_id];
+        expected.sort();
+        assert_eq!(pruned, expected);
+
+        assert_eq!(store.len(), 4);
+        for r in in_flight.iter().chain([&recently_completed]) {
+            assert_eq!(store.get(&r.swap_id), Some(r));
+        }
+        // Nothing left to prune at the same time
+        assert!(store.prune(50).is_empty());
+    }
+
+    #[test]
+    fn prune_swap_after_completion() {
+        let mut store = SwapStore::new();
+        let swap = record(SwapPhase::Locked, 10);
+        store.insert(swap.clone());
+        assert!(store.prune(u64::MAX).is_empty());
+
+        store
+            .get_mut(&swap.swap_id)
+            .unwrap()
+            .update_phase(SwapPhase::Completed, 20);
+        assert_eq!(store.prune(u64::MAX).len(), 1);
+        assert!(store.is_empty());
+    }
+
+    #[test]
+    fn swap_record_roundtrip() {
+        let mut swap = record(SwapPhase::Cancelled, 1_650_000_000);
+        swap.artifacts = vec![vec![0x01, 0x02], vec![], vec![0xff; 64]];
+        let decoded: SwapRecord = deserialize(&serialize(&swap)).unwrap();
+        assert_eq!(decoded, swap);
+
+        // Unknown phase
+        let mut bytes = serialize(&swap);
+        bytes[34] = 0x00;
+        assert!(deserialize::<SwapRecord>(&bytes).is_err());
+    }
+}
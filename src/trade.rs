@@ -35,8 +35,10 @@
 //! - A peer address, used to connect to the other peer
 
 use bitcoin::secp256k1::PublicKey;
-use inet2_addr::InetSocketAddr;
+use inet2_addr::{InetAddr, InetSocketAddr};
+#[cfg(feature = "serde")]
 use serde::ser::{Serialize, Serializer};
+#[cfg(feature = "serde")]
 use serde::{de, Deserialize, Deserializer};
 use std::fmt::Display;
 use std::str::FromStr;
@@ -46,9 +48,11 @@ use uuid::Uuid;
 
 use std::fmt;
 use std::io;
+use std::net::IpAddr;
 
-use crate::blockchain::{Blockchain, FeeStrategy, Network};
+use crate::blockchain::{AssetUnit, Blockchain, FeeStrategy, Network, Price};
 use crate::consensus::{self, serialize, serialize_hex, CanonicalBytes, Decodable, Encodable};
+#[cfg(feature = "serde")]
 use crate::hash::HashString;
 use crate::protocol::ArbitratingParameters;
 use crate::role::{SwapRole, TradeRole};
@@ -59,17 +63,37 @@ pub const DEAL_MAGIC_BYTES: &[u8; 6] = b"FCSWAP";
 /// Prefix for serialized deal.
 pub const DEAL_PREFIX: &str = "Deal:";
 
+/// Default number of confirmations required on the accordant lock, used for [`Version`] 1 deals,
+/// which do not carry the field, and when deserializing deal parameters without it with serde.
+pub const DEFAULT_ACCORDANT_CONFIRMATIONS: u64 = 10;
+
+/// Return the minimum number of confirmations of the accordant lock accepted in a deal on the
+/// given network: 10 on mainnet and testnet, the age at which Monero outputs become spendable, and
+/// 1 on local networks.
+pub fn min_accordant_confirmations(network: Network) -> u64 {
+    match network {
+        Network::Mainnet | Network::Testnet => 10,
+        Network::Local => 1,
+    }
+}
+
 /// A deal version containing the version and the activated features if any.
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Display, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Display)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[display("v{0}")]
 pub struct Version(u16);
 
 impl Version {
-    /// Create a new version 1 deal.
+    /// Create a new version 1 deal. Version 1 deals do not carry the accordant confirmations.
     pub fn new_v1() -> Self {
         Self::new(1)
     }
 
+    /// Create a new version 2 deal, carrying the accordant confirmations.
+    pub fn new_v2() -> Self {
+        Self::new(2)
+    }
+
     /// Create a deal from a raw version and feature `u16`.
     pub fn new(version: u16) -> Self {
         Version(version)
@@ -102,6 +126,127 @@ pub enum Error {
     /// The deal signature does not pass the validation tests.
     #[error("Invalid signature")]
     InvalidSignature,
+    /// An element is bound to a different network than the one agreed in the deal.
+    #[error("Network mismatch: deal is on {expected} but found {found}")]
+    NetworkMismatch {
+        /// The network agreed in the deal.
+        expected: Network,
+        /// The network the element is bound to.
+        found: Network,
+    },
+    /// The arbitrating and accordant blockchain networks cannot be used together in a swap.
+    #[error(
+        "Incompatible networks: arbitrating is on {arbitrating} but accordant is on {accordant}"
+    )]
+    IncompatibleNetworks {
+        /// The network of the arbitrating blockchain.
+        arbitrating: Network,
+        /// The network of the accordant blockchain.
+        accordant: Network,
+    },
+    /// A timelock does not match the value agreed in the deal.
+    #[error("The {0} timelock does not match the deal")]
+    TimelockMismatch(&'static str),
+    /// The punish timelock is not strictly greater than the cancel timelock, the punish path
+    /// would be reachable as soon as the refund path.
+    #[error("The punish timelock must be greater than the cancel timelock")]
+    UnorderedTimelocks,
+    /// A timelock is below the minimum required to cover the confirmation time.
+    #[error("The {0} timelock is below the minimum")]
+    TimelockTooShort(&'static str),
+    /// The accordant confirmations are below the minimum of the deal network.
+    #[error("{found} accordant confirmations are below the network minimum of {min}")]
+    NotEnoughConfirmations {
+        /// The minimum number of confirmations on the deal network.
+        min: u64,
+        /// The number of confirmations in the deal.
+        found: u64,
+    },
+    /// The fee strategy does not match the one agreed in the deal.
+    #[error("The fee strategy does not match the deal")]
+    FeeStrategyMismatch,
+    /// The fee strategy cannot be used for the arbitrating transactions of a deal.
+    #[error("The fee strategy is not supported in deals")]
+    UnsupportedFeeStrategy,
+    /// Version 1 deals do not carry the accordant confirmations, only the default can be used.
+    #[error("Version 1 deals cannot carry {0} accordant confirmations, use a version 2 deal")]
+    ConfirmationsRequireV2(u64),
+    /// The amount computed from a price does not fit in the amount type.
+    #[error("Amount overflow")]
+    AmountOverflow,
+    /// The peer address is neither a socket address nor an onion address.
+    #[error("Invalid peer address: {0}")]
+    InvalidPeerAddress(String),
+    /// The onion address is not a valid Tor v3 onion service address.
+    #[error("Invalid onion v3 address: {0}")]
+    InvalidOnionAddress(String),
+}
+
+/// Builds the peer address advertised in a [`Deal`]: an IPv4 or IPv6 socket address, or the
+/// address of a Tor v3 onion service. Parsed from and displayed as `1.2.3.4:9735`,
+/// `[::1]:9735`, or `<56 base32 characters>.onion`, onion services are advertised without port.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PeerAddress(InetSocketAddr);
+
+impl PeerAddress {
+    /// The suffix of onion service addresses.
+    pub const ONION_SUFFIX: &'static str = ".onion";
+
+    /// Create a peer address from an IP address and a port.
+    pub fn socket(ip: IpAddr, port: u16) -> Self {
+        Self(InetSocketAddr::socket(ip, port))
+    }
+
+    /// Create a peer address from a Tor v3 onion service address, with or without the `.onion`
+    /// suffix. Fails with [`Error::InvalidOnionAddress`] if the address is malformed or its
+    /// checksum or version is invalid.
+    pub fn onion_v3(onion: &str) -> Result<Self, Error> {
+        let key = onion.strip_suffix(Self::ONION_SUFFIX).unwrap_or(onion);
+        match InetAddr::from_str(key) {
+            Ok(InetAddr::Tor(key)) => Ok(Self(InetSocketAddr::Tor(key))),
+            _ => Err(Error::InvalidOnionAddress(onion.to_string())),
+        }
+    }
+
+    /// Returns true if the peer is a Tor onion service.
+    pub fn is_onion(&self) -> bool {
+        self.0.is_tor()
+    }
+}
+
+impl From<InetSocketAddr> for PeerAddress {
+    fn from(address: InetSocketAddr) -> Self {
+        Self(address)
+    }
+}
+
+impl From<PeerAddress> for InetSocketAddr {
+    fn from(address: PeerAddress) -> Self {
+        address.0
+    }
+}
+
+impl FromStr for PeerAddress {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.ends_with(Self::ONION_SUFFIX) {
+            return Self::onion_v3(s);
+        }
+        match InetSocketAddr::from_str(s) {
+            Ok(address) if !address.is_tor() => Ok(Self(address)),
+            _ => Err(Error::InvalidPeerAddress(s.to_string())),
+        }
+    }
+}
+
+impl Display for PeerAddress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0.address().onion_address() {
+            Some(onion) => write!(f, "{}", onion),
+            None => write!(f, "{}", self.0),
+        }
+    }
 }
 
 fixed_hash::construct_fixed_hash!(
@@ -110,6 +255,24 @@ fixed_hash::construct_fixed_hash!(
     pub struct DealFingerprint(32);
 );
 
+impl DealFingerprint {
+    /// Human readable prefix of the checksummed deal fingerprint representation.
+    pub const HRP: &'static str = "deal";
+
+    /// Return the checksummed representation of the fingerprint: a Bech32m string prefixed with
+    /// `deal`. Short enough to reference a deal instead of pasting it entirely.
+    pub fn to_bech32(&self) -> String {
+        consensus::to_bech32m(Self::HRP, self)
+    }
+
+    /// Parse a fingerprint from its checksummed representation, fails if the string is malformed,
+    /// has the wrong prefix, or does not match its checksum.
+    pub fn from_bech32(s: &str) -> Result<Self, consensus::Error> {
+        consensus::from_bech32m(Self::HRP, s)
+    }
+}
+
+#[cfg(feature = "serde")]
 impl Serialize for DealFingerprint {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -119,6 +282,7 @@ impl Serialize for DealFingerprint {
     }
 }
 
+#[cfg(feature = "serde")]
 impl<'de> Deserialize<'de> for DealFingerprint {
     fn deserialize<D>(deserializer: D) -> Result<DealFingerprint, D::Error>
     where
@@ -139,7 +303,14 @@ impl<'de> Deserialize<'de> for DealFingerprint {
 /// Amount types may have multiple serialization representation, e.g. btc and sat for bitcoin or
 /// xmr and pico for monero. Using [`Display`] and [`FromStr`] unifies the interface to
 /// de/serialize generic amounts.
-#[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+///
+/// Deserialized parameters are validated with [`DealParameters::validate_accordant_confirmations`].
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(try_from = "UncheckedDealParameters<Amt, Bmt, Ti, F>")
+)]
 pub struct DealParameters<Amt, Bmt, Ti, F> {
     /// The deal unique identifier.
     pub uuid: Uuid,
@@ -150,14 +321,20 @@ pub struct DealParameters<Amt, Bmt, Ti, F> {
     /// The chosen accordant blockchain.
     pub accordant_blockchain: Blockchain,
     /// Amount of arbitrating assets to exchanged.
-    #[serde(with = "string")]
-    #[serde(bound(serialize = "Amt: Display"))]
-    #[serde(bound(deserialize = "Amt: FromStr, Amt::Err: Display"))]
+    #[cfg_attr(feature = "serde", serde(with = "string"))]
+    #[cfg_attr(feature = "serde", serde(bound(serialize = "Amt: Display")))]
+    #[cfg_attr(
+        feature = "serde",
+        serde(bound(deserialize = "Amt: FromStr, Amt::Err: Display"))
+    )]
     pub arbitrating_amount: Amt,
     /// Amount of accordant assets to exchanged.
-    #[serde(with = "string")]
-    #[serde(bound(serialize = "Bmt: Display"))]
-    #[serde(bound(deserialize = "Bmt: FromStr, Bmt::Err: Display"))]
+    #[cfg_attr(feature = "serde", serde(with = "string"))]
+    #[cfg_attr(feature = "serde", serde(bound(serialize = "Bmt: Display")))]
+    #[cfg_attr(
+        feature = "serde",
+        serde(bound(deserialize = "Bmt: FromStr, Bmt::Err: Display"))
+    )]
     pub accordant_amount: Bmt,
     /// The cancel timelock parameter of the arbitrating blockchain.
     pub cancel_timelock: Ti,
@@ -167,8 +344,66 @@ pub struct DealParameters<Amt, Bmt, Ti, F> {
     pub fee_strategy: FeeStrategy<F>,
     /// The future maker swap role.
     pub maker_role: SwapRole,
+    /// Number of confirmations the accordant lock must reach before the swap proceeds, at least
+    /// [`min_accordant_confirmations`] of the network.
+    #[cfg_attr(feature = "serde", serde(default = "default_accordant_confirmations"))]
+    pub accordant_confirmations: u64,
 }
 
+#[cfg(feature = "serde")]
+fn default_accordant_confirmations() -> u64 {
+    DEFAULT_ACCORDANT_CONFIRMATIONS
+}
+
+/// Deal parameters deserialized with serde before being validated into [`DealParameters`].
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+struct UncheckedDealParameters<Amt, Bmt, Ti, F> {
+    uuid: Uuid,
+    network: Network,
+    arbitrating_blockchain: Blockchain,
+    accordant_blockchain: Blockchain,
+    #[serde(with = "string")]
+    #[serde(bound(deserialize = "Amt: FromStr, Amt::Err: Display"))]
+    arbitrating_amount: Amt,
+    #[serde(with = "string")]
+    #[serde(bound(deserialize = "Bmt: FromStr, Bmt::Err: Display"))]
+    accordant_amount: Bmt,
+    cancel_timelock: Ti,
+    punish_timelock: Ti,
+    fee_strategy: FeeStrategy<F>,
+    maker_role: SwapRole,
+    #[serde(default = "default_accordant_confirmations")]
+    accordant_confirmations: u64,
+}
+
+#[cfg(feature = "serde")]
+impl<Amt, Bmt, Ti, F> TryFrom<UncheckedDealParameters<Amt, Bmt, Ti, F>>
+    for DealParameters<Amt, Bmt, Ti, F>
+{
+    type Error = Error;
+
+    fn try_from(unchecked: UncheckedDealParameters<Amt, Bmt, Ti, F>) -> Result<Self, Error> {
+        let parameters = DealParameters {
+            uuid: unchecked.uuid,
+            network: unchecked.network,
+            arbitrating_blockchain: unchecked.arbitrating_blockchain,
+            accordant_blockchain: unchecked.accordant_blockchain,
+            arbitrating_amount: unchecked.arbitrating_amount,
+            accordant_amount: unchecked.accordant_amount,
+            cancel_timelock: unchecked.cancel_timelock,
+            punish_timelock: unchecked.punish_timelock,
+            fee_strategy: unchecked.fee_strategy,
+            maker_role: unchecked.maker_role,
+            accordant_confirmations: unchecked.accordant_confirmations,
+        };
+        parameters.validate_fee_strategy()?;
+        parameters.validate_accordant_confirmations()?;
+        Ok(parameters)
+    }
+}
+
+#[cfg(feature = "serde")]
 mod string {
     use std::fmt::Display;
     use std::str::FromStr;
@@ -197,11 +432,10 @@ mod string {
 
 impl<Amt, Bmt, Ti, F> Display for DealParameters<Amt, Bmt, Ti, F>
 where
-    Self: Encodable,
-    Amt: Display,
-    Bmt: Display,
-    Ti: Display,
-    F: Display,
+    Amt: CanonicalBytes + Display,
+    Bmt: CanonicalBytes + Display,
+    Ti: CanonicalBytes + Display,
+    F: CanonicalBytes + Display,
 {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         writeln!(f, "Uuid: {}", self.uuid)?;
@@ -211,6 +445,7 @@ where
         writeln!(f, "- amount: {}", self.arbitrating_amount)?;
         writeln!(f, "Blockchain: {}", self.accordant_blockchain)?;
         writeln!(f, "- amount: {}", self.accordant_amount)?;
+        writeln!(f, "- confirmations: {}", self.accordant_confirmations)?;
         writeln!(f, "Timelocks")?;
         writeln!(f, "- cancel: {}", self.cancel_timelock)?;
         writeln!(f, "- punish: {}", self.punish_timelock)?;
@@ -220,16 +455,90 @@ where
 }
 
 impl<Amt, Bmt, Ti, F> DealParameters<Amt, Bmt, Ti, F> {
-    /// Transform the deal parameters in a deal of [`Version`] 1.
-    pub fn to_v1(self, node_id: PublicKey, peer_address: InetSocketAddr) -> Deal<Amt, Bmt, Ti, F> {
-        Deal {
+    /// Transform the deal parameters in a deal of [`Version`] 1. The peer address can be built
+    /// with [`PeerAddress`] to advertise an onion service. Version 1 deals do not carry the
+    /// accordant confirmations, fails with [`Error::ConfirmationsRequireV2`] if they are not
+    /// [`DEFAULT_ACCORDANT_CONFIRMATIONS`], use [`Self::to_v2`] to negotiate another depth.
+    pub fn to_v1(
+        self,
+        node_id: PublicKey,
+        peer_address: impl Into<InetSocketAddr>,
+    ) -> Result<Deal<Amt, Bmt, Ti, F>, Error> {
+        self.check_version(&Version::new_v1())?;
+        Ok(Deal {
             version: Version::new_v1(),
             parameters: self,
             node_id,
-            peer_address,
+            peer_address: peer_address.into(),
+        })
+    }
+
+    /// Transform the deal parameters in a deal of [`Version`] 2, carrying the accordant
+    /// confirmations. The peer address can be built with [`PeerAddress`] to advertise an onion
+    /// service.
+    pub fn to_v2(
+        self,
+        node_id: PublicKey,
+        peer_address: impl Into<InetSocketAddr>,
+    ) -> Deal<Amt, Bmt, Ti, F> {
+        Deal {
+            version: Version::new_v2(),
+            parameters: self,
+            node_id,
+            peer_address: peer_address.into(),
         }
     }
 
+    /// Return the lowest deal version able to carry the parameters: version 1 when the accordant
+    /// confirmations are the default, version 2 otherwise.
+    pub fn min_version(&self) -> Version {
+        match self.accordant_confirmations {
+            DEFAULT_ACCORDANT_CONFIRMATIONS => Version::new_v1(),
+            _ => Version::new_v2(),
+        }
+    }
+
+    // Check that a deal of the given version can carry the parameters.
+    fn check_version(&self, version: &Version) -> Result<(), Error> {
+        match version.to_u16() {
+            1 if self.accordant_confirmations != DEFAULT_ACCORDANT_CONFIRMATIONS => {
+                Err(Error::ConfirmationsRequireV2(self.accordant_confirmations))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Set the number of confirmations the accordant lock must reach before the swap proceeds.
+    /// Fails with [`Error::NotEnoughConfirmations`] if below the minimum of the deal network.
+    pub fn with_accordant_confs(mut self, confirmations: u64) -> Result<Self, Error> {
+        self.accordant_confirmations = confirmations;
+        self.validate_accordant_confirmations()?;
+        Ok(self)
+    }
+
+    /// Validate that the fee strategy can be used for all the arbitrating transactions. The
+    /// [`FeeStrategy::None`] strategy is rejected with [`Error::UnsupportedFeeStrategy`] as only
+    /// the lock transaction can carry an anchor output.
+    pub fn validate_fee_strategy(&self) -> Result<(), Error> {
+        match self.fee_strategy {
+            FeeStrategy::None => Err(Error::UnsupportedFeeStrategy),
+            _ => Ok(()),
+        }
+    }
+
+    /// Validate that the accordant confirmations reach [`min_accordant_confirmations`] of the deal
+    /// network. Fails with [`Error::NotEnoughConfirmations`].
+    pub fn validate_accordant_confirmations(&self) -> Result<(), Error> {
+        let min = min_accordant_confirmations(self.network);
+        if self.accordant_confirmations < min {
+            return Err(Error::NotEnoughConfirmations {
+                min,
+                found: self.accordant_confirmations,
+            });
+        }
+        Ok(())
+    }
+
     /// Return the future swap role for the given trade role.
     pub fn swap_role(&self, trade_role: &TradeRole) -> SwapRole {
         match trade_role {
@@ -258,28 +567,109 @@ impl<Amt, Bmt, Ti, F> DealParameters<Amt, Bmt, Ti, F> {
 
 impl<Amt, Bmt, Ti, F> DealParameters<Amt, Bmt, Ti, F>
 where
-    Self: Encodable,
+    Ti: PartialOrd,
 {
-    /// Generate the [`DealFingerprint`] from the deal parameters. The fingerprint identifies the
-    /// content of a deal's parameters (**without the uuid**) by taking the hash value of its
-    /// serialization.
-    pub fn fingerprint(&self) -> DealFingerprint {
-        let mut keccak = Keccak::v256();
-        let mut out = [0u8; 32];
-        keccak.update(&serialize(self)[16..]);
-        keccak.finalize(&mut out);
-        DealFingerprint(out)
+    /// Validate that the cancel and punish timelocks are both at least `min_timelock`, enough to
+    /// cover the confirmation time of the arbitrating transactions, and that the punish timelock
+    /// is strictly greater than the cancel timelock. Fails with [`Error::TimelockTooShort`] or
+    /// [`Error::UnorderedTimelocks`].
+    pub fn validate_timelocks(&self, min_timelock: &Ti) -> Result<(), Error> {
+        if &self.cancel_timelock < min_timelock {
+            return Err(Error::TimelockTooShort("cancel"));
+        }
+        if &self.punish_timelock < min_timelock {
+            return Err(Error::TimelockTooShort("punish"));
+        }
+        if self.punish_timelock <= self.cancel_timelock {
+            return Err(Error::UnorderedTimelocks);
+        }
+        Ok(())
     }
 }
 
-impl<Amt, Bmt, Ti, F> Encodable for DealParameters<Amt, Bmt, Ti, F>
+impl<Amt, Bmt, Ti, F> DealParameters<Amt, Bmt, Ti, F>
+where
+    Amt: AssetUnit + Copy,
+    Bmt: AssetUnit + Copy,
+{
+    /// Return the price implied by the deal amounts, `None` if one of the amounts is zero.
+    pub fn price(&self) -> Option<Price<Amt, Bmt>> {
+        Price::new(self.arbitrating_amount, self.accordant_amount)
+    }
+
+    /// Set the accordant amount to the value of the arbitrating amount at the given price. Fails
+    /// with [`Error::AmountOverflow`] if the computed amount overflows.
+    pub fn set_accordant_amount_at(&mut self, price: &Price<Amt, Bmt>) -> Result<(), Error> {
+        self.accordant_amount = price
+            .accordant_amount(self.arbitrating_amount)
+            .ok_or(Error::AmountOverflow)?;
+        Ok(())
+    }
+
+    /// Set the arbitrating amount to the value of the accordant amount at the given price. Fails
+    /// with [`Error::AmountOverflow`] if the computed amount overflows.
+    pub fn set_arbitrating_amount_at(&mut self, price: &Price<Amt, Bmt>) -> Result<(), Error> {
+        self.arbitrating_amount = price
+            .arbitrating_amount(self.accordant_amount)
+            .ok_or(Error::AmountOverflow)?;
+        Ok(())
+    }
+}
+
+impl<Amt, Bmt, Ti, F> DealParameters<Amt, Bmt, Ti, F>
 where
     Amt: CanonicalBytes,
     Bmt: CanonicalBytes,
     Ti: CanonicalBytes,
     F: CanonicalBytes,
 {
-    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+    /// Generate the [`DealFingerprint`] from the deal parameters. The fingerprint identifies the
+    /// content of a deal's parameters (**without the uuid**) by taking the hash value of their
+    /// serialization at [`Self::min_version`], parameters with the default accordant confirmations
+    /// keep their version 1 fingerprint.
+    pub fn fingerprint(&self) -> DealFingerprint {
+        let mut ser = vec![];
+        self.consensus_encode_versioned(&self.min_version(), &mut ser)
+            .expect("The minimum version carries the parameters");
+        let mut keccak = Keccak::v256();
+        let mut out = [0u8; 32];
+        keccak.update(&ser[16..]);
+        keccak.finalize(&mut out);
+        DealFingerprint(out)
+    }
+
+    /// Encode the parameters as in a deal of the given version. Version 1 does not carry the
+    /// accordant confirmations and fails if they are not the default, later versions append them
+    /// after the maker role.
+    pub fn consensus_encode_versioned<W: io::Write>(
+        &self,
+        version: &Version,
+        s: &mut W,
+    ) -> Result<usize, io::Error> {
+        self.check_version(version)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let len = self.encode_v1(s)?;
+        match version.to_u16() {
+            1 => Ok(len),
+            _ => Ok(len + self.accordant_confirmations.consensus_encode(s)?),
+        }
+    }
+
+    /// Decode parameters encoded as in a deal of the given version, version 1 parameters use
+    /// [`DEFAULT_ACCORDANT_CONFIRMATIONS`]. Fails on versions other than 1 and 2.
+    pub fn consensus_decode_versioned<D: io::Read>(
+        d: &mut D,
+        version: &Version,
+    ) -> Result<Self, consensus::Error> {
+        match version.to_u16() {
+            1 => Self::decode_versioned(d, false),
+            2 => Self::decode_versioned(d, true),
+            _ => Err(consensus::Error::new(Error::UnsupportedVersion).in_field("version")),
+        }
+    }
+
+    // Encode the parameters without the accordant confirmations, as in version 1 deals.
+    fn encode_v1<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
         let mut len = self.uuid.to_bytes_le().consensus_encode(s)?;
         len += self.network.consensus_encode(s)?;
         len += self.arbitrating_blockchain.consensus_encode(s)?;
@@ -303,8 +693,56 @@ where
         len += self.fee_strategy.consensus_encode(s)?;
         Ok(len + self.maker_role.consensus_encode(s)?)
     }
+
+    // Decode the parameters, the accordant confirmations are only present from version 2 deals.
+    fn decode_versioned<D: io::Read>(
+        d: &mut D,
+        with_confirmations: bool,
+    ) -> Result<Self, consensus::Error> {
+        let mut parameters = DealParameters {
+            uuid: Uuid::from_bytes_le(decode_field(d, "uuid")?),
+            network: decode_field(d, "network")?,
+            arbitrating_blockchain: decode_field(d, "arbitrating blockchain")?,
+            accordant_blockchain: decode_field(d, "accordant blockchain")?,
+            arbitrating_amount: decode_amount(d, "arbitrating amount")?,
+            accordant_amount: decode_amount(d, "accordant amount")?,
+            cancel_timelock: decode_canonical_field(d, "cancel timelock")?,
+            punish_timelock: decode_canonical_field(d, "punish timelock")?,
+            fee_strategy: decode_field(d, "fee strategy")?,
+            maker_role: decode_field(d, "maker role")?,
+            accordant_confirmations: DEFAULT_ACCORDANT_CONFIRMATIONS,
+        };
+        parameters
+            .validate_fee_strategy()
+            .map_err(|e| consensus::Error::new(e).in_field("fee strategy"))?;
+        if with_confirmations {
+            let field = "accordant confirmations";
+            parameters.accordant_confirmations = decode_field(d, field)?;
+            parameters
+                .validate_accordant_confirmations()
+                .map_err(|e| consensus::Error::new(e).in_field(field))?;
+        }
+        Ok(parameters)
+    }
+}
+
+/// Standalone deal parameters use the version 1 encoding, encoding fails if the accordant
+/// confirmations are not the default. Negotiated confirmations are carried by version 2 deals, or
+/// with [`DealParameters::consensus_encode_versioned`].
+impl<Amt, Bmt, Ti, F> Encodable for DealParameters<Amt, Bmt, Ti, F>
+where
+    Amt: CanonicalBytes,
+    Bmt: CanonicalBytes,
+    Ti: CanonicalBytes,
+    F: CanonicalBytes,
+{
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        self.consensus_encode_versioned(&Version::new_v1(), s)
+    }
 }
 
+/// Standalone deal parameters use the version 1 encoding and decode with
+/// [`DEFAULT_ACCORDANT_CONFIRMATIONS`].
 impl<Amt, Bmt, Ti, F> Decodable for DealParameters<Amt, Bmt, Ti, F>
 where
     Amt: CanonicalBytes,
@@ -313,19 +751,41 @@ where
     F: CanonicalBytes,
 {
     fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
-        Ok(DealParameters {
-            uuid: Uuid::from_bytes_le(Decodable::consensus_decode(d)?),
-            network: Decodable::consensus_decode(d)?,
-            arbitrating_blockchain: Decodable::consensus_decode(d)?,
-            accordant_blockchain: Decodable::consensus_decode(d)?,
-            arbitrating_amount: Amt::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?,
-            accordant_amount: Bmt::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?,
-            cancel_timelock: Ti::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?,
-            punish_timelock: Ti::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?,
-            fee_strategy: Decodable::consensus_decode(d)?,
-            maker_role: Decodable::consensus_decode(d)?,
-        })
+        Self::consensus_decode_versioned(d, &Version::new_v1())
+    }
+}
+
+/// Decode a field of a deal, naming it in the error on failure.
+fn decode_field<T, D>(d: &mut D, field: &'static str) -> Result<T, consensus::Error>
+where
+    T: Decodable,
+    D: io::Read,
+{
+    T::consensus_decode(d).map_err(|e| e.in_field(field))
+}
+
+/// Decode a length prefixed canonical field of a deal, naming it in the error on failure.
+fn decode_canonical_field<T, D>(d: &mut D, field: &'static str) -> Result<T, consensus::Error>
+where
+    T: CanonicalBytes,
+    D: io::Read,
+{
+    let bytes: Vec<u8> = decode_field(d, field)?;
+    T::from_canonical_bytes(bytes.as_ref()).map_err(|e| e.in_field(field))
+}
+
+/// Decode an amount of a deal, rejecting zero amounts, i.e. amounts canonically encoded with only
+/// zero bytes.
+fn decode_amount<T, D>(d: &mut D, field: &'static str) -> Result<T, consensus::Error>
+where
+    T: CanonicalBytes,
+    D: io::Read,
+{
+    let amount: T = decode_canonical_field(d, field)?;
+    if amount.as_canonical_bytes().iter().all(|b| *b == 0) {
+        return Err(consensus::Error::ParseFailed("Amount is zero").in_field(field));
     }
+    Ok(amount)
 }
 
 impl_strict_encoding!(DealParameters<Amt, Bmt, Ti, F>, Amt: CanonicalBytes, Bmt: CanonicalBytes, Ti: CanonicalBytes, F: CanonicalBytes,);
@@ -333,15 +793,33 @@ impl_strict_encoding!(DealParameters<Amt, Bmt, Ti, F>, Amt: CanonicalBytes, Bmt:
 /// A deal is shared across [`TradeRole::Maker`]'s prefered network to signal is willing of trading
 /// some assets at some conditions. The assets and condition are defined in the [`DealParameters`],
 /// maker peer connection information are contained in the deal.
-#[derive(Debug, Clone, Eq, Hash, PartialEq, Serialize, Deserialize)]
+///
+/// ## Equality and hashing
+/// Two deals are equal, and hash equal, when all their fields are equal, i.e. when their consensus
+/// serializations are equal. This includes the deal uuid and the maker's node id and peer address,
+/// and identical deals relayed by different peers can be stored once in a `HashSet`. Use
+/// [`Deal::fingerprint`] to identify deals with the same content regardless of their uuid, or
+/// [`DealParameters::fingerprint`] to also ignore the maker's peer metadata.
+///
+/// Deserialized version 1 deals are rejected if their parameters carry non-default accordant
+/// confirmations, as they could not be encoded with Farcaster consensus.
+#[derive(Debug, Clone, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "UncheckedDeal<Amt, Bmt, Ti, F>"))]
 pub struct Deal<Amt, Bmt, Ti, F> {
     /// The deal version.
     pub version: Version,
     /// The content of the deal.
-    #[serde(bound(serialize = "Amt: Display, Bmt: Display, Ti: Serialize, F: Serialize"))]
-    #[serde(bound(
-        deserialize = "Amt: FromStr, Amt::Err: Display, Bmt: FromStr, Bmt::Err: Display, Ti: Deserialize<'de>, F: Deserialize<'de>"
-    ))]
+    #[cfg_attr(
+        feature = "serde",
+        serde(bound(serialize = "Amt: Display, Bmt: Display, Ti: Serialize, F: Serialize"))
+    )]
+    #[cfg_attr(
+        feature = "serde",
+        serde(bound(
+            deserialize = "Amt: FromStr, Amt::Err: Display, Bmt: FromStr, Bmt::Err: Display, Ti: Deserialize<'de>, F: Deserialize<'de>"
+        ))
+    )]
     pub parameters: DealParameters<Amt, Bmt, Ti, F>,
     /// Node public key, used both as an ID and encryption key for per-session ECDH.
     pub node_id: PublicKey,
@@ -350,6 +828,34 @@ pub struct Deal<Amt, Bmt, Ti, F> {
     pub peer_address: InetSocketAddr,
 }
 
+/// Deal deserialized with serde before its version is checked against its parameters.
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+struct UncheckedDeal<Amt, Bmt, Ti, F> {
+    version: Version,
+    #[serde(bound(
+        deserialize = "Amt: FromStr, Amt::Err: Display, Bmt: FromStr, Bmt::Err: Display, Ti: Deserialize<'de>, F: Deserialize<'de>"
+    ))]
+    parameters: DealParameters<Amt, Bmt, Ti, F>,
+    node_id: PublicKey,
+    peer_address: InetSocketAddr,
+}
+
+#[cfg(feature = "serde")]
+impl<Amt, Bmt, Ti, F> TryFrom<UncheckedDeal<Amt, Bmt, Ti, F>> for Deal<Amt, Bmt, Ti, F> {
+    type Error = Error;
+
+    fn try_from(unchecked: UncheckedDeal<Amt, Bmt, Ti, F>) -> Result<Self, Error> {
+        unchecked.parameters.check_version(&unchecked.version)?;
+        Ok(Deal {
+            version: unchecked.version,
+            parameters: unchecked.parameters,
+            node_id: unchecked.node_id,
+            peer_address: unchecked.peer_address,
+        })
+    }
+}
+
 impl<Amt, Bmt, Ti, F> Deal<Amt, Bmt, Ti, F>
 where
     Amt: Copy,
@@ -362,6 +868,7 @@ where
             cancel_timelock: self.parameters.cancel_timelock,
             punish_timelock: self.parameters.punish_timelock,
             fee_strategy: self.parameters.fee_strategy,
+            lock_metadata: None,
         }
     }
 }
@@ -370,6 +877,19 @@ impl<Amt, Bmt, Ti, F> Deal<Amt, Bmt, Ti, F>
 where
     Self: Encodable,
 {
+    /// Generate the [`DealFingerprint`] of the deal parameters, identifying the content of the
+    /// deal independently of its uuid and of the maker's node id and peer address. Same as calling
+    /// [`DealParameters::fingerprint`] on the deal parameters.
+    pub fn parameters_fingerprint(&self) -> DealFingerprint
+    where
+        Amt: CanonicalBytes,
+        Bmt: CanonicalBytes,
+        Ti: CanonicalBytes,
+        F: CanonicalBytes,
+    {
+        self.parameters.fingerprint()
+    }
+
     /// Generate the deal [`DealFingerprint`]. Serialized the deal (**without uuid**) and return
     /// its keccak hash.
     pub fn fingerprint(&self) -> DealFingerprint {
@@ -450,7 +970,9 @@ where
     fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
         let mut len = DEAL_MAGIC_BYTES.consensus_encode(s)?;
         len += self.version.consensus_encode(s)?;
-        len += self.parameters.consensus_encode(s)?;
+        len += self
+            .parameters
+            .consensus_encode_versioned(&self.version, s)?;
         len += self.node_id.as_canonical_bytes().consensus_encode(s)?;
         len +=
             strict_encoding::StrictEncode::strict_encode(&self.peer_address, s).map_err(|_| {
@@ -475,12 +997,14 @@ where
         if magic_bytes != *DEAL_MAGIC_BYTES {
             return Err(consensus::Error::IncorrectMagicBytes);
         }
+        let version: Version = decode_field(d, "version")?;
+        let parameters = DealParameters::consensus_decode_versioned(d, &version)?;
         Ok(Deal {
-            version: Decodable::consensus_decode(d)?,
-            parameters: Decodable::consensus_decode(d)?,
-            node_id: PublicKey::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?,
+            version,
+            parameters,
+            node_id: decode_canonical_field(d, "node id")?,
             peer_address: strict_encoding::StrictDecode::strict_decode(d)
-                .map_err(consensus::Error::new)?,
+                .map_err(|e| consensus::Error::new(e).in_field("peer address"))?,
         })
     }
 }
@@ -532,6 +1056,7 @@ mod tests {
                 punish_timelock: CSVTimelock::new(6),
                 fee_strategy: FeeStrategy::Fixed(SatPerVByte::from_sat(1)),
                 maker_role: SwapRole::Bob,
+                accordant_confirmations: 10,
             }
         };
     }
@@ -560,16 +1085,20 @@ mod tests {
 
     #[test]
     fn display_deal_params() {
-        assert_eq!(&format!("{}", *DEAL_PARAMS), "Uuid: 67e55044-10b1-426f-9247-bb680e5fe0c8\nFingerprint: 0xd68b1483de11001050026ca012a2b440818dac23341384c60680f668b52697b0\nNetwork: Testnet\nBlockchain: Bitcoin\n- amount: 0.00001350 BTC\nBlockchain: Monero\n- amount: 0.000000010000 XMR\nTimelocks\n- cancel: 4 blocks\n- punish: 6 blocks\nFee strategy: 1 satoshi/vByte\nMaker swap role: Bob\n");
+        assert_eq!(&format!("{}", *DEAL_PARAMS), "Uuid: 67e55044-10b1-426f-9247-bb680e5fe0c8\nFingerprint: 0xd68b1483de11001050026ca012a2b440818dac23341384c60680f668b52697b0\nNetwork: Testnet\nBlockchain: Bitcoin\n- amount: 0.00001350 BTC\nBlockchain: Monero\n- amount: 0.000000010000 XMR\n- confirmations: 10\nTimelocks\n- cancel: 4 blocks\n- punish: 6 blocks\nFee strategy: 1 satoshi/vByte\nMaker swap role: Bob\n");
     }
 
     #[test]
     fn display_deal() {
-        let deal = DEAL_PARAMS.clone().to_v1(*NODE_ID, *PEER_ADDRESS);
+        let deal = DEAL_PARAMS
+            .clone()
+            .to_v1(*NODE_ID, *PEER_ADDRESS)
+            .expect("Default confirmations");
         assert_eq!(&format!("{}", deal), S);
     }
 
     #[test]
+    #[cfg(feature = "serde")]
     fn serialize_deal_params_in_yaml() {
         let deal_params: DealParameters<bitcoin::Amount, monero::Amount, CSVTimelock, SatPerVByte> =
             DealParameters {
@@ -583,15 +1112,17 @@ mod tests {
                 punish_timelock: CSVTimelock::new(8),
                 fee_strategy: FeeStrategy::Fixed(SatPerVByte::from_sat(9)),
                 maker_role: SwapRole::Bob,
+                accordant_confirmations: 10,
             };
         let s = serde_yaml::to_string(&deal_params).expect("Encode deal in yaml");
         assert_eq!(
-            "---\nuuid: 67e55044-10b1-426f-9247-bb680e5fe0c8\nnetwork: Testnet\narbitrating_blockchain: Bitcoin\naccordant_blockchain: Monero\narbitrating_amount: 0.00000005 BTC\naccordant_amount: 0.000000000006 XMR\ncancel_timelock: 7\npunish_timelock: 8\nfee_strategy:\n  Fixed: 9 satoshi/vByte\nmaker_role: Bob\n",
+            "---\nuuid: 67e55044-10b1-426f-9247-bb680e5fe0c8\nnetwork: Testnet\narbitrating_blockchain: Bitcoin\naccordant_blockchain: Monero\narbitrating_amount: 0.00000005 BTC\naccordant_amount: 0.000000000006 XMR\ncancel_timelock: 7\npunish_timelock: 8\nfee_strategy:\n  Fixed: 9 satoshi/vByte\nmaker_role: Bob\naccordant_confirmations: 10\n",
             s
         );
     }
 
     #[test]
+    #[cfg(feature = "serde")]
     fn deserialize_deal_params_from_yaml() {
         let s = "---\nuuid: 67e55044-10b1-426f-9247-bb680e5fe0c8\nnetwork: Testnet\narbitrating_blockchain: Bitcoin\naccordant_blockchain: Monero\narbitrating_amount: 0.00000005 BTC\naccordant_amount: 0.000000000006 XMR\ncancel_timelock: 7\npunish_timelock: 8\nfee_strategy:\n  Fixed: 9 satoshi/vByte\nmaker_role: Bob\n";
         let deal_params = serde_yaml::from_str(&s).expect("Decode deal from yaml");
@@ -607,26 +1138,29 @@ mod tests {
                 punish_timelock: CSVTimelock::new(8),
                 fee_strategy: FeeStrategy::Fixed(SatPerVByte::from_sat(9)),
                 maker_role: SwapRole::Bob,
+                accordant_confirmations: 10,
             },
             deal_params
         );
     }
 
     #[test]
+    #[cfg(feature = "serde")]
     fn serialize_deal_in_yaml() {
         let deal =
             Deal::<bitcoin::Amount, monero::Amount, CSVTimelock, SatPerVByte>::from_str("Deal:Cke4ftrP5A7CRkYdGNd87TRU6sUP1kBKM1W723UjzEWsNR4gmBqNCsR11111uMFubBevJ2E5fp6ZR11111TBALTh113GTvtvqfD1111114A4TTfifktDH7QZD71vpdfo6EVo2ds7KviHz7vYbLZDkgsMNb11111111111111111111111111111111111111111AfZ113XRBuL3QS1m")
             .expect("Valid deal");
         let s = serde_yaml::to_string(&deal).expect("Encode deal in yaml");
         assert_eq!(
-            "---\nversion: 1\nparameters:\n  uuid: 67e55044-10b1-426f-9247-bb680e5fe0c8\n  network: Local\n  arbitrating_blockchain: Bitcoin\n  accordant_blockchain: Monero\n  arbitrating_amount: 0.00001350 BTC\n  accordant_amount: 1000000.001000000000 XMR\n  cancel_timelock: 4\n  punish_timelock: 6\n  fee_strategy:\n    Fixed: 1 satoshi/vByte\n  maker_role: Bob\nnode_id: 02e77b779cdc2c713823f7a19147a67e4209c74d77e2cb5045bce0584a6be064d4\npeer_address:\n  IPv4: \"127.0.0.1:9735\"\n",
+            "---\nversion: 1\nparameters:\n  uuid: 67e55044-10b1-426f-9247-bb680e5fe0c8\n  network: Local\n  arbitrating_blockchain: Bitcoin\n  accordant_blockchain: Monero\n  arbitrating_amount: 0.00001350 BTC\n  accordant_amount: 1000000.001000000000 XMR\n  cancel_timelock: 4\n  punish_timelock: 6\n  fee_strategy:\n    Fixed: 1 satoshi/vByte\n  maker_role: Bob\n  accordant_confirmations: 10\nnode_id: 02e77b779cdc2c713823f7a19147a67e4209c74d77e2cb5045bce0584a6be064d4\npeer_address:\n  IPv4: \"127.0.0.1:9735\"\n",
             s
         );
     }
 
     #[test]
+    #[cfg(feature = "serde")]
     fn deserialize_deal_from_yaml() {
-        let s = "---\nversion: 1\nparameters:\n  uuid: 67e55044-10b1-426f-9247-bb680e5fe0c8\n  network: Local\n  arbitrating_blockchain: Bitcoin\n  accordant_blockchain: Monero\n  arbitrating_amount: 0.00001350 BTC\n  accordant_amount: 1000000.001000000000 XMR\n  cancel_timelock: 4\n  punish_timelock: 6\n  fee_strategy:\n    Fixed: 1 satoshi/vByte\n  maker_role: Bob\nnode_id: 02e77b779cdc2c713823f7a19147a67e4209c74d77e2cb5045bce0584a6be064d4\npeer_address:\n  IPv4: \"127.0.0.1:9735\"\n";
+        let s = "---\nversion: 1\nparameters:\n  uuid: 67e55044-10b1-426f-9247-bb680e5fe0c8\n  network: Local\n  arbitrating_blockchain: Bitcoin\n  accordant_blockchain: Monero\n  arbitrating_amount: 0.00001350 BTC\n  accordant_amount: 1000000.001000000000 XMR\n  cancel_timelock: 4\n  punish_timelock: 6\n  fee_strategy:\n    Fixed: 1 satoshi/vByte\n  maker_role: Bob\n  accordant_confirmations: 10\nnode_id: 02e77b779cdc2c713823f7a19147a67e4209c74d77e2cb5045bce0584a6be064d4\npeer_address:\n  IPv4: \"127.0.0.1:9735\"\n";
         let deal = serde_yaml::from_str(&s).expect("Decode deal from yaml");
         assert_eq!(
             Deal::<bitcoin::Amount, monero::Amount, CSVTimelock, SatPerVByte>::from_str("Deal:Cke4ftrP5A7CRkYdGNd87TRU6sUP1kBKM1W723UjzEWsNR4gmBqNCsR11111uMFubBevJ2E5fp6ZR11111TBALTh113GTvtvqfD1111114A4TTfifktDH7QZD71vpdfo6EVo2ds7KviHz7vYbLZDkgsMNb11111111111111111111111111111111111111111AfZ113XRBuL3QS1m")
@@ -634,4 +1168,35 @@ mod tests {
             deal
         );
     }
+
+    #[test]
+    fn convert_amounts_with_price() {
+        let mut deal_params = DEAL_PARAMS.clone();
+        assert!(deal_params.price().is_some());
+        // 1 BTC is worth 163.25 XMR
+        let price = Price::new(
+            bitcoin::Amount::ONE_BTC,
+            monero::Amount::from_pico(163_250_000_000_000),
+        )
+        .unwrap();
+
+        deal_params.arbitrating_amount = bitcoin::Amount::from_sat(50_000_000);
+        deal_params.set_accordant_amount_at(&price).unwrap();
+        assert_eq!(
+            deal_params.accordant_amount,
+            monero::Amount::from_pico(81_625_000_000_000)
+        );
+        deal_params.set_arbitrating_amount_at(&price).unwrap();
+        assert_eq!(
+            deal_params.arbitrating_amount,
+            bitcoin::Amount::from_sat(50_000_000)
+        );
+
+        deal_params.arbitrating_amount = bitcoin::Amount::from_sat(u64::MAX);
+        assert!(matches!(
+            deal_params.set_accordant_amount_at(&price),
+            Err(Error::AmountOverflow)
+        ));
+        assert!(Price::new(bitcoin::Amount::ZERO, monero::Amount::ONE_XMR).is_none());
+    }
 }
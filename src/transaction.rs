@@ -17,14 +17,17 @@
 //! Arbitrating transaction traits used as the on-chain arbitration engine on the arbitrating
 //! blockchain. These traits define the steps allowed in the arbitration engine enforced on-chain.
 
+use std::cmp::Ordering;
 use std::error;
 use std::fmt::Debug;
 use std::io;
+use std::str::FromStr;
 
 use thiserror::Error;
 
-use crate::blockchain::Network;
-use crate::consensus::{self, Decodable, Encodable};
+use crate::blockchain::{Fee, FeePriority, FeeStrategy, Network};
+use crate::consensus::{self, CanonicalBytes, Decodable, Encodable};
+use crate::crypto::{self, ArbitratingKeyId, Sign};
 use crate::script::{DataLock, DataPunishableLock, ScriptPath};
 
 /// A list specifying general categories of transaction error.
@@ -39,6 +42,10 @@ pub enum Error {
     /// Missing witness data.
     #[error("Missing witness data")]
     MissingWitness,
+    /// Some inputs still miss signatures, see [`Witnessable::missing_witnesses`] for the keys
+    /// expected to sign them.
+    #[error("Missing signatures on inputs {0:?}")]
+    MissingSignatures(Vec<usize>),
     /// Missing network data.
     #[error("Missing network data")]
     MissingNetwork,
@@ -51,15 +58,40 @@ pub enum Error {
     /// The arbitrating targeted amount is invalid.
     #[error("The targeted amount is invalid")]
     InvalidTargetAmount,
+    /// The fee rate paid by the transaction is out of the fee strategy bounds.
+    #[error("The fee rate is out of the fee strategy bounds")]
+    FeeOutOfBounds,
     /// Not enough assets to create the transaction.
     #[error("Not enough assets to create the transaction")]
     NotEnoughAssets,
+    /// The same output is spent by several inputs of the transaction.
+    #[error("The same output is spent more than once")]
+    DuplicateInput,
     /// Wrong transaction template.
     #[error("Wrong transaction template: {0}")]
     WrongTemplate(&'static str),
+    /// A signature or an adaptor signature does not pass the validation for the transaction.
+    #[error("Invalid signature for the {0} transaction")]
+    InvalidSignature(TxLabel),
     /// The transaction chain validation failed
     #[error("The transaction chain validation failed")]
     InvalidTransactionChain,
+    /// A transaction is not built on top of the transaction it must spend.
+    #[error("The {tx} transaction is not built on top of the {prev} transaction")]
+    BrokenLink {
+        /// The label of the transaction spending the previous one.
+        tx: TxLabel,
+        /// The label of the transaction that must be spent.
+        prev: TxLabel,
+    },
+    /// A saved transaction is loaded as another transaction type.
+    #[error("Expected a saved {expected} transaction, found a {found} transaction")]
+    LabelMismatch {
+        /// The label of the transaction type being loaded.
+        expected: TxLabel,
+        /// The label of the saved transaction.
+        found: TxLabel,
+    },
     /// Any transaction error not part of this list.
     #[error("Transaction error: {0}")]
     Other(Box<dyn error::Error + Send + Sync>),
@@ -96,11 +128,13 @@ impl Error {
 /// use bitcoin::Amount;
 /// use bitcoin::util::psbt::PartiallySignedTransaction;
 /// use bitcoin::blockdata::transaction::TxIn;
-/// use farcaster_core::transaction::Transaction;
+/// use farcaster_core::transaction::{Transaction, TxLabel};
 ///
 /// pub struct MyTx(PartiallySignedTransaction);
 ///
 /// impl Transaction<PartiallySignedTransaction, TxIn, Amount> for MyTx {
+///     const LABEL: TxLabel = TxLabel::Lock;
+///
 ///     fn as_partial(&self) -> &PartiallySignedTransaction {
 ///         todo!()
 ///     }
@@ -127,6 +161,10 @@ impl Error {
 /// }
 /// ```
 pub trait Transaction<Px, Out, Amt> {
+    /// The Farcaster transaction identifier of the transaction type, stored when the transaction
+    /// is [`save`](Transaction::save)d.
+    const LABEL: TxLabel;
+
     /// Returns a reference to the inner partial transaction data.
     fn as_partial(&self) -> &Px;
 
@@ -145,16 +183,95 @@ pub trait Transaction<Px, Out, Amt> {
 
     /// Returns the output amount of the transaction.
     fn output_amount(&self) -> Amt;
+
+    /// Save the transaction, with the witnesses already added, in a [`SavedTransaction`] labeled
+    /// with [`Transaction::LABEL`] that can be persisted and later [`load`](Transaction::load)ed
+    /// to resume the protocol.
+    fn save(&self) -> SavedTransaction<Px>
+    where
+        Px: Clone,
+    {
+        SavedTransaction::new(Self::LABEL, self.as_partial().clone())
+    }
+
+    /// Restore a transaction previously [`save`](Transaction::save)d. Fails with
+    /// [`Error::LabelMismatch`] if the saved transaction is not of this transaction type.
+    fn load(saved: SavedTransaction<Px>) -> Result<Self, Error>
+    where
+        Self: Sized,
+    {
+        if saved.label != Self::LABEL {
+            return Err(Error::LabelMismatch {
+                expected: Self::LABEL,
+                found: saved.label,
+            });
+        }
+        Ok(Self::from_partial(saved.partial))
+    }
+}
+
+/// Versioned container holding everything needed to reconstruct an arbitrating transaction in
+/// the middle of a swap, e.g. after a daemon restart. Witnesses added with
+/// [`Witnessable::add_witness`] are stored inside the partial transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SavedTransaction<Px> {
+    /// The transaction label.
+    pub label: TxLabel,
+    /// The partial transaction, with its collected witnesses.
+    pub partial: Px,
+}
+
+impl<Px> SavedTransaction<Px> {
+    /// Current version of the saved transaction encoding.
+    pub const VERSION: u16 = 1;
+
+    /// Create a new saved transaction from its label and partial transaction.
+    pub fn new(label: TxLabel, partial: Px) -> Self {
+        Self { label, partial }
+    }
+}
+
+impl<Px> Encodable for SavedTransaction<Px>
+where
+    Px: CanonicalBytes,
+{
+    fn consensus_encode<W: io::Write>(&self, writer: &mut W) -> Result<usize, io::Error> {
+        let mut len = Self::VERSION.consensus_encode(writer)?;
+        len += self.label.consensus_encode(writer)?;
+        Ok(len + self.partial.as_canonical_bytes().consensus_encode(writer)?)
+    }
+}
+
+impl<Px> Decodable for SavedTransaction<Px>
+where
+    Px: CanonicalBytes,
+{
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        let version: u16 = Decodable::consensus_decode(d)?;
+        if version != Self::VERSION {
+            return Err(consensus::Error::ParseFailed(
+                "Unsupported saved transaction version",
+            ));
+        }
+        Ok(Self {
+            label: Decodable::consensus_decode(d)?,
+            partial: Px::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?,
+        })
+    }
 }
 
-/// Defines the transaction Farcaster IDs for serialization and network communication.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display, Serialize, Deserialize)]
+impl_strict_encoding!(SavedTransaction<Px>, Px: CanonicalBytes);
+
+/// Defines the transaction Farcaster IDs for serialization and network communication. Labels are
+/// displayed as lowercase names, e.g. `lock` or `cancel`, and parsed back case-insensitively.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[display(lowercase)]
 pub enum TxLabel {
     /// Represents the first transaction created outside of the system by an external wallet to
     /// fund the swap on the arbitrating blockchain.
     Funding,
     /// Represents the core locking arbitrating transaction.
-    #[display("Arbitrating Lock")]
     Lock,
     /// Represents the happy path for swapping the assets.
     Buy,
@@ -166,24 +283,81 @@ pub enum TxLabel {
     /// didn't act accordingly to the protocol.
     Punish,
     /// Represents the accordant lock transaction
-    #[display("Accordant Lock")]
     AccLock,
+    /// Represents a plain spend of a completed swap output, e.g. the output of `buy (c)` or
+    /// `refund (e)`, to another address.
+    Sweep,
 }
 
-impl Encodable for TxLabel {
-    fn consensus_encode<W: io::Write>(&self, writer: &mut W) -> Result<usize, io::Error> {
+impl TxLabel {
+    /// All the transaction labels, in consensus tag order.
+    pub const ALL: [TxLabel; 8] = [
+        TxLabel::Funding,
+        TxLabel::Lock,
+        TxLabel::Buy,
+        TxLabel::Cancel,
+        TxLabel::Refund,
+        TxLabel::Punish,
+        TxLabel::AccLock,
+        TxLabel::Sweep,
+    ];
+
+    // Consensus tag of the label, also used for ordering labels.
+    fn tag(&self) -> u16 {
         match self {
-            TxLabel::Funding => 0x01u16.consensus_encode(writer),
-            TxLabel::Lock => 0x02u16.consensus_encode(writer),
-            TxLabel::Buy => 0x03u16.consensus_encode(writer),
-            TxLabel::Cancel => 0x04u16.consensus_encode(writer),
-            TxLabel::Refund => 0x05u16.consensus_encode(writer),
-            TxLabel::Punish => 0x06u16.consensus_encode(writer),
-            TxLabel::AccLock => 0x07u16.consensus_encode(writer),
+            TxLabel::Funding => 0x01,
+            TxLabel::Lock => 0x02,
+            TxLabel::Buy => 0x03,
+            TxLabel::Cancel => 0x04,
+            TxLabel::Refund => 0x05,
+            TxLabel::Punish => 0x06,
+            TxLabel::AccLock => 0x07,
+            TxLabel::Sweep => 0x08,
         }
     }
 }
 
+/// Labels are ordered by their consensus tag, which follows the protocol sequence: `funding`,
+/// `lock`, then `buy` and `cancel`, then `refund` and `punish`. `buy` and `cancel`, as well as
+/// `refund` and `punish`, are alternatives spending the same output, they are not sequential and
+/// are ordered by tag only to get a total order. `acclock`, on the accordant blockchain, and
+/// `sweep`, spending the output of a completed swap, come last.
+impl PartialOrd for TxLabel {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for TxLabel {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.tag().cmp(&other.tag())
+    }
+}
+
+impl FromStr for TxLabel {
+    type Err = consensus::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "funding" => Ok(TxLabel::Funding),
+            "lock" => Ok(TxLabel::Lock),
+            "buy" => Ok(TxLabel::Buy),
+            "cancel" => Ok(TxLabel::Cancel),
+            "refund" => Ok(TxLabel::Refund),
+            "punish" => Ok(TxLabel::Punish),
+            "acclock" => Ok(TxLabel::AccLock),
+            "sweep" => Ok(TxLabel::Sweep),
+            _ => Err(consensus::Error::UnknownType),
+        }
+    }
+}
+
+impl Encodable for TxLabel {
+    fn consensus_encode<W: io::Write>(&self, writer: &mut W) -> Result<usize, io::Error> {
+        self.tag().consensus_encode(writer)
+    }
+}
+
 impl Decodable for TxLabel {
     fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
         match Decodable::consensus_decode(d)? {
@@ -194,6 +368,7 @@ impl Decodable for TxLabel {
             0x05u16 => Ok(TxLabel::Refund),
             0x06u16 => Ok(TxLabel::Punish),
             0x07u16 => Ok(TxLabel::AccLock),
+            0x08u16 => Ok(TxLabel::Sweep),
             _ => Err(consensus::Error::UnknownType),
         }
     }
@@ -210,6 +385,11 @@ pub trait Witnessable<Ms, Pk, Si> {
 
     /// Add a cooperation to the transaction and store it internally for later usage.
     fn add_witness(&mut self, pubkey: Pk, sig: Si) -> Result<(), Error>;
+
+    /// List the signatures still required to finalize the transaction, as the index of the input
+    /// and the public key expected to sign it, in input order. Empty when every known signature
+    /// is present.
+    fn missing_witnesses(&self) -> Result<Vec<(usize, Pk)>, Error>;
 }
 
 /// Define a transaction that must have a finalization step.
@@ -275,10 +455,47 @@ where
     }
 }
 
+/// Verifies that the core arbitrating transactions form a consistent chain: `cancel (d)` builds on
+/// top of `lock (b)`, `refund (e)` on top of `cancel (d)`, and, if provided, `buy (c)` on top of
+/// `lock (b)`. Links are checked in this order and the first broken one is reported with
+/// [`Error::BrokenLink`].
+pub fn verify_chain<Px, Out, Amt, L, C, R, B>(
+    lock: &L,
+    cancel: &C,
+    refund: &R,
+    buy: Option<&B>,
+) -> Result<(), Error>
+where
+    Out: Eq,
+    L: Transaction<Px, Out, Amt> + Linkable<Out>,
+    C: Transaction<Px, Out, Amt> + Linkable<Out>,
+    R: Transaction<Px, Out, Amt>,
+    B: Transaction<Px, Out, Amt>,
+{
+    let link = |res, tx, prev| match res {
+        Err(Error::InvalidTransactionChain) => Err(Error::BrokenLink { tx, prev }),
+        res => res,
+    };
+    link(
+        cancel.is_build_on_top_of(lock),
+        TxLabel::Cancel,
+        TxLabel::Lock,
+    )?;
+    link(
+        refund.is_build_on_top_of(cancel),
+        TxLabel::Refund,
+        TxLabel::Cancel,
+    )?;
+    if let Some(buy) = buy {
+        link(buy.is_build_on_top_of(lock), TxLabel::Buy, TxLabel::Lock)?;
+    }
+    Ok(())
+}
+
 /// Fundable is NOT a transaction generated by this library but the funds that arrived in the
 /// generated address are controlled by the system. This trait allows to inject assets in the
 /// system.
-pub trait Fundable<Tx, Out, Addr, Pk>: Linkable<Out> {
+pub trait Fundable<Tx, Out, Addr, Pk, Amt>: Linkable<Out> {
     /// Create a new funding 'output', or equivalent depending on the blockchain and the
     /// cryptographic engine.
     fn initialize(pubkey: Pk, network: Network) -> Result<Self, Error>
@@ -298,6 +515,12 @@ pub trait Fundable<Tx, Out, Addr, Pk>: Linkable<Out> {
     /// Boolean indicating whether the transaction was seen
     fn was_seen(&self) -> bool;
 
+    /// Verifies that the transaction seen on-chain pays the expected address with at least the
+    /// expected amount, to be checked before building on top of the funding. Returns
+    /// [`Error::WrongTemplate`] on mismatch and [`Error::MissingOnchainTransaction`] if the
+    /// transaction has not been seen yet.
+    fn verify_funding(&self, expected_address: &Addr, expected_amount: Amt) -> Result<(), Error>;
+
     /// Create a raw funding structure based only on the transaction seen on-chain.
     fn raw(tx: Tx) -> Result<Self, Error>
     where
@@ -327,17 +550,50 @@ pub trait Lockable<Addr, Tx, Px, Out, Amt, Ti, Ms, Pk, Si>:
     /// validated against the freshly created transaction to ensure that fee is valid for the
     /// transaction. The initialization must return an error if the amount is insufficient.
     ///
+    /// # Metadata
+    ///
+    /// Optional metadata, e.g. a commitment to the swap identifier, can be embedded in the
+    /// transaction for later audit. The initialization must return [`Error::WrongTemplate`] if the
+    /// metadata cannot be relayed by the blockchain network.
+    ///
     fn initialize(
-        prev: &impl Fundable<Tx, Out, Addr, Pk>,
+        prev: &impl Fundable<Tx, Out, Addr, Pk, Amt>,
         lock: DataLock<Ti, Pk>,
         target_amount: Amt,
+        metadata: Option<&[u8]>,
     ) -> Result<Self, Error>
     where
+        Self: Sized,
+    {
+        Self::initialize_with_inputs(std::slice::from_ref(prev), lock, target_amount, metadata)
+    }
+
+    /// Creates a new `lock (b)` transaction spending all the provided `funding (a)` outputs. The
+    /// sum of the funding amounts is checked against the target amount, the initialization must
+    /// return [`Error::NotEnoughAssets`] if the total is insufficient or if no funding is provided,
+    /// and [`Error::DuplicateInput`] if the same funding output is provided more than once.
+    fn initialize_with_inputs<F>(
+        prevs: &[F],
+        lock: DataLock<Ti, Pk>,
+        target_amount: Amt,
+        metadata: Option<&[u8]>,
+    ) -> Result<Self, Error>
+    where
+        F: Fundable<Tx, Out, Addr, Pk, Amt>,
         Self: Sized;
 
-    /// Verifies that the transaction is compliant with the protocol requirements and implements
-    /// the correct conditions of the [`DataLock`].
-    fn verify_template(&self, lock: DataLock<Ti, Pk>) -> Result<(), Error>;
+    /// Verifies that the transaction is compliant with the protocol requirements, implements the
+    /// correct conditions of the [`DataLock`], and pays a fee rate within the fee strategy bounds,
+    /// see [`Fee::validate_rate`]. The transaction must carry exactly the expected `metadata`, or
+    /// no metadata if `None`, otherwise fails with [`Error::WrongTemplate`].
+    fn verify_template(
+        &self,
+        lock: DataLock<Ti, Pk>,
+        metadata: Option<&[u8]>,
+        fee_strategy: &FeeStrategy<<Px as Fee>::FeeUnit>,
+    ) -> Result<(), Error>
+    where
+        Px: Fee;
 
     // TODO this could be moved to transaction directly
     /// Verifies that the available output amount in lock is equal to the target amount.
@@ -384,13 +640,25 @@ where
     where
         Self: Sized;
 
-    /// Verifies that the transaction is compliant with the protocol requirements and implements
-    /// the correct conditions of the [`DataLock`] and the destination address.
-    fn verify_template(&self, destination_target: Addr) -> Result<(), Error>;
+    /// Verifies that the transaction is compliant with the protocol requirements, implements the
+    /// correct conditions of the [`DataLock`] and the destination address, and pays a fee rate
+    /// within the fee strategy bounds, see [`Fee::validate_rate`].
+    fn verify_template(
+        &self,
+        destination_target: Addr,
+        fee_strategy: &FeeStrategy<<Px as Fee>::FeeUnit>,
+    ) -> Result<(), Error>
+    where
+        Px: Fee;
 
     /// Extract the valuable witness from a transaction.
     fn extract_witness(tx: Tx) -> Si;
 
+    /// Extract the valuable witness from an on-chain transaction after checking that it is the
+    /// finalized version of this transaction. Fails with [`Error::MissingWitness`] if the
+    /// transaction is not this `buy (c)` or does not carry the expected witness.
+    fn extract_onchain_witness(&self, tx: Tx) -> Result<Si, Error>;
+
     /// Return the Farcaster transaction identifier.
     fn get_label(&self) -> TxLabel {
         TxLabel::Buy
@@ -424,13 +692,17 @@ where
     where
         Self: Sized;
 
-    /// Verifies that the transaction is compliant with the protocol requirements and implements
-    /// the correct conditions of the [`DataLock`] and the [`DataPunishableLock`].
+    /// Verifies that the transaction is compliant with the protocol requirements, implements the
+    /// correct conditions of the [`DataLock`] and the [`DataPunishableLock`], and pays a fee rate
+    /// within the fee strategy bounds, see [`Fee::validate_rate`].
     fn verify_template(
         &self,
         lock: DataLock<Ti, Pk>,
         punish_lock: DataPunishableLock<Ti, Pk>,
-    ) -> Result<(), Error>;
+        fee_strategy: &FeeStrategy<<Px as Fee>::FeeUnit>,
+    ) -> Result<(), Error>
+    where
+        Px: Fee;
 
     /// Return the Farcaster transaction identifier.
     fn get_label(&self) -> TxLabel {
@@ -463,9 +735,16 @@ where
     where
         Self: Sized;
 
-    /// Verifies that the transaction is compliant with the protocol requirements and implements
-    /// the correct conditions of the [`DataPunishableLock`] and the refund address.
-    fn verify_template(&self, refund_target: Addr) -> Result<(), Error>;
+    /// Verifies that the transaction is compliant with the protocol requirements, implements the
+    /// correct conditions of the [`DataPunishableLock`] and the refund address, and pays a fee
+    /// rate within the fee strategy bounds, see [`Fee::validate_rate`].
+    fn verify_template(
+        &self,
+        refund_target: Addr,
+        fee_strategy: &FeeStrategy<<Px as Fee>::FeeUnit>,
+    ) -> Result<(), Error>
+    where
+        Px: Fee;
 
     /// Extract the valuable witness from a transaction.
     fn extract_witness(tx: Tx) -> Si;
@@ -509,8 +788,119 @@ where
     where
         Self: Sized;
 
+    /// Generate the witness message for the failure path of the punishable lock and sign it with
+    /// the [`ArbitratingKeyId::Punish`] key, allowing the `punish (f)` transaction to be completed
+    /// unilaterally once the punish timelock expires. Returns [`Error::MissingPublicKey`] if the
+    /// punish key is not available in the wallet.
+    fn generate_punish_witness<S>(&self, wallet: &mut S) -> Result<Si, Error>
+    where
+        S: Sign<Pk, Ms, Si>,
+    {
+        let msg = self.generate_witness_message(ScriptPath::Failure)?;
+        wallet
+            .sign(ArbitratingKeyId::Punish, msg)
+            .map_err(|e| match e {
+                crypto::Error::MissingKey | crypto::Error::UnsupportedKey => {
+                    Error::MissingPublicKey
+                }
+                e => Error::new(e),
+            })
+    }
+
     /// Return the Farcaster transaction identifier.
     fn get_label(&self) -> TxLabel {
         TxLabel::Punish
     }
 }
+
+/// Represent a sweepable transaction, a plain spend of the output of a completed swap, such as
+/// the output of `buy (c)` or `refund (e)`, transferring the funds to another destination, e.g. a
+/// cold wallet, in a single input and single output transaction paying its own fee.
+///
+/// The sweep is not part of the protocol, it is created and signed unilaterally by the owner of
+/// the swept output.
+pub trait Sweepable<Addr, Tx, Px, Out, Amt, Fu, Ms, Pk, Si>:
+    Transaction<Px, Out, Amt>
+    + Broadcastable<Tx>
+    + Linkable<Out>
+    + Witnessable<Ms, Pk, Si>
+    + Chainable<Px, Out, Amt>
+where
+    Out: Eq,
+{
+    /// Creates a new sweep transaction spending the consumable output of `prev`, locked to
+    /// `signing_key`, and sending the funds minus the fee set with the fee strategy and priority
+    /// to the destination address.
+    ///
+    /// This correspond to the "creator" and initial "updater" roles in BIP 174. Creates a new
+    /// transaction and fill the inputs and outputs data.
+    fn initialize(
+        prev: &impl Linkable<Out>,
+        signing_key: Pk,
+        destination_target: Addr,
+        fee_strategy: &FeeStrategy<Fu>,
+        fee_politic: FeePriority,
+    ) -> Result<Self, Error>
+    where
+        Self: Sized;
+
+    /// Verifies that the transaction is a single input and single output transaction sending the
+    /// funds to the destination address, and pays a fee rate within the fee strategy bounds, see
+    /// [`Fee::validate_rate`].
+    fn verify_template(
+        &self,
+        destination_target: Addr,
+        fee_strategy: &FeeStrategy<Fu>,
+    ) -> Result<(), Error>;
+
+    /// Return the Farcaster transaction identifier.
+    fn get_label(&self) -> TxLabel {
+        TxLabel::Sweep
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consensus::serialize;
+
+    #[test]
+    fn tx_label_display_and_parse() {
+        for (i, label) in TxLabel::ALL.iter().enumerate() {
+            let name = label.to_string();
+            assert_eq!(name, name.to_lowercase());
+            assert_eq!(&TxLabel::from_str(&name).unwrap(), label);
+            assert_eq!(&TxLabel::from_str(&name.to_uppercase()).unwrap(), label);
+            // Labels are listed in consensus tag order
+            assert_eq!(serialize(label), serialize(&(i as u16 + 1)));
+        }
+        assert_eq!(TxLabel::Cancel.to_string(), "cancel");
+        assert_eq!(TxLabel::AccLock.to_string(), "acclock");
+        assert!(TxLabel::from_str("unknown").is_err());
+    }
+
+    #[test]
+    fn tx_labels_sort_in_protocol_order() {
+        let mut labels = vec![
+            TxLabel::Punish,
+            TxLabel::Sweep,
+            TxLabel::AccLock,
+            TxLabel::Cancel,
+            TxLabel::Funding,
+            TxLabel::Refund,
+            TxLabel::Buy,
+            TxLabel::Lock,
+        ];
+        labels.sort();
+        assert_eq!(labels, TxLabel::ALL);
+        assert!(TxLabel::Lock < TxLabel::Cancel);
+        assert!(TxLabel::Cancel < TxLabel::Refund);
+
+        let map: std::collections::BTreeMap<_, _> = TxLabel::ALL
+            .iter()
+            .rev()
+            .map(|label| (*label, ()))
+            .collect();
+        assert_eq!(map.keys().copied().collect::<Vec<_>>(), TxLabel::ALL);
+    }
+}
@@ -15,20 +15,26 @@
 // Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301, USA
 
 use farcaster_core::bitcoin::segwitv0::{BuyTx, CancelTx, FundingTx, LockTx, PunishTx, RefundTx};
+use farcaster_core::bitcoin::timelock::CSVTimelock;
 use farcaster_core::bitcoin::BitcoinSegwitV0 as Btc;
 use farcaster_core::monero::Monero as Xmr;
 use farcaster_core::swap::btcxmr::KeyManager;
 
 use farcaster_core::blockchain::{FeePriority, Network};
-use farcaster_core::consensus::deserialize;
+use farcaster_core::consensus::{deserialize, serialize};
+use farcaster_core::crypto::dleq::DLEQProof;
 use farcaster_core::crypto::KeccakCommitment;
 use farcaster_core::crypto::{
-    ArbitratingKeyId, CommitmentEngine, GenerateKey, ProveCrossGroupDleq,
+    ArbitratingKeyId, Commit, CommitmentEngine, GenerateKey, ProveCrossGroupDleq,
 };
+use farcaster_core::protocol::bundle::{AliceCommitRevealBundle, CommitRevealBundle};
 use farcaster_core::protocol::message::*;
+use farcaster_core::role::SwapRole;
 use farcaster_core::swap::btcxmr::{Alice, Bob, Deal, Parameters};
 use farcaster_core::swap::SwapId;
+use farcaster_core::trade;
 use farcaster_core::transaction::*;
+use farcaster_core::Error;
 
 use bitcoin::blockdata::transaction::{OutPoint, TxIn, TxOut};
 use bitcoin::blockdata::witness::Witness;
@@ -40,6 +46,7 @@ use ecdsa_fun::adaptor::EncryptedSignature;
 
 use monero::{PrivateKey as MPriv, PublicKey as MPub};
 
+use std::convert::TryFrom;
 use std::str::FromStr;
 
 macro_rules! test_strict_ser {
@@ -47,6 +54,10 @@ macro_rules! test_strict_ser {
         let strict_ser = strict_encoding::strict_serialize(&$var).unwrap();
         let res: Result<$type, _> = strict_encoding::strict_deserialize(&strict_ser);
         assert!(res.is_ok());
+        assert_eq!(
+            farcaster_core::consensus::Encodable::serialized_len(&$var).unwrap(),
+            farcaster_core::consensus::serialize(&$var).len()
+        );
     };
 }
 
@@ -67,6 +78,246 @@ fn init() -> (Alice, Bob, Deal) {
     (alice, bob, deal)
 }
 
+#[test]
+fn verify_parameters_against_deal() {
+    let (alice, _, deal) = init();
+    let mut alice_key_manager = KeyManager::new([1; 32], 1).unwrap();
+    let mut alice_params: Parameters = alice
+        .generate_parameters(&mut alice_key_manager, &deal)
+        .unwrap();
+
+    // Mainnet destination address against a testnet deal
+    assert!(matches!(
+        alice_params.verify_against_deal(&deal),
+        Err(Error::Trade(trade::Error::NetworkMismatch {
+            expected: Network::Testnet,
+            found: Network::Mainnet,
+        }))
+    ));
+
+    alice_params.destination_address.network = bitcoin::Network::Testnet;
+    assert!(alice_params.verify_against_deal(&deal).is_ok());
+
+    alice_params.punish_timelock = Some(CSVTimelock::new(1));
+    assert!(matches!(
+        alice_params.verify_against_deal(&deal),
+        Err(Error::Trade(trade::Error::TimelockMismatch("punish")))
+    ));
+}
+
+#[test]
+fn merge_deal_into_revealed_parameters() {
+    let (alice, _, deal) = init();
+    let mut alice_key_manager = KeyManager::new([1; 32], 1).unwrap();
+    let alice_params: Parameters = alice
+        .generate_parameters(&mut alice_key_manager, &deal)
+        .unwrap();
+
+    let mut params: Parameters = alice_params
+        .reveal_alice(SwapId::random())
+        .into_parameters();
+    assert!(params.cancel_timelock.is_none());
+    assert!(params.punish_timelock.is_none());
+    assert!(params.fee_strategy.is_none());
+
+    params.merge_deal(&deal).unwrap();
+    assert_eq!(
+        params.cancel_timelock,
+        Some(deal.parameters.cancel_timelock)
+    );
+    assert_eq!(
+        params.punish_timelock,
+        Some(deal.parameters.punish_timelock)
+    );
+    assert_eq!(params.fee_strategy, Some(deal.parameters.fee_strategy));
+
+    // Merging twice is idempotent
+    assert!(params.merge_deal(&deal).is_ok());
+
+    // Conflicting values are rejected and left untouched
+    params.fee_strategy = None;
+    params.cancel_timelock = Some(CSVTimelock::new(1));
+    assert!(matches!(
+        params.merge_deal(&deal),
+        Err(Error::Trade(trade::Error::TimelockMismatch("cancel")))
+    ));
+    assert!(params.fee_strategy.is_none());
+}
+
+#[test]
+fn verify_revealed_proof_against_revealed_keys() {
+    let (alice, bob, deal) = init();
+    let swap_id = SwapId::random();
+    let mut alice_key_manager = KeyManager::new([1; 32], 1).unwrap();
+    let mut bob_key_manager = KeyManager::new([2; 32], 1).unwrap();
+    let alice_params: Parameters = alice
+        .generate_parameters(&mut alice_key_manager, &deal)
+        .unwrap();
+    let bob_params: Parameters = bob
+        .generate_parameters(&mut bob_key_manager, &deal)
+        .unwrap();
+
+    let alice_proof = RevealProof {
+        swap_id,
+        proof: alice_params.proof.clone().unwrap(),
+    };
+    let bob_proof = RevealProof {
+        swap_id,
+        proof: bob_params.proof.clone().unwrap(),
+    };
+    let reveal_alice = alice_params.reveal_alice(swap_id);
+    let reveal_bob = bob_params.reveal_bob(swap_id);
+    assert!(reveal_alice
+        .verify_proof(&mut bob_key_manager, &alice_proof)
+        .is_ok());
+    assert!(reveal_bob
+        .verify_proof(&mut alice_key_manager, &bob_proof)
+        .is_ok());
+
+    // A valid proof over a different adaptor key is rejected
+    let mut other_reveal = reveal_alice.clone();
+    other_reveal.adaptor = reveal_bob.adaptor;
+    assert!(matches!(
+        other_reveal.verify_proof(&mut bob_key_manager, &alice_proof),
+        Err(Error::Crypto(_))
+    ));
+    // Another participant's proof is rejected
+    assert!(reveal_alice
+        .verify_proof(&mut bob_key_manager, &bob_proof)
+        .is_err());
+
+    // The proof must relate to the same swap
+    let other_swap_proof = RevealProof {
+        swap_id: SwapId::random(),
+        ..alice_proof
+    };
+    assert!(matches!(
+        reveal_alice.verify_proof(&mut bob_key_manager, &other_swap_proof),
+        Err(Error::SwapIdMismatch { expected, .. }) if expected == swap_id
+    ));
+}
+
+#[test]
+fn validate_complete_parameters() {
+    let (alice, bob, deal) = init();
+    let mut alice_key_manager = KeyManager::new([1; 32], 1).unwrap();
+    let mut bob_key_manager = KeyManager::new([2; 32], 1).unwrap();
+    let alice_params: Parameters = alice
+        .generate_parameters(&mut alice_key_manager, &deal)
+        .unwrap();
+    let mut bob_params: Parameters = bob
+        .generate_parameters(&mut bob_key_manager, &deal)
+        .unwrap();
+    assert!(alice_params.validate_complete(SwapRole::Alice).is_ok());
+    assert!(bob_params.validate_complete(SwapRole::Bob).is_ok());
+
+    // Bob's parameters accidentally carrying a punish key
+    bob_params.punish = alice_params.punish;
+    assert!(matches!(
+        bob_params.validate_complete(SwapRole::Bob),
+        Err(Error::UnexpectedParameter("punish"))
+    ));
+
+    // Parameters received through reveal messages are not merged with the deal yet
+    let reveal: Parameters = alice_params
+        .reveal_alice(SwapId::random())
+        .into_parameters();
+    assert!(matches!(
+        reveal.validate_complete(SwapRole::Alice),
+        Err(Error::MissingParameter("proof"))
+    ));
+}
+
+#[test]
+fn generic_commit_parameters_encode_as_role_specific() {
+    let (alice, bob, deal) = init();
+    let mut alice_key_manager = KeyManager::new([1; 32], 1).unwrap();
+    let mut bob_key_manager = KeyManager::new([2; 32], 1).unwrap();
+    let alice_params: Parameters = alice
+        .generate_parameters(&mut alice_key_manager, &deal)
+        .unwrap();
+    let bob_params: Parameters = bob
+        .generate_parameters(&mut bob_key_manager, &deal)
+        .unwrap();
+    let swap_id = SwapId::random();
+
+    let commit_alice = alice_params.commit_alice(swap_id, &CommitmentEngine);
+    let commit = alice_params.commit(swap_id, &CommitmentEngine);
+    assert_eq!(commit.role(), SwapRole::Alice);
+    assert_eq!(serialize(&commit), serialize(&commit_alice));
+    assert_eq!(CommitParameters::from(commit_alice.clone()), commit);
+    assert_eq!(
+        CommitAliceParameters::try_from(commit.clone()).unwrap(),
+        commit_alice
+    );
+    assert!(matches!(
+        CommitBobParameters::try_from(commit),
+        Err(Error::UnexpectedParameter("punish"))
+    ));
+
+    let commit_bob = bob_params.commit_bob(swap_id, &CommitmentEngine);
+    let commit = bob_params.commit(swap_id, &CommitmentEngine);
+    assert_eq!(commit.role(), SwapRole::Bob);
+    assert_eq!(serialize(&commit), serialize(&commit_bob));
+    assert_eq!(CommitParameters::from(commit_bob.clone()), commit);
+    assert_eq!(
+        CommitBobParameters::try_from(commit.clone()).unwrap(),
+        commit_bob
+    );
+    assert!(matches!(
+        CommitAliceParameters::try_from(commit),
+        Err(Error::MissingParameter("punish"))
+    ));
+}
+
+// Commitment engine failing the test if any commitment is computed
+struct NoCommit;
+
+impl Commit<KeccakCommitment> for NoCommit {
+    fn commit_to<T: AsRef<[u8]>>(&self, _value: T) -> KeccakCommitment {
+        panic!("No commitment must be computed")
+    }
+}
+
+#[test]
+fn pair_commit_with_reveal() {
+    let (alice, bob, deal) = init();
+    let mut alice_key_manager = KeyManager::new([1; 32], 1).unwrap();
+    let mut bob_key_manager = KeyManager::new([2; 32], 1).unwrap();
+    let alice_params: Parameters = alice
+        .generate_parameters(&mut alice_key_manager, &deal)
+        .unwrap();
+    let bob_params: Parameters = bob
+        .generate_parameters(&mut bob_key_manager, &deal)
+        .unwrap();
+    let swap_id = SwapId::random();
+    let other_swap_id = SwapId::random();
+
+    let commit_alice = alice_params.commit_alice(swap_id, &CommitmentEngine);
+    let reveal_alice = alice_params.clone().reveal_alice(swap_id);
+    let verified = commit_alice
+        .pair_with(&CommitmentEngine, reveal_alice.clone())
+        .unwrap();
+    assert_eq!(verified.reveal(), &reveal_alice);
+    let revealed: Parameters = verified.into_parameters();
+    assert_eq!(revealed.punish, alice_params.punish);
+
+    // Swap ids are checked before any commitment is verified
+    assert!(matches!(
+        commit_alice.pair_with(&NoCommit, alice_params.reveal_alice(other_swap_id)),
+        Err(Error::SwapIdMismatch { expected, found }) if expected == swap_id && found == other_swap_id
+    ));
+
+    let commit_bob = bob_params.commit_bob(swap_id, &CommitmentEngine);
+    assert!(commit_bob
+        .pair_with(&CommitmentEngine, bob_params.clone().reveal_bob(swap_id))
+        .is_ok());
+    assert!(matches!(
+        commit_bob.pair_with(&NoCommit, bob_params.reveal_bob(other_swap_id)),
+        Err(Error::SwapIdMismatch { .. })
+    ));
+}
+
 #[test]
 fn execute_offline_protocol() {
     let (alice, bob, deal) = init();
@@ -121,6 +372,47 @@ fn execute_offline_protocol() {
         .verify_with_reveal(&commitment_engine, reveal_bob_params.clone())
         .is_ok());
 
+    // Bundle the commit/reveal step
+    let mut bundle: AliceCommitRevealBundle<
+        KeccakCommitment,
+        BPub,
+        MPub,
+        BPriv,
+        MPriv,
+        Address,
+        _,
+    > = CommitRevealBundle {
+        commit: commit_alice_params.clone(),
+        reveal: reveal_alice_params.clone(),
+        proof: RevealProof {
+            swap_id,
+            proof: alice_params.proof.clone().unwrap(),
+        },
+    };
+    assert!(bundle.validate().is_ok());
+    test_strict_ser!(bundle, AliceCommitRevealBundle<KeccakCommitment, BPub, MPub, BPriv, MPriv, Address, DLEQProof>);
+    bundle.proof.swap_id = SwapId::random();
+    assert!(matches!(
+        bundle.validate(),
+        Err(Error::SwapIdMismatch { expected, .. }) if expected == swap_id
+    ));
+
+    let revealed_alice_params: Parameters = commit_alice_params
+        .verify_into_parameters(&commitment_engine, reveal_alice_params.clone())
+        .unwrap();
+    assert_eq!(revealed_alice_params.buy, alice_params.buy);
+    assert_eq!(revealed_alice_params.punish, alice_params.punish);
+    let revealed_bob_params: Parameters = commit_bob_params
+        .verify_into_parameters(&commitment_engine, reveal_bob_params.clone())
+        .unwrap();
+    assert_eq!(revealed_bob_params.spend, bob_params.spend);
+    // Cannot verify a reveal against the commitment of the other participant
+    let mut other_params = bob_params.clone();
+    other_params.punish = alice_params.punish;
+    let res: Result<Parameters, _> = commit_alice_params
+        .verify_into_parameters(&commitment_engine, other_params.reveal_alice(swap_id));
+    assert!(res.is_err());
+
     //
     // Get Funding Address and Transaction
     //
@@ -138,11 +430,87 @@ fn execute_offline_protocol() {
             witness: Witness::new(),
         }],
         output: vec![TxOut {
-            value: 123456789,
+            // The deal arbitrating amount plus the lock fee at 20 sat/vByte
+            value: 107520,
             script_pubkey: funding_address.script_pubkey(),
         }],
     };
 
+    // A funding paying less than the deal arbitrating amount is rejected
+    let mut underfunded = funding.clone();
+    let mut underfunded_tx = funding_tx.clone();
+    underfunded_tx.output[0].value = 99999;
+    underfunded.update(underfunded_tx).unwrap();
+    assert!(matches!(
+        bob.core_arbitrating_transactions(
+            &alice_params,
+            &bob_params,
+            underfunded,
+            deal.to_arbitrating_params(),
+        ),
+        Err(Error::Transaction(
+            farcaster_core::transaction::Error::WrongTemplate(_)
+        ))
+    ));
+
+    // A lock paying a fee rate outside of the deal strategy is rejected
+    let mut overpaid = funding.clone();
+    let mut overpaid_tx = funding_tx.clone();
+    overpaid_tx.output[0].value = 123456789;
+    overpaid.update(overpaid_tx).unwrap();
+    assert!(matches!(
+        bob.core_arbitrating_transactions(
+            &alice_params,
+            &bob_params,
+            overpaid,
+            deal.to_arbitrating_params(),
+        ),
+        Err(Error::Transaction(
+            farcaster_core::transaction::Error::FeeOutOfBounds
+        ))
+    ));
+
+    // The lock carries the metadata agreed in the arbitrating parameters
+    let mut with_metadata = funding.clone();
+    let mut with_metadata_tx = funding_tx.clone();
+    // The metadata output increases the lock fee at 20 sat/vByte
+    with_metadata_tx.output[0].value = 110960;
+    with_metadata.update(with_metadata_tx).unwrap();
+    let arb_params = deal
+        .to_arbitrating_params()
+        .with_lock_metadata(&swap_id[..]);
+    let core_with_metadata = bob
+        .core_arbitrating_transactions(
+            &alice_params,
+            &bob_params,
+            with_metadata,
+            arb_params.clone(),
+        )
+        .unwrap();
+    let lock = LockTx::from_partial(core_with_metadata.lock.clone());
+    assert_eq!(lock.metadata(), Some(&swap_id[..]));
+    assert!(alice
+        .sign_adaptor_refund(
+            &mut alice_key_manager,
+            &alice_params,
+            &bob_params,
+            &core_with_metadata,
+            arb_params,
+        )
+        .is_ok());
+    assert!(matches!(
+        alice.sign_adaptor_refund(
+            &mut alice_key_manager,
+            &alice_params,
+            &bob_params,
+            &core_with_metadata,
+            deal.to_arbitrating_params(),
+        ),
+        Err(Error::Transaction(
+            farcaster_core::transaction::Error::WrongTemplate(_)
+        ))
+    ));
+
     funding.update(funding_tx).unwrap();
 
     //
@@ -0,0 +1,233 @@
+// Copyright 2021-2022 Farcaster Devs
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; either
+// version 3 of the License, or (at your option) any later version.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301, USA
+
+//! Property based round-trip tests for the protocol messages: any generated message must decode
+//! back to an equal value after being consensus encoded.
+
+use farcaster_core::consensus::{deserialize, serialize};
+use farcaster_core::crypto::{KeccakCommitment, SharedKeyId, TaggedElements};
+use farcaster_core::protocol::message::*;
+use farcaster_core::swap::SwapId;
+
+use bitcoin::secp256k1::{PublicKey as BPub, Secp256k1, SecretKey as BPriv};
+use bitcoin::Address;
+
+use curve25519_dalek::scalar::Scalar;
+use monero::{PrivateKey as MPriv, PublicKey as MPub};
+
+use proptest::prelude::*;
+
+use std::hash::Hash;
+
+type RevealAlice = RevealAliceParameters<BPub, MPub, BPriv, MPriv, Address>;
+type RevealBob = RevealBobParameters<BPub, MPub, BPriv, MPriv, Address>;
+
+fn swap_id() -> impl Strategy<Value = SwapId> {
+    any::<[u8; 32]>().prop_map(SwapId::from)
+}
+
+fn commitment() -> impl Strategy<Value = KeccakCommitment> {
+    any::<[u8; 32]>().prop_map(KeccakCommitment::new)
+}
+
+fn btc_secret_key() -> impl Strategy<Value = BPriv> {
+    any::<[u8; 32]>().prop_filter_map("invalid secp256k1 scalar", |b| BPriv::from_slice(&b).ok())
+}
+
+fn btc_public_key() -> impl Strategy<Value = BPub> {
+    btc_secret_key().prop_map(|sk| BPub::from_secret_key(&Secp256k1::signing_only(), &sk))
+}
+
+fn xmr_private_key() -> impl Strategy<Value = MPriv> {
+    any::<[u8; 32]>().prop_map(|b| MPriv::from_scalar(Scalar::from_bytes_mod_order(b)))
+}
+
+fn xmr_public_key() -> impl Strategy<Value = MPub> {
+    xmr_private_key().prop_map(|sk| MPub::from_private_key(&sk))
+}
+
+fn address() -> impl Strategy<Value = Address> {
+    btc_public_key().prop_map(|pk| {
+        Address::p2wpkh(&bitcoin::PublicKey::new(pk), bitcoin::Network::Bitcoin)
+            .expect("compressed key")
+    })
+}
+
+fn tagged<T, E>(
+    tag: impl Strategy<Value = T>,
+    elem: impl Strategy<Value = E>,
+) -> impl Strategy<Value = TaggedElements<T, E>>
+where
+    T: Hash + Eq + Clone + std::fmt::Debug,
+    E: Clone + std::fmt::Debug,
+{
+    prop::collection::hash_map(tag, elem, 0..4).prop_map(|elems| {
+        let mut tagged = TaggedElements::new();
+        for (tag, elem) in elems {
+            tagged.insert(tag, elem).expect("unique tags");
+        }
+        tagged
+    })
+}
+
+fn shared_key_id() -> impl Strategy<Value = SharedKeyId> {
+    any::<u16>().prop_map(SharedKeyId::new)
+}
+
+prop_compose! {
+    fn commit_alice()(
+        swap_id in swap_id(),
+        keys in prop::array::uniform6(commitment()),
+        extra_arbitrating_keys in tagged(any::<u16>(), commitment()),
+        arbitrating_shared_keys in tagged(shared_key_id(), commitment()),
+        extra_accordant_keys in tagged(any::<u16>(), commitment()),
+        accordant_shared_keys in tagged(shared_key_id(), commitment()),
+    ) -> CommitAliceParameters<KeccakCommitment> {
+        let [buy, cancel, refund, punish, adaptor, spend] = keys;
+        CommitAliceParameters {
+            swap_id,
+            buy,
+            cancel,
+            refund,
+            punish,
+            adaptor,
+            extra_arbitrating_keys,
+            arbitrating_shared_keys,
+            spend,
+            extra_accordant_keys,
+            accordant_shared_keys,
+        }
+    }
+}
+
+prop_compose! {
+    fn commit_bob()(
+        swap_id in swap_id(),
+        keys in prop::array::uniform5(commitment()),
+        extra_arbitrating_keys in tagged(any::<u16>(), commitment()),
+        arbitrating_shared_keys in tagged(shared_key_id(), commitment()),
+        extra_accordant_keys in tagged(any::<u16>(), commitment()),
+        accordant_shared_keys in tagged(shared_key_id(), commitment()),
+    ) -> CommitBobParameters<KeccakCommitment> {
+        let [buy, cancel, refund, adaptor, spend] = keys;
+        CommitBobParameters {
+            swap_id,
+            buy,
+            cancel,
+            refund,
+            adaptor,
+            extra_arbitrating_keys,
+            arbitrating_shared_keys,
+            spend,
+            extra_accordant_keys,
+            accordant_shared_keys,
+        }
+    }
+}
+
+prop_compose! {
+    fn reveal_alice()(
+        swap_id in swap_id(),
+        keys in prop::array::uniform5(btc_public_key()),
+        extra_arbitrating_keys in tagged(any::<u16>(), btc_public_key()),
+        arbitrating_shared_keys in tagged(shared_key_id(), btc_secret_key()),
+        spend in xmr_public_key(),
+        extra_accordant_keys in tagged(any::<u16>(), xmr_public_key()),
+        accordant_shared_keys in tagged(shared_key_id(), xmr_private_key()),
+        address in address(),
+    ) -> RevealAlice {
+        let [buy, cancel, refund, punish, adaptor] = keys;
+        RevealAliceParameters {
+            swap_id,
+            buy,
+            cancel,
+            refund,
+            punish,
+            adaptor,
+            extra_arbitrating_keys,
+            arbitrating_shared_keys,
+            spend,
+            extra_accordant_keys,
+            accordant_shared_keys,
+            address,
+        }
+    }
+}
+
+prop_compose! {
+    fn reveal_bob()(
+        swap_id in swap_id(),
+        keys in prop::array::uniform4(btc_public_key()),
+        extra_arbitrating_keys in tagged(any::<u16>(), btc_public_key()),
+        arbitrating_shared_keys in tagged(shared_key_id(), btc_secret_key()),
+        spend in xmr_public_key(),
+        extra_accordant_keys in tagged(any::<u16>(), xmr_public_key()),
+        accordant_shared_keys in tagged(shared_key_id(), xmr_private_key()),
+        address in address(),
+    ) -> RevealBob {
+        let [buy, cancel, refund, adaptor] = keys;
+        RevealBobParameters {
+            swap_id,
+            buy,
+            cancel,
+            refund,
+            adaptor,
+            extra_arbitrating_keys,
+            arbitrating_shared_keys,
+            spend,
+            extra_accordant_keys,
+            accordant_shared_keys,
+            address,
+        }
+    }
+}
+
+proptest! {
+    #[test]
+    fn commit_alice_parameters_roundtrip(msg in commit_alice()) {
+        let decoded: CommitAliceParameters<KeccakCommitment> = deserialize(&serialize(&msg)).unwrap();
+        prop_assert_eq!(decoded, msg);
+    }
+
+    #[test]
+    fn commit_bob_parameters_roundtrip(msg in commit_bob()) {
+        let decoded: CommitBobParameters<KeccakCommitment> = deserialize(&serialize(&msg)).unwrap();
+        prop_assert_eq!(decoded, msg);
+    }
+
+    #[test]
+    fn reveal_alice_parameters_roundtrip(msg in reveal_alice()) {
+        let decoded: RevealAlice = deserialize(&serialize(&msg)).unwrap();
+        prop_assert_eq!(decoded, msg);
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn reveal_alice_parameters_json_roundtrip(msg in reveal_alice()) {
+        let json = serde_json::to_string(&msg).unwrap();
+        // Keys are rendered as their hex encoded canonical bytes
+        let buy = format!("\"buy\":\"{}\"", hex::encode(msg.buy.serialize()));
+        prop_assert!(json.contains(&buy));
+        let decoded: RevealAlice = serde_json::from_str(&json).unwrap();
+        prop_assert_eq!(decoded, msg);
+    }
+
+    #[test]
+    fn reveal_bob_parameters_roundtrip(msg in reveal_bob()) {
+        let decoded: RevealBob = deserialize(&serialize(&msg)).unwrap();
+        prop_assert_eq!(decoded, msg);
+    }
+}
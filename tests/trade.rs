@@ -19,14 +19,17 @@ use farcaster_core::bitcoin::timelock::CSVTimelock;
 use farcaster_core::blockchain::Blockchain;
 
 use farcaster_core::blockchain::{FeeStrategy, Network};
-use farcaster_core::consensus::{self, deserialize, serialize_hex};
+use farcaster_core::consensus::{self, deserialize, serialize_hex, Encodable};
 use farcaster_core::role::SwapRole;
-use farcaster_core::trade::{Deal, DealFingerprint, DealParameters};
+use farcaster_core::trade::{self, Deal, DealFingerprint, DealParameters, PeerAddress, Version};
 
 use bitcoin::Amount;
 use inet2_addr::InetSocketAddr;
 use uuid::uuid;
 
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
 use std::str::FromStr;
 
 #[test]
@@ -45,6 +48,7 @@ fn create_deal_parameters() {
             punish_timelock: CSVTimelock::new(8),
             fee_strategy: FeeStrategy::Fixed(SatPerVByte::from_sat(9)),
             maker_role: SwapRole::Bob,
+            accordant_confirmations: 10,
         };
 
     assert_eq!(hex, serialize_hex(&deal));
@@ -84,6 +88,47 @@ fn get_deal_parameters_uuid() {
     assert_eq!(uuid!("67e55044-10b1-426f-9247-bb680e5fe0c8"), res.uuid());
 }
 
+#[test]
+fn create_deal_parameters_v2() {
+    let hex = "4450e567b1106f429247bb680e5fe0c802000000808000008008000500000000000000080006000000000000000400070000000400080000000\
+               108000900000000000000021400000000000000";
+    let deal: DealParameters<bitcoin::Amount, monero::Amount, CSVTimelock, SatPerVByte> =
+        DealParameters {
+            uuid: uuid!("67e55044-10b1-426f-9247-bb680e5fe0c8"),
+            network: Network::Testnet,
+            arbitrating_blockchain: Blockchain::Bitcoin,
+            accordant_blockchain: Blockchain::Monero,
+            arbitrating_amount: Amount::from_sat(5),
+            accordant_amount: monero::Amount::from_pico(6),
+            cancel_timelock: CSVTimelock::new(7),
+            punish_timelock: CSVTimelock::new(8),
+            fee_strategy: FeeStrategy::Fixed(SatPerVByte::from_sat(9)),
+            maker_role: SwapRole::Bob,
+            accordant_confirmations: 20,
+        };
+    assert_eq!(deal.min_version(), Version::new_v2());
+
+    let mut ser = vec![];
+    deal.consensus_encode_versioned(&Version::new_v2(), &mut ser)
+        .unwrap();
+    assert_eq!(hex, hex::encode(&ser));
+    let res =
+        DealParameters::consensus_decode_versioned(&mut &ser[..], &Version::new_v2()).unwrap();
+    assert_eq!(&deal, &res);
+
+    let id = DealFingerprint::from_str(
+        "937160a9cb7bfee3c22e764b2104a9377ecd86e5dfd89f3aeb2eeae373cc10f8",
+    )
+    .unwrap();
+    assert_eq!(id, deal.fingerprint());
+
+    // The version 1 encoding cannot carry the confirmations
+    assert!(deal
+        .consensus_encode_versioned(&Version::new_v1(), &mut vec![])
+        .is_err());
+    assert!(deal.consensus_encode(&mut vec![]).is_err());
+}
+
 #[test]
 fn serialize_deal() {
     let hex = "46435357415001004450e567b1106f429247bb680e5fe0c80200000080800000800800a08601000\
@@ -102,6 +147,7 @@ fn serialize_deal() {
             punish_timelock: CSVTimelock::new(10),
             fee_strategy: FeeStrategy::Fixed(SatPerVByte::from_sat(20)),
             maker_role: SwapRole::Bob,
+            accordant_confirmations: 10,
         };
     let ip = FromStr::from_str("0.0.0.0").unwrap();
     let port = FromStr::from_str("9735").unwrap();
@@ -114,7 +160,7 @@ fn serialize_deal() {
     .inner;
     let node_id = secp256k1::PublicKey::from_secret_key(&secp, &sk);
     let peer_address = InetSocketAddr::socket(ip, port);
-    let deal = deal.to_v1(node_id, peer_address);
+    let deal = deal.to_v1(node_id, peer_address).unwrap();
 
     assert_eq!(hex, serialize_hex(&deal));
     let strict_ser = strict_encoding::strict_serialize(&deal).unwrap();
@@ -124,6 +170,75 @@ fn serialize_deal() {
     assert_eq!(&deal, &res);
 }
 
+fn build_deal(address: &str) -> Deal<bitcoin::Amount, monero::Amount, CSVTimelock, SatPerVByte> {
+    let deal_parameters = DealParameters {
+        uuid: uuid!("67e55044-10b1-426f-9247-bb680e5fe0c8"),
+        network: Network::Testnet,
+        arbitrating_blockchain: Blockchain::Bitcoin,
+        accordant_blockchain: Blockchain::Monero,
+        arbitrating_amount: Amount::from_sat(100000),
+        accordant_amount: monero::Amount::from_pico(200),
+        cancel_timelock: CSVTimelock::new(10),
+        punish_timelock: CSVTimelock::new(10),
+        fee_strategy: FeeStrategy::Fixed(SatPerVByte::from_sat(20)),
+        maker_role: SwapRole::Bob,
+        accordant_confirmations: 10,
+    };
+    let secp = secp256k1::Secp256k1::new();
+    let sk = bitcoin::util::key::PrivateKey::from_wif(
+        "L1HKVVLHXiUhecWnwFYF6L3shkf1E12HUmuZTESvBXUdx3yqVP1D",
+    )
+    .unwrap()
+    .inner;
+    let node_id = secp256k1::PublicKey::from_secret_key(&secp, &sk);
+    let peer_address = InetSocketAddr::socket(
+        FromStr::from_str(address).unwrap(),
+        FromStr::from_str("9735").unwrap(),
+    );
+    deal_parameters.to_v2(node_id, peer_address)
+}
+
+#[test]
+fn deduplicate_identical_deals() {
+    let deal = build_deal("1.2.3.4");
+    let same_deal = build_deal("1.2.3.4");
+    let other_peer = build_deal("5.6.7.8");
+
+    let hash = |deal: &Deal<bitcoin::Amount, monero::Amount, CSVTimelock, SatPerVByte>| {
+        let mut hasher = DefaultHasher::new();
+        deal.hash(&mut hasher);
+        hasher.finish()
+    };
+    assert_eq!(deal, same_deal);
+    assert_eq!(hash(&deal), hash(&same_deal));
+    // The peer metadata is part of the deal identity
+    assert_ne!(deal, other_peer);
+    assert_ne!(deal.fingerprint(), other_peer.fingerprint());
+
+    let deals: HashSet<_> = vec![deal, same_deal, other_peer].into_iter().collect();
+    assert_eq!(deals.len(), 2);
+}
+
+#[test]
+fn deal_parameters_fingerprint() {
+    let deal = build_deal("1.2.3.4");
+    let fingerprint = deal.parameters_fingerprint();
+    // Independent of the peer address and stable across serialization
+    assert_eq!(fingerprint, build_deal("5.6.7.8").parameters_fingerprint());
+    let res: Deal<bitcoin::Amount, monero::Amount, CSVTimelock, SatPerVByte> =
+        deserialize(&consensus::serialize(&deal)).unwrap();
+    assert_eq!(fingerprint, res.parameters_fingerprint());
+    // Changing the amount changes the fingerprint
+    let mut other = build_deal("1.2.3.4");
+    other.parameters.arbitrating_amount = Amount::from_sat(100001);
+    assert_ne!(fingerprint, other.parameters_fingerprint());
+
+    let s = fingerprint.to_bech32();
+    assert!(s.starts_with("deal1"));
+    assert_eq!(DealFingerprint::from_bech32(&s).unwrap(), fingerprint);
+    assert!(DealFingerprint::from_bech32(&s[..s.len() - 1]).is_err());
+}
+
 #[test]
 fn get_deal_fingerprint() {
     let hex = "46435357415001004450e567b1106f429247bb680e5fe0c80200000080800000800800a08601000\
@@ -198,3 +313,276 @@ fn parse_deal() {
         assert!(res.is_ok());
     }
 }
+
+#[test]
+fn deal_advertising_onion_service() {
+    const ONION: &str = "vww6ybal4bd7szmgncyruucpgfkqahzddi37ktceo3ah7ngmcopnpyyd.onion";
+    let peer = PeerAddress::from_str(ONION).unwrap();
+    assert!(peer.is_onion());
+    assert_eq!(peer.to_string(), ONION);
+    assert_eq!(PeerAddress::onion_v3(&ONION[..56]).unwrap(), peer);
+
+    let mut deal = build_deal("1.2.3.4");
+    deal.peer_address = peer.into();
+    let encoded = strict_encoding::strict_serialize(&deal).unwrap();
+    let decoded: Deal<bitcoin::Amount, monero::Amount, CSVTimelock, SatPerVByte> =
+        strict_encoding::strict_deserialize(&encoded).unwrap();
+    assert_eq!(decoded, deal);
+    let parsed: Deal<bitcoin::Amount, monero::Amount, CSVTimelock, SatPerVByte> =
+        Deal::from_str(&deal.to_string()).unwrap();
+    assert_eq!(PeerAddress::from(parsed.peer_address).to_string(), ONION);
+
+    let socket = PeerAddress::from_str("1.2.3.4:9735").unwrap();
+    assert!(!socket.is_onion());
+    assert_eq!(socket.to_string(), "1.2.3.4:9735");
+    assert_eq!(
+        InetSocketAddr::from(socket),
+        build_deal("1.2.3.4").peer_address
+    );
+
+    // Bad checksum, truncated, and port suffixed onion addresses are rejected
+    for onion in [
+        "aww6ybal4bd7szmgncyruucpgfkqahzddi37ktceo3ah7ngmcopnpyyd.onion",
+        "vww6ybal4bd7szmgncyruucpgfkqahzddi37ktceo3ah7ngmcopnpyy.onion",
+        "vww6ybal4bd7szmgncyruucpgfkqahzddi37ktceo3ah7ngmcopnpyyd.onion:9735",
+    ] {
+        assert!(matches!(
+            PeerAddress::from_str(onion),
+            Err(trade::Error::InvalidOnionAddress(_) | trade::Error::InvalidPeerAddress(_))
+        ));
+    }
+    assert!(matches!(
+        PeerAddress::from_str("vww6ybal4bd7szmgncyruucpgfkqahzddi37ktceo3ah7ngmcopnpyyd"),
+        Err(trade::Error::InvalidPeerAddress(_))
+    ));
+}
+
+#[test]
+fn validate_deal_timelocks() {
+    let mut deal = build_deal("1.2.3.4").parameters;
+    let min = CSVTimelock::new(4);
+
+    // Equal timelocks leave the punish path reachable as soon as the refund path
+    assert!(matches!(
+        deal.validate_timelocks(&min),
+        Err(trade::Error::UnorderedTimelocks)
+    ));
+
+    deal.punish_timelock = CSVTimelock::new(20);
+    assert!(deal.validate_timelocks(&min).is_ok());
+    assert!(matches!(
+        deal.validate_timelocks(&CSVTimelock::new(15)),
+        Err(trade::Error::TimelockTooShort("cancel"))
+    ));
+
+    // Inverted pair
+    deal.cancel_timelock = CSVTimelock::new(20);
+    deal.punish_timelock = CSVTimelock::new(10);
+    assert!(matches!(
+        deal.validate_timelocks(&min),
+        Err(trade::Error::UnorderedTimelocks)
+    ));
+    assert!(matches!(
+        deal.validate_timelocks(&CSVTimelock::new(15)),
+        Err(trade::Error::TimelockTooShort("punish"))
+    ));
+}
+
+#[test]
+fn decode_deal_names_invalid_field() {
+    let bytes = consensus::serialize(&build_deal("1.2.3.4"));
+    let decode_corrupted = |corrupt: &dyn Fn(&mut Vec<u8>)| {
+        let mut corrupted = bytes.clone();
+        corrupt(&mut corrupted);
+        deserialize::<Deal<bitcoin::Amount, monero::Amount, CSVTimelock, SatPerVByte>>(&corrupted)
+    };
+    let check = |corrupt: &dyn Fn(&mut Vec<u8>), expected: &str| match decode_corrupted(corrupt) {
+        Err(consensus::Error::InvalidField { field, .. }) => assert_eq!(field, expected),
+        res => panic!("expected invalid {}, got {:?}", expected, res),
+    };
+
+    // magic bytes (6), version (2), uuid (16), then the network
+    check(&|b| b[24] = 0xff, "network");
+    check(
+        &|b| b[25..29].copy_from_slice(&[0xff; 4]),
+        "arbitrating blockchain",
+    );
+    check(
+        &|b| b[29..33].copy_from_slice(&[0xff; 4]),
+        "accordant blockchain",
+    );
+    // amounts are 8 bytes prefixed by their 2 bytes length
+    check(
+        &|b| b[35..43].copy_from_slice(&[0; 8]),
+        "arbitrating amount",
+    );
+    check(&|b| b[45..53].copy_from_slice(&[0; 8]), "accordant amount");
+    // timelocks are 4 bytes prefixed by their 2 bytes length
+    check(&|b| b[53] = 0x03, "cancel timelock");
+    check(&|b| b[59] = 0x03, "punish timelock");
+    check(&|b| b[65] = 0xff, "fee strategy");
+    check(&|b| b[76] = 0xff, "maker role");
+    check(
+        &|b| b[77..85].copy_from_slice(&[0; 8]),
+        "accordant confirmations",
+    );
+    // node id is 33 bytes prefixed by its 2 bytes length
+    check(&|b| b[87] = 0x05, "node id");
+    check(&|b| b.truncate(120), "peer address");
+
+    // the uncorrupted deal still decodes
+    assert!(decode_corrupted(&|_| ()).is_ok());
+}
+
+#[test]
+fn deal_accordant_confirmations() {
+    let deal = build_deal("1.2.3.4");
+    assert_eq!(deal.version, Version::new_v2());
+    assert_eq!(deal.parameters.accordant_confirmations, 10);
+
+    // Agreed confirmations round-trip in the consensus serialization of version 2 deals
+    let parameters = deal.parameters.clone().with_accordant_confs(20).unwrap();
+    assert_eq!(parameters.accordant_confirmations, 20);
+    let v2 = parameters.clone().to_v2(deal.node_id, deal.peer_address);
+    let res: Deal<bitcoin::Amount, monero::Amount, CSVTimelock, SatPerVByte> =
+        deserialize(&consensus::serialize(&v2)).unwrap();
+    assert_eq!(res.parameters.accordant_confirmations, 20);
+    assert_eq!(res, v2);
+
+    // Version 1 deals do not carry the field and only accept the default
+    assert!(matches!(
+        parameters.clone().to_v1(deal.node_id, deal.peer_address),
+        Err(trade::Error::ConfirmationsRequireV2(20))
+    ));
+    let mut invalid_v1 = v2.clone();
+    invalid_v1.version = Version::new_v1();
+    assert!(invalid_v1.consensus_encode(&mut vec![]).is_err());
+    let v1 = deal
+        .parameters
+        .clone()
+        .to_v1(deal.node_id, deal.peer_address)
+        .unwrap();
+    let bytes = consensus::serialize(&v1);
+    assert_eq!(bytes.len() + 8, consensus::serialize(&deal).len());
+    let res: Deal<bitcoin::Amount, monero::Amount, CSVTimelock, SatPerVByte> =
+        deserialize(&bytes).unwrap();
+    assert_eq!(res, v1);
+    // The parameters fingerprint only covers the confirmations when they differ from the default
+    assert_eq!(
+        v1.parameters_fingerprint(),
+        deal.parameters
+            .clone()
+            .to_v2(deal.node_id, deal.peer_address)
+            .parameters_fingerprint()
+    );
+    assert_ne!(v1.parameters_fingerprint(), v2.parameters_fingerprint());
+
+    // Unknown versions are rejected
+    let mut v3 = v2.clone();
+    v3.version = Version::new(3);
+    let res: Result<Deal<bitcoin::Amount, monero::Amount, CSVTimelock, SatPerVByte>, _> =
+        deserialize(&consensus::serialize(&v3));
+    assert!(matches!(
+        res,
+        Err(consensus::Error::InvalidField {
+            field: "version",
+            ..
+        })
+    ));
+
+    // Confirmations below the network minimum are rejected when set and when decoded
+    assert_eq!(trade::min_accordant_confirmations(Network::Testnet), 10);
+    assert!(matches!(
+        parameters.clone().with_accordant_confs(9),
+        Err(trade::Error::NotEnoughConfirmations { min: 10, found: 9 })
+    ));
+    let mut below = v2.clone();
+    below.parameters.accordant_confirmations = 0;
+    assert!(below.parameters.validate_accordant_confirmations().is_err());
+    let res: Result<Deal<bitcoin::Amount, monero::Amount, CSVTimelock, SatPerVByte>, _> =
+        deserialize(&consensus::serialize(&below));
+    assert!(matches!(
+        res,
+        Err(consensus::Error::InvalidField {
+            field: "accordant confirmations",
+            ..
+        })
+    ));
+
+    // Local networks accept a single confirmation
+    let mut local = parameters;
+    local.network = Network::Local;
+    let local = local
+        .with_accordant_confs(1)
+        .unwrap()
+        .to_v2(deal.node_id, deal.peer_address);
+    let res: Deal<bitcoin::Amount, monero::Amount, CSVTimelock, SatPerVByte> =
+        deserialize(&consensus::serialize(&local)).unwrap();
+    assert_eq!(res, local);
+}
+
+#[test]
+fn deal_rejects_none_fee_strategy() {
+    let mut deal = build_deal("1.2.3.4");
+    assert!(deal.parameters.validate_fee_strategy().is_ok());
+
+    // Only the lock transaction carries an anchor output to pay the fee with a child transaction
+    deal.parameters.fee_strategy = FeeStrategy::None;
+    assert!(matches!(
+        deal.parameters.validate_fee_strategy(),
+        Err(trade::Error::UnsupportedFeeStrategy)
+    ));
+    let res: Result<Deal<bitcoin::Amount, monero::Amount, CSVTimelock, SatPerVByte>, _> =
+        deserialize(&consensus::serialize(&deal));
+    assert!(matches!(
+        res,
+        Err(consensus::Error::InvalidField {
+            field: "fee strategy",
+            ..
+        })
+    ));
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn deal_rejects_none_fee_strategy_in_yaml() {
+    let mut deal = build_deal("1.2.3.4");
+    deal.parameters.fee_strategy = FeeStrategy::None;
+    let s = serde_yaml::to_string(&deal).unwrap();
+    let res: Result<Deal<bitcoin::Amount, monero::Amount, CSVTimelock, SatPerVByte>, _> =
+        serde_yaml::from_str(&s);
+    assert!(res.is_err());
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn deal_accordant_confirmations_default_in_yaml() {
+    let s = "---\nuuid: 67e55044-10b1-426f-9247-bb680e5fe0c8\nnetwork: Testnet\narbitrating_blockchain: Bitcoin\naccordant_blockchain: Monero\narbitrating_amount: 0.00000005 BTC\naccordant_amount: 0.000000000006 XMR\ncancel_timelock: 7\npunish_timelock: 8\nfee_strategy:\n  Fixed: 9 satoshi/vByte\nmaker_role: Bob\n";
+    let res: DealParameters<bitcoin::Amount, monero::Amount, CSVTimelock, SatPerVByte> =
+        serde_yaml::from_str(s).unwrap();
+    assert_eq!(
+        res.accordant_confirmations,
+        trade::DEFAULT_ACCORDANT_CONFIRMATIONS
+    );
+
+    // Confirmations below the network minimum are rejected
+    let s = s.replace(
+        "maker_role: Bob\n",
+        "maker_role: Bob\naccordant_confirmations: 0\n",
+    );
+    let res: Result<DealParameters<bitcoin::Amount, monero::Amount, CSVTimelock, SatPerVByte>, _> =
+        serde_yaml::from_str(&s);
+    assert!(res.is_err());
+
+    // Version 1 deals cannot carry other confirmations than the default
+    let mut deal = build_deal("1.2.3.4");
+    deal.parameters.accordant_confirmations = 20;
+    let s = serde_yaml::to_string(&deal).unwrap();
+    let res: Deal<bitcoin::Amount, monero::Amount, CSVTimelock, SatPerVByte> =
+        serde_yaml::from_str(&s).unwrap();
+    assert_eq!(res, deal);
+    let s = s.replace("version: 2\n", "version: 1\n");
+    let res: Result<Deal<bitcoin::Amount, monero::Amount, CSVTimelock, SatPerVByte>, _> =
+        serde_yaml::from_str(&s);
+    assert!(res.is_err());
+}
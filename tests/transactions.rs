@@ -86,7 +86,9 @@ macro_rules! setup_txs {
         let fee = FeeStrategy::Fixed(SatPerVByte::from_sat(1));
         let politic = FeePriority::Low;
 
-        let mut lock = LockTx::initialize(&funding, datalock.clone(), target_amount).unwrap();
+        let mut lock = LockTx::initialize(&funding, datalock.clone(), target_amount, None).unwrap();
+        // Pay the lock fee from the funding according to the strategy
+        lock.as_partial_mut().set_fee(&fee, politic).unwrap();
 
         //
         // Create cancel tx
@@ -112,11 +114,11 @@ macro_rules! setup_txs {
         // Set the fees according to the given strategy
         refund.as_partial_mut().set_fee(&fee, politic).unwrap();
 
-        lock.verify_template(datalock.clone()).unwrap();
+        lock.verify_template(datalock.clone(), None, &fee).unwrap();
         cancel
-            .verify_template(datalock.clone(), datapunishablelock.clone())
+            .verify_template(datalock.clone(), datapunishablelock.clone(), &fee)
             .unwrap();
-        refund.verify_template(new_address.clone()).unwrap();
+        refund.verify_template(new_address.clone(), &fee).unwrap();
 
         //
         // Co-Sign refund
@@ -167,7 +169,7 @@ macro_rules! setup_txs {
         // Set the fees according to the given strategy
         buy.as_partial_mut().set_fee(&fee, politic).unwrap();
 
-        buy.verify_template(new_address.clone()).unwrap();
+        buy.verify_template(new_address.clone(), &fee).unwrap();
 
         //
         // Co-Sign buy